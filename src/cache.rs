@@ -0,0 +1,46 @@
+use crate::github_data_fetchers::GitMemory;
+use crate::kv_store::KvStore;
+use crate::pipeline_config::StageConfig;
+use chrono::{DateTime, Utc};
+
+/// A commit's content never changes, so its summary is keyed by SHA and by
+/// the stage, model and prompts (see [`PromptRegistry::fingerprint`]) that wrote it.
+///
+/// [`PromptRegistry::fingerprint`]: crate::prompts::PromptRegistry::fingerprint
+pub fn commit_key(stage: &StageConfig, prompts_hash: &str, sha: &str) -> String {
+    format!(
+        "analysis:{prompts_hash}:{}:{}:commit:{sha}",
+        stage.name, stage.model
+    )
+}
+
+/// Issue summaries stay valid until the issue is updated; they also depend
+/// on whose contributions the prompt was asked to emphasize.
+pub fn issue_key(
+    stage: &StageConfig,
+    prompts_hash: &str,
+    node_id: &str,
+    updated_at: &DateTime<Utc>,
+    target_person: Option<&str>,
+) -> String {
+    format!(
+        "analysis:{prompts_hash}:{}:{}:issue:{node_id}:{}:{}",
+        stage.name,
+        stage.model,
+        updated_at.timestamp(),
+        target_person.unwrap_or("")
+    )
+}
+
+pub fn get_memory(store: &dyn KvStore, key: &str) -> Option<GitMemory> {
+    store
+        .get(key)
+        .and_then(|val| serde_json::from_value::<GitMemory>(val).ok())
+}
+
+pub fn set_memory(store: &dyn KvStore, key: &str, gm: &GitMemory) {
+    match serde_json::to_value(gm) {
+        Ok(val) => store.set(key, val),
+        Err(e) => log::error!("failed to cache analysis under {key}: {e}"),
+    }
+}
@@ -0,0 +1,300 @@
+use crate::error::AnalyzerError;
+use crate::http::{HttpRequest, HttpResponse, HttpTransport, Method};
+use crate::llm::{ChatMessage, ChatRequest, LlmClient};
+use futures::future::LocalBoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const CASSETTE_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Passes every request on and keeps what came back.
+    Record,
+    /// Answers from the recording without touching the network.
+    Replay,
+}
+
+/// One GitHub request and its response. Request headers are not kept, so
+/// the cassette holds no credentials; `token=` query values are redacted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HttpExchange {
+    pub method: Method,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<String>,
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// One chat completion and the model's answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatExchange {
+    pub chat_id: String,
+    pub stage: String,
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub answer: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Tape {
+    version: u32,
+    http: Vec<HttpExchange>,
+    chats: Vec<ChatExchange>,
+}
+
+/// The GitHub and LLM traffic of a run, kept in a JSON file so a report can
+/// be reproduced later: record once against the live services, then replay
+/// after changing prompts or truncation and diff the reports.
+///
+/// A replayed request is matched by method, url and body (by model and
+/// messages for chats). Repeated identical requests get their recorded
+/// responses in order, the last one once they run out.
+pub struct Cassette {
+    path: PathBuf,
+    mode: CassetteMode,
+    tape: Mutex<Tape>,
+    played: Mutex<HashMap<String, usize>>,
+}
+
+impl Cassette {
+    /// Starts an empty recording, written to `path` by [`Cassette::save`].
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Cassette::new(path.into(), CassetteMode::Record, Tape::default())
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self, AnalyzerError> {
+        let path = path.into();
+        let text = fs::read_to_string(&path).map_err(|e| {
+            AnalyzerError::InvalidInput(format!("cannot read cassette {}: {e}", path.display()))
+        })?;
+        let tape = serde_json::from_str::<Tape>(&text)
+            .map_err(|e| AnalyzerError::decode(&format!("cassette {}", path.display()), e))?;
+        if tape.version != CASSETTE_VERSION {
+            return Err(AnalyzerError::InvalidInput(format!(
+                "cassette {} has version {}, expected {CASSETTE_VERSION}",
+                path.display(),
+                tape.version
+            )));
+        }
+        Ok(Cassette::new(path, CassetteMode::Replay, tape))
+    }
+
+    fn new(path: PathBuf, mode: CassetteMode, tape: Tape) -> Self {
+        Cassette {
+            path,
+            mode,
+            tape: Mutex::new(tape),
+            played: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn mode(&self) -> CassetteMode {
+        self.mode
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes what was recorded so far; replaying leaves the file alone.
+    pub fn save(&self) -> Result<(), AnalyzerError> {
+        if self.mode == CassetteMode::Replay {
+            return Ok(());
+        }
+        let text = {
+            let Ok(mut tape) = self.tape.lock() else {
+                return Err(AnalyzerError::InvalidInput(
+                    "cassette lock poisoned".to_string(),
+                ));
+            };
+            tape.version = CASSETTE_VERSION;
+            serde_json::to_string_pretty(&*tape)
+                .map_err(|e| AnalyzerError::decode("cassette", e))?
+        };
+        fs::write(&self.path, text).map_err(|e| {
+            AnalyzerError::InvalidInput(format!(
+                "cannot write cassette {}: {e}",
+                self.path.display()
+            ))
+        })
+    }
+
+    /// Wraps the transport of a [`GitHubClient`](crate::github_client::GitHubClient).
+    pub fn transport(self: &Arc<Self>, inner: Arc<dyn HttpTransport>) -> Arc<dyn HttpTransport> {
+        Arc::new(CassetteTransport {
+            cassette: self.clone(),
+            inner,
+        })
+    }
+
+    /// Wraps an LLM client. Replaying a chat that is not on the cassette,
+    /// e.g. after a prompt changed, asks `inner` and logs a warning.
+    pub fn llm<'a>(&'a self, inner: &'a dyn LlmClient) -> CassetteLlm<'a> {
+        CassetteLlm {
+            cassette: self,
+            inner,
+        }
+    }
+
+    /// The `n`th recorded match for `key`, where `n` counts earlier lookups.
+    fn next_index(&self, key: String, matches: &[usize]) -> Option<usize> {
+        let mut played = self.played.lock().ok()?;
+        let n = played.entry(key).or_insert(0);
+        let index = matches.get(*n).or(matches.last()).copied();
+        *n += 1;
+        index
+    }
+}
+
+pub struct CassetteTransport {
+    cassette: Arc<Cassette>,
+    inner: Arc<dyn HttpTransport>,
+}
+
+impl HttpTransport for CassetteTransport {
+    fn send<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+    ) -> LocalBoxFuture<'a, Result<HttpResponse, AnalyzerError>> {
+        Box::pin(async move {
+            let url = redact(&req.url);
+            let request_body = req
+                .body
+                .as_ref()
+                .map(|body| String::from_utf8_lossy(body).to_string());
+
+            if self.cassette.mode == CassetteMode::Record {
+                let res = self.inner.send(req).await?;
+                if let Ok(mut tape) = self.cassette.tape.lock() {
+                    tape.http.push(HttpExchange {
+                        method: req.method,
+                        url,
+                        request_body,
+                        status: res.status,
+                        headers: res.headers.clone(),
+                        body: String::from_utf8_lossy(&res.body).to_string(),
+                    });
+                }
+                return Ok(res);
+            }
+
+            let exchange = {
+                let Ok(tape) = self.cassette.tape.lock() else {
+                    return Err(AnalyzerError::Transport(
+                        "cassette lock poisoned".to_string(),
+                    ));
+                };
+                let matches = tape
+                    .http
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| e.method == req.method && e.url == url)
+                    .filter(|(_, e)| e.request_body == request_body)
+                    .map(|(i, _)| i)
+                    .collect::<Vec<usize>>();
+                let key = format!(
+                    "{:?} {url} {}",
+                    req.method,
+                    request_body.unwrap_or_default()
+                );
+                self.cassette
+                    .next_index(key, &matches)
+                    .map(|i| tape.http[i].clone())
+            };
+            match exchange {
+                Some(e) => Ok(HttpResponse {
+                    status: e.status,
+                    headers: e.headers,
+                    body: e.body.into_bytes(),
+                }),
+                None => Err(AnalyzerError::Transport(format!(
+                    "{url} is not on cassette {}",
+                    self.cassette.path.display()
+                ))),
+            }
+        })
+    }
+}
+
+pub struct CassetteLlm<'a> {
+    cassette: &'a Cassette,
+    inner: &'a dyn LlmClient,
+}
+
+impl CassetteLlm<'_> {
+    fn replayed(&self, req: &ChatRequest) -> Option<String> {
+        let tape = self.cassette.tape.lock().ok()?;
+        let matches = tape
+            .chats
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.model == req.model && c.messages == req.messages)
+            .map(|(i, _)| i)
+            .collect::<Vec<usize>>();
+        let key = serde_json::to_string(&(&req.model, &req.messages)).ok()?;
+        let index = self.cassette.next_index(key, &matches)?;
+        Some(tape.chats[index].answer.clone())
+    }
+}
+
+impl LlmClient for CassetteLlm<'_> {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        Box::pin(async move {
+            if self.cassette.mode == CassetteMode::Replay {
+                if let Some(answer) = self.replayed(req) {
+                    return Ok(answer);
+                }
+                log::warn!(
+                    "chat {} ({}) is not on cassette {}, asking the model",
+                    req.chat_id,
+                    req.stage,
+                    self.cassette.path.display()
+                );
+                return self.inner.chat(req).await;
+            }
+
+            let answer = self.inner.chat(req).await?;
+            if let Ok(mut tape) = self.cassette.tape.lock() {
+                tape.chats.push(ChatExchange {
+                    chat_id: req.chat_id.clone(),
+                    stage: req.stage.clone(),
+                    model: req.model.clone(),
+                    messages: req.messages.clone(),
+                    answer: answer.clone(),
+                });
+            }
+            Ok(answer)
+        })
+    }
+
+    fn over_budget(&self) -> bool {
+        self.inner.over_budget()
+    }
+
+    fn model_for(&self, req: &ChatRequest) -> String {
+        self.inner.model_for(req)
+    }
+}
+
+/// Replaces the values of `token=` parameters, which the fetchers append
+/// with `&` even to urls without a query.
+fn redact(url: &str) -> String {
+    url.split('&')
+        .map(|part| match part.split_once("token=") {
+            Some((before, _)) if before.is_empty() || before.ends_with('?') => {
+                format!("{before}token=REDACTED")
+            }
+            _ => part.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&")
+}
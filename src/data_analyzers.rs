@@ -1,18 +1,24 @@
 use std::fmt::format;
 
+use crate::cache;
+use crate::error::AnalyzerError;
+use crate::github_client::{GitHubClient, RATE_LIMIT_RESERVE};
 use crate::github_data_fetchers::*;
-use crate::octocrab_compat::{Comment, Issue};
+use crate::issue_lifecycle::IssueLifecycle;
+use crate::kv_store::KvStore;
+use crate::llm::{generate, LlmClient};
+use crate::notifier::Notifier;
+use crate::octocrab_compat::{Comment, Issue, IssueEvent, ReviewState};
+use crate::pipeline_config::{PipelineConfig, StageConfig};
+use crate::prompts::PromptRegistry;
+use crate::synthesis::SYNTHESIS_SCHEMA;
 use crate::utils::*;
 use chrono::{DateTime, Utc};
 use log;
-use openai_flows::{
-    self,
-    chat::{self, ChatOptions},
-    OpenAIFlows,
-};
 use serde::Deserialize;
+use std::collections::HashMap;
 
-pub async fn search_bing(bing_key: &str, query: &str) -> Option<String> {
+pub async fn search_bing(bing_key: &str, query: &str) -> Result<String, AnalyzerError> {
     #[derive(Debug, Clone, Deserialize)]
     struct QueryContext {
         originalQuery: String,
@@ -75,112 +81,92 @@ pub async fn search_bing(bing_key: &str, query: &str) -> Option<String> {
         encoded_query
     );
 
-    let url = http_req::uri::Uri::try_from(url_str.as_str()).unwrap();
+    let url = http_req::uri::Uri::try_from(url_str.as_str())
+        .map_err(|e| AnalyzerError::Transport(format!("invalid url {url_str}: {e}")))?;
     let mut writer = Vec::new();
 
-    match http_req::request::Request::new(&url)
+    let res = http_req::request::Request::new(&url)
         .method(http_req::request::Method::GET)
         .header("User-Agent", "flows-network connector")
         .header("Content-Type", "application/vnd.github.v3+json")
         .header("Ocp-Apim-Subscription-Key", &format!("{bing_key}"))
         .send(&mut writer)
-    {
-        Ok(res) => {
-            if !res.status_code().is_success() {
-                log::error!("Github http error {:?}", res.status_code());
-                return None;
-            };
-
-            match serde_json::from_slice::<SearchResponse>(&writer) {
-                Err(_e) => {
-                    log::error!("Error parsing SearchResponse: {:?}", _e);
-                    None
-                }
-                Ok(search_response) => {
-                    let out = search_response
-                        .webPages
-                        .value
-                        .iter()
-                        .map(|val| format!("webpage at {} states: {}", val.url, val.snippet))
-                        .collect::<Vec<String>>()
-                        .join("\n");
-
-                    Some(out)
-                }
-            }
-        }
-        Err(_e) => {
-            log::error!("Error getting response from Github: {:?}", _e);
-            None
-        }
-    }
-}
-
-pub async fn maybe_include_search_data(current_data: &str, search_data: &str) -> Option<String> {
-    let mut _openai = OpenAIFlows::new();
-    _openai.set_retry_times(2);
+        .map_err(|e| AnalyzerError::Transport(e.to_string()))?;
 
-    let sys_prompt = "Determine if the primary block of text data from definitive sources and the secondary block from search results correspond to the same user or project. If they do, compile the information into a comprehensive summary. If they don’t match, only summarize the primary block. Proceed based on your judgment, without explaining the matching process. Please respond in the following JSON format, leaving a field empty if no information is available, and make a concise summary of available information.";
-
-    let co = ChatOptions {
-        model: chat::ChatModel::GPT35Turbo,
-        system_prompt: Some(sys_prompt),
-        restart: true,
-        temperature: Some(0.7),
-        max_tokens: Some(700),
-        ..Default::default()
+    if !res.status_code().is_success() {
+        return Err(AnalyzerError::from_status(
+            u16::from(res.status_code()),
+            "api.bing.microsoft.com",
+        ));
     };
 
-    slack_flows::send_message_to_channel(
-        "ik8",
-        "ch_in",
-        format!(
-            "current_data: {}\n search_data: {}",
-            current_data.clone(),
-            search_data.clone()
-        ),
-    )
-    .await;
-    let usr_prompt = &format!(
-        r#"We have two blocks of text data for you to examine. The first block: `{}` comes from our verified databases, and the second block: `{}` is from various search results. Your task is to identify whether they are associated with the same user or project. If they are, provide a summary that combines the information, giving more emphasis to the data from the definitive sources and using the search data to supplement it. If not, simply summarize the first block. There's no need to explain your matching process, just proceed based on your analysis. Provide your response in the following JSON format, leaving a field blank if no information is present, and make a concise summary of the available data:
-    ```json{{
-        "MatchStatus": "",
-        "Summary": ""
-      }}
-      ```"#,
-        current_data, search_data
-    );
+    let search_response = serde_json::from_slice::<SearchResponse>(&writer)
+        .map_err(|e| AnalyzerError::decode("bing SearchResponse", e))?;
+    let out = search_response
+        .webPages
+        .value
+        .iter()
+        .map(|val| format!("webpage at {} states: {}", val.url, val.snippet))
+        .collect::<Vec<String>>()
+        .join("\n");
 
-    match _openai
-        .chat_completion("integrate_99", usr_prompt, &co)
-        .await
-    {
-        Ok(r) => {
-            slack_flows::send_message_to_channel("ik8", "ch_in", r.choice.clone()).await;
-            let input = r.choice;
-            let key_string = r#""Summary":"#.to_string();
-
-            if let Some(start) = input.find(&key_string) {
-                let value_start = start + key_string.len();
-                let value_end = match input[value_start..].find("\"") {
-                    Some(end) => end,
-                    None => input.len() - value_start,
-                };
+    Ok(out)
+}
 
-                let value = &input[value_start..value_start + value_end];
-                return Some(value.trim().to_string());
-            } else {
-                return None;
-            }
-        }
-        Err(_e) => {
-            log::error!("Error consolidating search data: {}", _e);
-            return None;
+pub async fn maybe_include_search_data(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    notifier: &dyn Notifier,
+    prompts: &PromptRegistry,
+    current_data: &str,
+    search_data: &str,
+) -> Result<String, AnalyzerError> {
+    let sys_prompt = &prompts.render("search_merge_system", &[])?;
+
+    notifier
+        .notify(
+            "ch_in",
+            &format!("current_data: {current_data}\n search_data: {search_data}"),
+        )
+        .await;
+    let usr_prompt = &prompts.render(
+        "search_merge_user",
+        &[("current_data", current_data), ("search_data", search_data)],
+    )?;
+
+    let req = stage
+        .request("integrate_99")
+        .with_system(sys_prompt)
+        .with_user(usr_prompt);
+    let input = generate(llm, &req, "consolidating search data").await?;
+
+    notifier.notify("ch_in", &input).await;
+    let key_string = r#""Summary":"#.to_string();
+
+    match input.find(&key_string) {
+        Some(start) => {
+            let value_start = start + key_string.len();
+            let value_end = match input[value_start..].find("\"") {
+                Some(end) => end,
+                None => input.len() - value_start,
+            };
+
+            let value = &input[value_start..value_start + value_end];
+            Ok(value.trim().to_string())
         }
+        None => Err(AnalyzerError::Llm(
+            "consolidated search data has no Summary field".to_string(),
+        )),
     }
 }
 
-pub async fn get_repo_info(github_token: &str, about_repo: &str) -> Option<String> {
+pub async fn get_repo_info(
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
+    about_repo: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Deserialize)]
     struct CommunityProfile {
         health_percentage: u16,
@@ -188,109 +174,89 @@ pub async fn get_repo_info(github_token: &str, about_repo: &str) -> Option<Strin
         readme: Option<String>,
         updated_at: Option<DateTime<Utc>>,
     }
-    let _openai = OpenAIFlows::new();
 
-    let community_profile_url = format!(
-        "https://api.github.com/repos/{}/community/profile",
-        about_repo
-    );
+    let community_profile_url = client.api_url(&format!("repos/{}/community/profile", about_repo));
 
     let mut description = String::new();
     let mut date = Utc::now().date_naive();
-    match github_http_fetch(&github_token, &community_profile_url).await {
-        Some(res) => match serde_json::from_slice::<CommunityProfile>(&res) {
-            Ok(profile) => {
-                description = profile
-                    .description
-                    .as_ref()
-                    .unwrap_or(&String::from(""))
-                    .to_string();
-                date = profile
-                    .updated_at
-                    .as_ref()
-                    .unwrap_or(&Utc::now())
-                    .date_naive();
-            }
-            Err(e) => log::error!("Error parsing Community Profile: {:?}", e),
-        },
-        None => log::error!(
-            "Error fetching Community Profile: {:?}",
-            community_profile_url
-        ),
+    match client
+        .get_json::<CommunityProfile>(&community_profile_url, "community profile")
+        .await
+    {
+        Ok(profile) => {
+            description = profile
+                .description
+                .as_ref()
+                .unwrap_or(&String::from(""))
+                .to_string();
+            date = profile
+                .updated_at
+                .as_ref()
+                .unwrap_or(&Utc::now())
+                .date_naive();
+        }
+        Err(AnalyzerError::Decode { context }) => {
+            log::error!("Error parsing Community Profile: {}", context)
+        }
+        Err(e) => return Err(e),
     }
 
     let mut payload = String::new();
-    match get_readme_owner_repo(github_token, about_repo).await {
-        Some(content) => {
-            let content = content.chars().take(20000).collect::<String>();
-            match analyze_readme(&content).await {
-                Some(summary) => payload = summary,
-                None => log::error!("Error parsing README.md: {}", about_repo),
-            }
-        }
-        None => log::error!("Error fetching README.md: {}", about_repo),
+    match get_readme_owner_repo(client, about_repo).await {
+        Ok(content) => match analyze_readme(llm, &config.readme, prompts, &content).await {
+            Ok(summary) => payload = summary,
+            Err(e) => log::error!("Error parsing README.md: {}: {}", about_repo, e),
+        },
+        Err(e) => log::error!("Error fetching README.md: {}: {}", about_repo, e),
     };
     if description.is_empty() && payload.is_empty() {
-        return None;
+        return Err(AnalyzerError::NotFound(format!(
+            "description or README of {about_repo}"
+        )));
     }
 
     if payload.is_empty() {
-        return Some(description);
+        Ok(description)
     } else {
-        return Some(payload);
+        Ok(payload)
     }
 }
-pub async fn get_repo_overview_by_scraper(github_token: &str, about_repo: &str) -> Option<String> {
-    let mut _openai = OpenAIFlows::new();
-    _openai.set_retry_times(2);
+/// Summarizes the repository's GitHub page as rendered by flows.network's scraper.
+#[cfg(feature = "flows")]
+pub async fn get_repo_overview_by_scraper(
+    _client: &GitHubClient,
+    llm: &dyn LlmClient,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
+    about_repo: &str,
+) -> Result<String, AnalyzerError> {
     let repo_home_url = format!("https://github.com/{}", about_repo);
 
-    let mut raw_text = String::new();
-    match web_scraper_flows::get_page_text(&repo_home_url).await {
-        Ok(page_text) => raw_text = page_text,
-        Err(_e) => {
-            log::error!("Error getting response from Github: {:?}", _e);
-
-            return None;
-        }
-    }
-
-    let raw_text = if raw_text.len() > 48_000 {
-        squeeze_fit_post_texts(&raw_text, 12_000, 0.7)
-    } else {
-        raw_text.to_string()
-    };
-
-    let sys_prompt = "Your task is to examine the textual content from a GitHub repo page, emphasizing the Header, About, Release, Contributors, Languages, and README sections. This process should be carried out objectively, focusing on factual information extraction from each segment. Avoid making subjective judgments or inferences. The data should be presented systematically, corresponding to each section. Please note, the provided text will be in a flattened format.";
+    let raw_text = web_scraper_flows::get_page_text(&repo_home_url)
+        .await
+        .map_err(|_e| AnalyzerError::NotFound(repo_home_url.clone()))?;
 
-    let co = ChatOptions {
-        model: chat::ChatModel::GPT35Turbo16K,
-        system_prompt: Some(sys_prompt),
-        restart: true,
-        temperature: Some(0.7),
-        max_tokens: Some(700),
-        ..Default::default()
-    };
+    let stage = &config.repo_overview;
+    let raw_text = stage.fit(&raw_text);
 
-    let usr_prompt = &format!("I’ve obtained a flattened text from a GitHub repo page and require analysis of the following sections: 1) Header, with data on Fork, Star, Issues, Pull Request, etc.; 2) About, containing project description, keywords, number of stars, watchers, and forks; 3) Release, with details on the latest release and total releases; 4) Contributors, showing the number of contributors; 5) Languages, displaying the language composition in the project, and 6) README, which is usually a body of text describing the project, please summarize README when presenting result. Please extract and present data from these sections individually. Here is the text: {}", raw_text);
+    let sys_prompt = &prompts.render("repo_overview_system", &[])?;
+    let usr_prompt = &prompts.render("repo_overview_user", &[("page_text", &raw_text)])?;
 
-    match _openai
-        .chat_completion("repo_overview_99", usr_prompt, &co)
-        .await
-    {
-        Ok(r) => return Some(r.choice),
-        Err(_e) => {
-            log::error!("Error summarizing meta data: {}", _e);
-            return None;
-        }
-    }
+    let req = stage
+        .request("repo_overview_99")
+        .with_system(sys_prompt)
+        .with_user(usr_prompt);
+    generate(llm, &req, "summarizing repo overview").await
 }
 
 pub async fn is_valid_owner_repo_integrated(
-    github_token: &str,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
     owner: &str,
     repo: &str,
-) -> Option<GitMemory> {
+) -> Result<GitMemory, AnalyzerError> {
     #[derive(Deserialize)]
     struct CommunityProfile {
         health_percentage: u16,
@@ -298,50 +264,46 @@ pub async fn is_valid_owner_repo_integrated(
         readme: Option<String>,
         updated_at: Option<DateTime<Utc>>,
     }
-    let _openai = OpenAIFlows::new();
 
-    let community_profile_url = format!(
-        "https://api.github.com/repos/{}/{}/community/profile",
-        owner, repo
-    );
+    let community_profile_url =
+        client.api_url(&format!("repos/{}/{}/community/profile", owner, repo));
 
     let mut description = String::new();
     let mut date = Utc::now().date_naive();
-    match github_http_fetch(&github_token, &community_profile_url).await {
-        Some(res) => match serde_json::from_slice::<CommunityProfile>(&res) {
-            Ok(profile) => {
-                description = profile
-                    .description
-                    .as_ref()
-                    .unwrap_or(&String::from(""))
-                    .to_string();
-                date = profile
-                    .updated_at
-                    .as_ref()
-                    .unwrap_or(&Utc::now())
-                    .date_naive();
-            }
-            Err(e) => log::error!("Error parsing Community Profile: {:?}", e),
-        },
-        None => log::error!(
-            "Error fetching Community Profile: {:?}",
-            community_profile_url
-        ),
+    match client
+        .get_json::<CommunityProfile>(&community_profile_url, "community profile")
+        .await
+    {
+        Ok(profile) => {
+            description = profile
+                .description
+                .as_ref()
+                .unwrap_or(&String::from(""))
+                .to_string();
+            date = profile
+                .updated_at
+                .as_ref()
+                .unwrap_or(&Utc::now())
+                .date_naive();
+        }
+        Err(AnalyzerError::Decode { context }) => {
+            log::error!("Error parsing Community Profile: {}", context)
+        }
+        Err(e) => return Err(e),
     }
 
     let mut payload = String::new();
-    match get_readme(github_token, owner, repo).await {
-        Some(content) => {
-            let content = content.chars().take(20000).collect::<String>();
-            match analyze_readme(&content).await {
-                Some(summary) => payload = summary,
-                None => log::error!("Error parsing README.md: {}/{}", owner, repo),
-            }
-        }
-        None => log::error!("Error fetching README.md: {}/{}", owner, repo),
+    match get_readme(client, owner, repo).await {
+        Ok(content) => match analyze_readme(llm, &config.readme, prompts, &content).await {
+            Ok(summary) => payload = summary,
+            Err(e) => log::error!("Error parsing README.md: {}/{}: {}", owner, repo, e),
+        },
+        Err(e) => log::error!("Error fetching README.md: {}/{}: {}", owner, repo, e),
     };
     if description.is_empty() && payload.is_empty() {
-        return None;
+        return Err(AnalyzerError::NotFound(format!(
+            "description or README of {owner}/{repo}"
+        )));
     }
 
     if description.is_empty() {
@@ -350,7 +312,7 @@ pub async fn is_valid_owner_repo_integrated(
         payload = description.clone();
     }
 
-    Some(GitMemory {
+    Ok(GitMemory {
         memory_type: MemoryType::Meta,
         name: format!("{}/{}", owner, repo),
         tag_line: description,
@@ -360,33 +322,91 @@ pub async fn is_valid_owner_repo_integrated(
     })
 }
 
+/// Why a `process_*` loop stopped before its last item.
+fn stop_reason(llm: &dyn LlmClient) -> &'static str {
+    match llm.over_budget() {
+        true => "token budget used up",
+        false => "GitHub rate limit nearly exhausted",
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn process_issues(
-    github_token: &str,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    store: &dyn KvStore,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
     inp_vec: Vec<Issue>,
+    timelines: &HashMap<u64, Vec<IssueEvent>>,
     target_person: Option<String>,
     _turbo: bool,
     is_sparce: bool,
     token: Option<String>,
-) -> Option<(String, usize, Vec<GitMemory>)> {
+) -> Result<(String, usize, Vec<GitMemory>), AnalyzerError> {
     let mut issues_summaries = String::new();
     let mut git_memory_vec = vec![];
+    let mut last_error = None;
+    let stage = match is_sparce {
+        true => &config.issue_long,
+        false => &config.issue,
+    };
+    let prompts_hash = &prompts.fingerprint(&["issue_system", "issue_user"]);
+
+    let concurrency = analysis_concurrency();
+    let results = map_bounded(inp_vec.iter().take(21), concurrency, |issue| {
+        let target_person = target_person.clone();
+        let token = token.clone();
+        async move {
+            let key = cache::issue_key(
+                stage,
+                prompts_hash,
+                &issue.node_id,
+                &issue.updated_at,
+                target_person.as_deref(),
+            );
+            if let Some(gm) = cache::get_memory(store, &key) {
+                return Some(Ok((format!("{} {}", issue.url, gm.payload), gm)));
+            }
+            if !client.has_budget(RATE_LIMIT_RESERVE + concurrency as u32) || llm.over_budget() {
+                return None;
+            }
+            let res = analyze_issue_integrated(
+                client,
+                llm,
+                stage,
+                prompts,
+                issue,
+                timelines.get(&issue.number).map_or(&[], Vec::as_slice),
+                target_person,
+                _turbo,
+                token,
+            )
+            .await;
+            if let Ok((_, gm)) = &res {
+                cache::set_memory(store, &key, gm);
+            }
+            Some(res)
+        }
+    })
+    .await;
 
-    for issue in &inp_vec {
-        match analyze_issue_integrated(
-            github_token,
-            issue,
-            target_person.clone(),
-            _turbo,
-            is_sparce,
-            token.clone(),
-        )
-        .await
-        {
+    for (issue, result) in inp_vec.iter().zip(results) {
+        match result {
             None => {
-                log::error!("Error analyzing issue: {:?}", issue.url.to_string());
+                log::warn!(
+                    "{}, stopping after {} issues",
+                    stop_reason(llm),
+                    git_memory_vec.len()
+                );
+                break;
+            }
+            Some(Err(e)) => {
+                log::error!("Error analyzing issue: {:?}: {}", issue.url.to_string(), e);
+                last_error = Some(e);
                 continue;
             }
-            Some((summary, gm)) => {
+            Some(Ok((summary, gm))) => {
                 issues_summaries.push_str(&format!("{} {}\n", gm.date, summary));
                 // slack_flows::send_message_to_channel("ik8", "ch_iss", gm.source_url.to_string())
                 //     .await;
@@ -401,58 +421,321 @@ pub async fn process_issues(
 
     let count = git_memory_vec.len();
     if count == 0 {
-        log::error!("No issues processed");
-        return None;
+        if let Some(e) = last_error {
+            log::error!("No issues processed");
+            return Err(e);
+        }
     }
-    Some((issues_summaries, count, git_memory_vec))
+    Ok((issues_summaries, count, git_memory_vec))
 }
-pub async fn analyze_readme(content: &str) -> Option<String> {
-    let _openai = OpenAIFlows::new();
 
-    let sys_prompt_1 = &format!(
-        "Your task is to objectively analyze a GitHub profile and the README of their project. Focus on extracting factual information about the features of the project, and its stated objectives. Avoid making judgments or inferring subjective value."
-    );
+pub async fn process_pull_requests(
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
+    inp_vec: Vec<PullRequest>,
+    target_person: Option<String>,
+    is_sparce: bool,
+) -> Result<(String, usize, Vec<GitMemory>), AnalyzerError> {
+    let mut pulls_summaries = String::new();
+    let mut git_memory_vec = vec![];
+    let mut last_error = None;
+    let stage = match is_sparce {
+        true => &config.pull_request_long,
+        false => &config.pull_request,
+    };
 
-    let co = ChatOptions {
-        model: chat::ChatModel::GPT35Turbo16K,
-        system_prompt: Some(sys_prompt_1),
-        restart: true,
-        temperature: Some(0.7),
-        max_tokens: Some(256),
-        ..Default::default()
+    let concurrency = analysis_concurrency();
+    let results = map_bounded(inp_vec.iter().take(21), concurrency, |pull| {
+        let target_person = target_person.clone();
+        async move {
+            if !client.has_budget(RATE_LIMIT_RESERVE + concurrency as u32) || llm.over_budget() {
+                return None;
+            }
+            Some(analyze_pull_request(client, llm, stage, prompts, pull, target_person).await)
+        }
+    })
+    .await;
+
+    for (pull, result) in inp_vec.iter().zip(results) {
+        match result {
+            None => {
+                log::warn!(
+                    "{}, stopping after {} pull requests",
+                    stop_reason(llm),
+                    git_memory_vec.len()
+                );
+                break;
+            }
+            Some(Err(e)) => {
+                log::error!("Error analyzing pull request: {}: {}", pull.html_url, e);
+                last_error = Some(e);
+                continue;
+            }
+            Some(Ok((summary, gm))) => {
+                pulls_summaries.push_str(&format!("{} {}\n", gm.date, summary));
+                git_memory_vec.push(gm);
+                if git_memory_vec.len() > 20 {
+                    break;
+                }
+            }
+        }
+    }
+
+    let count = git_memory_vec.len();
+    if count == 0 {
+        if let Some(e) = last_error {
+            log::error!("No pull requests processed");
+            return Err(e);
+        }
+    }
+    Ok((pulls_summaries, count, git_memory_vec))
+}
+
+pub async fn analyze_pull_request(
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
+    pull: &PullRequest,
+    target_person: Option<String>,
+) -> Result<(String, GitMemory), AnalyzerError> {
+    let pull_number = pull.number;
+    let pull_title = pull.title.to_string();
+    let author = &pull.author;
+
+    let diff_path = format!("repos/{}/pulls/{}", pull.repository, pull_number);
+    let diff = match client.get_diff(&diff_path).await {
+        Ok(diff) => diff,
+        Err(e) => {
+            log::error!(
+                "Error fetching diff for pull request {}: {}",
+                pull.html_url,
+                e
+            );
+            String::new()
+        }
     };
+    // The diff gets three fifths of the input budget, the review thread the rest.
+    let diff = stage.fit_to(&diff, stage.input_budget() * 3 / 5);
+
+    let mut review_thread = String::new();
+    let mut comments = client.paginate::<Comment>(
+        &format!(
+            "repos/{}/issues/{}/comments?per_page=100",
+            pull.repository, pull_number
+        ),
+        "pull request comments",
+    );
+    // Pages past what the stage can take would only be cut off again.
+    while stage.fits(&review_thread) {
+        match comments.next_page().await {
+            Ok(Some(comments_obj)) => {
+                for comment in &comments_obj {
+                    let comment_body = match &comment.body {
+                        Some(body) => squeeze_fit_remove_quoted(body, 200, 1.0),
+                        None => String::new(),
+                    };
+                    review_thread.push_str(&format!(
+                        "{} commented: {}\n",
+                        comment.user.login, comment_body
+                    ));
+                }
+            }
+            Ok(None) => break,
+            Err(e) => {
+                log::error!(
+                    "Error fetching comments for pull request {}: {}",
+                    pull.html_url,
+                    e
+                );
+                break;
+            }
+        }
+    }
+    let review_thread = stage.fit_to(&review_thread, stage.input_budget() * 2 / 5);
 
-    let content = if content.len() > 48_000 {
-        squeeze_fit_remove_quoted(&content, 9_000, 0.7)
-    } else {
-        content.to_string()
+    let pull_body = squeeze_fit_remove_quoted(&pull.body, 400, 0.7);
+    let merged_str = match pull.merged_at {
+        Some(merged_at) => format!("merged on {}", merged_at.date_naive()),
+        None => format!("state {}", pull.state.to_lowercase()),
     };
-    let usr_prompt_1 = &format!(
-        "Based on the profile and README provided: {content}, extract a concise summary detailing this project's factual significance in its domain, their areas of expertise, and the main features and goals of the project. Ensure the insights are objective and under 110 tokens."
+    let reviewers_str = match pull.reviewers.is_empty() {
+        true => "no reviewers".to_string(),
+        false => format!("reviewed by {}", pull.reviewers.join(", ")),
+    };
+    let linked_str = match pull.linked_issues.is_empty() {
+        true => String::new(),
+        false => format!(", closing {}", pull.linked_issues.join(", ")),
+    };
+    let all_text_from_pull = format!(
+        "User '{author}' opened pull request #{pull_number} titled '{pull_title}' ({merged_str}, +{} -{} in {} files, {reviewers_str}{linked_str}) with the following description: '{pull_body}'. Diff: {diff} Review thread: {review_thread}",
+        pull.additions, pull.deletions, pull.changed_files
     );
 
-    match _openai
-        .chat_completion(&format!("profile-99"), usr_prompt_1, &co)
-        .await
-    {
-        Ok(r) => Some(r.choice),
-        Err(e) => {
-            log::error!("Error summarizing meta data: {}", e);
-            None
+    let target_str = target_person
+        .clone()
+        .map_or("key participants".to_string(), |t| t.to_string());
+
+    let sys_prompt_1 = &prompts.render(
+        "pull_request_system",
+        &[("author", author), ("title", &pull_title)],
+    )?;
+    let usr_prompt_1 = &prompts.render(
+        "pull_request_user",
+        &[
+            ("pull_request", &all_text_from_pull),
+            ("target", &target_str),
+        ],
+    )?;
+
+    let req = stage
+        .request(&format!("pull_{pull_number}"))
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1);
+    let choice = generate(
+        llm,
+        &req,
+        &format!("generating pull request summary #{pull_number}"),
+    )
+    .await?;
+
+    let out = format!("{} {}", pull.html_url, choice);
+    let name = target_person.map_or(author.to_string(), |t| t.to_string());
+    let gm = GitMemory {
+        memory_type: MemoryType::PullRequest,
+        name,
+        tag_line: pull_title,
+        source_url: pull.html_url.to_string(),
+        payload: choice,
+        date: pull.created_at.date_naive(),
+    };
+
+    Ok((out, gm))
+}
+
+/// Summarizes review activity: who reviewed which pull requests, how the
+/// verdicts split between approvals and requested changes, and notable feedback.
+pub async fn analyze_reviews(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
+    reviews_vec: &[PullReviews],
+    target_person: Option<String>,
+) -> Result<String, AnalyzerError> {
+    let mut approved = 0;
+    let mut changes_requested = 0;
+    let mut commented = 0;
+    let mut who_reviewed_what = Vec::<String>::new();
+    let mut feedback = String::new();
+
+    for pull_reviews in reviews_vec {
+        let pull = &pull_reviews.pull;
+        for review in &pull_reviews.reviews {
+            let reviewer = review.user.as_ref().map_or("ghost", |u| u.login.as_str());
+            let verdict = match review.state {
+                Some(ReviewState::Approved) => {
+                    approved += 1;
+                    "approved"
+                }
+                Some(ReviewState::ChangesRequested) => {
+                    changes_requested += 1;
+                    "requested changes on"
+                }
+                Some(ReviewState::Commented) => {
+                    commented += 1;
+                    "commented on"
+                }
+                _ => continue,
+            };
+            who_reviewed_what.push(format!(
+                "{reviewer} {verdict} #{} '{}'",
+                pull.number, pull.title
+            ));
+            if let Some(body) = review.body.as_ref().filter(|b| !b.trim().is_empty()) {
+                let body = squeeze_fit_remove_quoted(body, 200, 1.0);
+                feedback.push_str(&format!("{reviewer} on #{}: {body}\n", pull.number));
+            }
         }
+        for comment in &pull_reviews.comments {
+            let reviewer = comment.user.as_ref().map_or("ghost", |u| u.login.as_str());
+            let body = squeeze_fit_remove_quoted(&comment.body, 200, 1.0);
+            feedback.push_str(&format!(
+                "{reviewer} on #{} {}: {body}\n",
+                pull.number, comment.path
+            ));
+        }
+    }
+
+    let total = approved + changes_requested + commented;
+    if total == 0 && feedback.is_empty() {
+        return Ok(String::new());
+    }
+    let ratio = |n: i32| match total {
+        0 => 0.0,
+        _ => n as f32 * 100.0 / total as f32,
+    };
+    let stats = format!(
+        "{total} reviews: {approved} approvals ({:.0}%), {changes_requested} changes requested ({:.0}%), {commented} comment-only ({:.0}%).",
+        ratio(approved),
+        ratio(changes_requested),
+        ratio(commented)
+    );
+    let who_reviewed_what = who_reviewed_what.join("; ");
+    if feedback.is_empty() {
+        return Ok(format!("{stats} {who_reviewed_what}"));
     }
+
+    let feedback = stage.fit(&feedback);
+    let target_str = target_person.map_or("the reviewers".to_string(), |t| t.to_string());
+
+    let sys_prompt_1 = &prompts.render("reviews_system", &[])?;
+    let usr_prompt_1 = &prompts.render(
+        "reviews_user",
+        &[("feedback", &feedback), ("target", &target_str)],
+    )?;
+
+    let req = stage
+        .request("reviews-99")
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1);
+    let feedback_summary = generate(llm, &req, "generating review summary").await?;
+    Ok(format!(
+        "{stats} {who_reviewed_what}\nNotable feedback: {feedback_summary}"
+    ))
 }
 
+pub async fn analyze_readme(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
+    content: &str,
+) -> Result<String, AnalyzerError> {
+    let content = stage.fit(content);
+    let sys_prompt_1 = &prompts.render("readme_system", &[])?;
+    let usr_prompt_1 = &prompts.render("readme_user", &[("readme", &content)])?;
+
+    let req = stage
+        .request("profile-99")
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1);
+    generate(llm, &req, "summarizing meta data").await
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn analyze_issue_integrated(
-    github_token: &str,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
     issue: &Issue,
+    timeline: &[IssueEvent],
     target_person: Option<String>,
     _turbo: bool,
-    is_sparce: bool,
     token: Option<String>,
-) -> Option<(String, GitMemory)> {
-    let _openai = OpenAIFlows::new();
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+) -> Result<(String, GitMemory), AnalyzerError> {
+    let bpe = bpe();
 
     let issue_creator_name = &issue.user.login;
     let issue_title = issue.title.to_string();
@@ -482,10 +765,14 @@ pub async fn analyze_issue_integrated(
         .collect::<Vec<String>>()
         .join(", ");
 
-    let all_text_from_issue = format!(
+    let mut all_text_from_issue = format!(
         "User '{}', opened an issue titled '{}', labeled '{}', with the following post: '{}'.",
         issue_creator_name, issue_title, labels, issue_body
     );
+    let lifecycle = IssueLifecycle::from_events(timeline).describe();
+    if !lifecycle.is_empty() {
+        all_text_from_issue.push_str(&format!(" {lifecycle} "));
+    }
     let mut all_text_tokens = bpe.encode_ordinary(&all_text_from_issue);
     let token_str = match token {
         None => String::new(),
@@ -496,42 +783,34 @@ pub async fn analyze_issue_integrated(
         issue_url, token_str
     );
 
-    match github_http_fetch(&github_token, &url_str).await {
-        Some(res) => match serde_json::from_slice::<Vec<Comment>>(res.as_slice()) {
-            Err(_e) => {
-                log::error!("Error parsing Vec<Comment> : {:?}", _e);
-            }
-            Ok(comments_obj) => {
-                for comment in &comments_obj {
-                    let comment_body = match &comment.body {
-                        Some(body) => {
-                            squeeze_fit_remove_quoted(body, 200, 1.0)
-
-                            // if is_sparce {
-                            //     squeeze_fit_remove_quoted(body, 300, 1.0)
-                            // } else {
-                            //     squeeze_fit_remove_quoted(body, 200, 1.0)
-                            // }
-                        }
-                        None => String::new(),
-                    };
-                    let commenter = &comment.user.login;
-                    let commenter_input = format!("{} commented: {}", commenter, comment_body);
-                    let mut commenter_token = bpe.encode_ordinary(&commenter_input);
-                    all_text_tokens.append(&mut commenter_token);
-                    if is_sparce {
-                        if all_text_tokens.len() > 12_000 {
-                            break;
-                        }
-                    } else {
-                        if all_text_tokens.len() > 3_000 {
-                            break;
-                        }
+    match client
+        .get_json::<Vec<Comment>>(&url_str, "issue comments")
+        .await
+    {
+        Err(e) => log::error!("Error fetching comments for issue: {:?}: {}", url_str, e),
+        Ok(comments_obj) => {
+            for comment in &comments_obj {
+                let comment_body = match &comment.body {
+                    Some(body) => {
+                        squeeze_fit_remove_quoted(body, 200, 1.0)
+
+                        // if is_sparce {
+                        //     squeeze_fit_remove_quoted(body, 300, 1.0)
+                        // } else {
+                        //     squeeze_fit_remove_quoted(body, 200, 1.0)
+                        // }
                     }
+                    None => String::new(),
+                };
+                let commenter = &comment.user.login;
+                let commenter_input = format!("{} commented: {}", commenter, comment_body);
+                let mut commenter_token = bpe.encode_ordinary(&commenter_input);
+                all_text_tokens.append(&mut commenter_token);
+                if all_text_tokens.len() > stage.input_budget() {
+                    break;
                 }
             }
-        },
-        None => log::error!("Error fetching comments for issue: {:?}", url_str),
+        }
     }
 
     let all_text_from_issue = bpe.decode(all_text_tokens).ok().unwrap_or(String::new());
@@ -540,288 +819,249 @@ pub async fn analyze_issue_integrated(
         .clone()
         .map_or("key participants".to_string(), |t| t.to_string());
 
-    let sys_prompt_1 = &format!(
-        "Given the information that user '{issue_creator_name}' opened an issue titled '{issue_title}', your task is to deeply analyze the content of the issue posts. Distill the crux of the issue, the potential solutions suggested, and evaluate the significant contributions of the participants in resolving or progressing the discussion."
-    );
-    let co = if is_sparce {
-        ChatOptions {
-            model: chat::ChatModel::GPT35Turbo16K,
-            system_prompt: Some(sys_prompt_1),
-            restart: true,
-            temperature: Some(0.7),
-            max_tokens: Some(128),
-            ..Default::default()
-        }
-    } else {
-        ChatOptions {
-            model: chat::ChatModel::GPT35Turbo,
-            system_prompt: Some(sys_prompt_1),
-            restart: true,
-            temperature: Some(0.7),
-            max_tokens: Some(128),
-            ..Default::default()
-        }
+    let sys_prompt_1 = &prompts.render(
+        "issue_system",
+        &[("author", issue_creator_name), ("title", &issue_title)],
+    )?;
+    let usr_prompt_1 = &prompts.render(
+        "issue_user",
+        &[("issue", &all_text_from_issue), ("target", &target_str)],
+    )?;
+
+    let req = stage
+        .request(&format!("issue_{issue_number}"))
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1);
+    let choice = generate(
+        llm,
+        &req,
+        &format!("generating issue summary #{issue_number}"),
+    )
+    .await?;
+
+    let out = format!("{} {}", issue_url, choice);
+    let name = target_person.map_or(issue_creator_name.to_string(), |t| t.to_string());
+    let gm = GitMemory {
+        memory_type: MemoryType::Issue,
+        name: name,
+        tag_line: issue_title,
+        source_url: source_url,
+        payload: choice,
+        date: issue_date,
     };
 
-    let usr_prompt_1 = &format!(
-        "Analyze the GitHub issue content: {all_text_from_issue}. Provide a concise analysis touching upon: The central problem discussed in the issue. The main solutions proposed or agreed upon. Emphasize the role and significance of '{target_str}' in contributing towards the resolution or progression of the discussion. Aim for a succinct, analytical summary that stays under 110 tokens."
-    );
-
-    match _openai
-        .chat_completion(&format!("issue_{issue_number}"), usr_prompt_1, &co)
-        .await
-    {
-        Ok(r) => {
-            let out = format!("{} {}", issue_url, r.choice);
-            let name = target_person.map_or(issue_creator_name.to_string(), |t| t.to_string());
-            let gm = GitMemory {
-                memory_type: MemoryType::Issue,
-                name: name,
-                tag_line: issue_title,
-                source_url: source_url,
-                payload: r.choice,
-                date: issue_date,
-            };
-
-            Some((out, gm))
-        }
-        Err(_e) => {
-            log::error!("Error generating issue summary #{}: {}", issue_number, _e);
-            None
-        }
-    }
+    Ok((out, gm))
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn analyze_commit_integrated(
-    github_token: &str,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
     user_name: &str,
     tag_line: &str,
     url: &str,
     _turbo: bool,
     is_sparce: bool,
     token: Option<String>,
-) -> Option<String> {
-    let _openai = OpenAIFlows::new();
-
+) -> Result<String, AnalyzerError> {
     let token_str = match token {
         None => String::new(),
         Some(t) => format!("&token={}", t.as_str()),
     };
 
     let commit_patch_str = format!("{url}.patch{token_str}");
-    let uri = http_req::uri::Uri::try_from(commit_patch_str.as_str())
-        .expect(&format!("Error generating URI from {:?}", commit_patch_str));
-    let mut writer = Vec::new();
-    match http_req::request::Request::new(&uri)
-        .method(http_req::request::Method::GET)
-        .header("User-Agent", "flows-network connector")
-        .header("Content-Type", "plain/text")
-        .header("Authorization", &format!("Bearer {github_token}"))
-        .send(&mut writer)
-    {
-        Ok(res) => {
-            if !res.status_code().is_success() {
-                log::error!("Github http error {:?}", res.status_code());
-                return None;
-            };
-
-            let text = String::from_utf8_lossy(writer.as_slice());
-            // let mut stripped_texts = String::with_capacity(text.len());
-
-            // 'commit_text_block: {
-            //     let lines_count = text.lines().count();
-            //     if lines_count > 150 {
-            //         stripped_texts = text
-            //             .splitn(2, "diff --git")
-            //             .nth(0)
-            //             .unwrap_or("")
-            //             .to_string();
-            //         break 'commit_text_block;
-            //     }
-
-            //     let mut inside_diff_block = false;
-
-            //     match is_sparce {
-            //         false => {
-            //             for line in text.lines() {
-            //                 if line.starts_with("diff --git") {
-            //                     inside_diff_block = true;
-            //                     stripped_texts.push_str(line);
-            //                     stripped_texts.push('\n');
-            //                     continue;
-            //                 }
-
-            //                 if inside_diff_block {
-            //                     if line
-            //                         .chars()
-            //                         .any(|ch| ch == '[' || ch == ']' || ch == '{' || ch == '}')
-            //                     {
-            //                         continue;
-            //                     }
-            //                 }
-
-            //                 stripped_texts.push_str(line);
-            //                 stripped_texts.push('\n');
-
-            //                 if line.is_empty() {
-            //                     inside_diff_block = false;
-            //                 }
-            //             }
-            //         }
-            //         true => stripped_texts = text.to_string(),
-            //     }
-            // }
-            // slack_flows::send_message_to_channel("ik8", "ch_rep", stripped_texts.clone()).await;
-
-            let sys_prompt_1 = &format!(
-                "Given a commit patch from user {user_name}, analyze its content. Focus on changes that substantively alter code or functionality. A good analysis prioritizes the commit message for clues on intent and refrains from overstating the impact of minor changes. Aim to provide a balanced, fact-based representation that distinguishes between major and minor contributions to the project. Keep your analysis concise."
-            );
+    let text = client.get_text(&commit_patch_str).await?;
 
-            let mut co: ChatOptions = ChatOptions {
-                model: chat::ChatModel::GPT35Turbo,
-                system_prompt: Some(sys_prompt_1),
-                restart: true,
-                temperature: Some(0.7),
-                max_tokens: Some(128),
-                ..Default::default()
-            };
+    let sys_prompt_1 = &prompts.render("commit_system", &[("user_name", user_name)])?;
 
-            let stripped_texts = if !is_sparce {
-                let stripped_texts = text
-                    .splitn(2, "diff --git")
-                    .nth(0)
-                    .unwrap_or("")
-                    .to_string();
-
-                let stripped_texts = squeeze_fit_remove_quoted(&stripped_texts, 5_000, 1.0);
-                squeeze_fit_post_texts(&stripped_texts, 3_000, 0.6)
-            } else {
-                co = ChatOptions {
-                    model: chat::ChatModel::GPT35Turbo16K,
-                    system_prompt: Some(sys_prompt_1),
-                    restart: true,
-                    temperature: Some(0.7),
-                    max_tokens: Some(128),
-                    ..Default::default()
-                };
-                text.chars().take(24_000).collect::<String>()
-            };
+    let stripped_texts = if !is_sparce {
+        let stripped_texts = text
+            .splitn(2, "diff --git")
+            .nth(0)
+            .unwrap_or("")
+            .to_string();
 
-            // let stripped_texts = if turbo {
-            //     squeeze_fit_post_texts(&stripped_texts, 3_000, 0.6)
-            // } else {
-            //     if stripped_texts.len() > 12_000 {
-            //         co = ChatOptions {
-            //             model: chat::ChatModel::GPT35Turbo16K,
-            //             system_prompt: Some(sys_prompt_1),
-            //             restart: true,
-            //             temperature: Some(0.7),
-            //             max_tokens: Some(128),
-            //             ..Default::default()
-            //         };
-            //     }
-            //     squeeze_fit_post_texts(&stripped_texts, 12_000, 0.6)
-            // };
-
-            let usr_prompt_1 = &format!(
-                "Analyze the commit patch: {stripped_texts}, and its description: {tag_line}. Summarize the main changes, but only emphasize modifications that directly affect core functionality. A good summary is fact-based, derived primarily from the commit message, and avoids over-interpretation. It recognizes the difference between minor textual changes and substantial code adjustments. Conclude by evaluating the realistic impact of {user_name}'s contributions in this commit on the project. Limit the response to 110 tokens."
-            );
+        let stripped_texts = squeeze_fit_remove_quoted(&stripped_texts, 5_000, 1.0);
+        stage.fit(&stripped_texts)
+    } else {
+        stage.fit(&text)
+    };
 
-            let sha_serial = match url.rsplitn(2, "/").nth(0) {
-                Some(s) => s.chars().take(5).collect::<String>(),
-                None => "0000".to_string(),
-            };
-            match _openai
-                .chat_completion(&format!("commit-{sha_serial}"), usr_prompt_1, &co)
-                .await
-            {
-                Ok(r) => {
-                    let out = format!("{} {}", url, r.choice);
-                    Some(out)
-                }
-                Err(_e) => {
-                    log::error!("Error generating issue summary #{}: {}", sha_serial, _e);
-                    None
-                }
-            }
-        }
-        Err(_e) => {
-            log::error!("Error getting response from Github: {:?}", _e);
-            None
-        }
-    }
+    let usr_prompt_1 = &prompts.render(
+        "commit_user",
+        &[
+            ("patch", &stripped_texts),
+            ("tag_line", tag_line),
+            ("user_name", user_name),
+        ],
+    )?;
+
+    let sha_serial = match url.rsplitn(2, "/").nth(0) {
+        Some(s) => s.chars().take(5).collect::<String>(),
+        None => "0000".to_string(),
+    };
+    let req = stage
+        .request(&format!("commit-{sha_serial}"))
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1);
+    generate(
+        llm,
+        &req,
+        &format!("generating commit summary #{sha_serial}"),
+    )
+    .await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn process_commits(
-    github_token: &str,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    store: &dyn KvStore,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
     inp_vec: &mut Vec<GitMemory>,
     _turbo: bool,
     is_sparce: bool,
     token: Option<String>,
-) -> Option<String> {
+) -> Result<String, AnalyzerError> {
     let mut commits_summaries = String::new();
     let mut processed_count = 0; // Number of processed entries
-
-    for commit_obj in inp_vec.iter_mut() {
-        match analyze_commit_integrated(
-            github_token,
-            &commit_obj.name,
-            &commit_obj.tag_line,
-            &commit_obj.source_url,
-            _turbo,
-            is_sparce,
-            token.clone(),
-        )
-        .await
-        {
-            Some(summary) => {
-                let len = commits_summaries.split_whitespace().count();
-                if len > 3000 {
-                    break;
+    let mut last_error = None;
+    let stage = match is_sparce {
+        true => &config.commit_long,
+        false => &config.commit,
+    };
+    let prompts_hash = &prompts.fingerprint(&["commit_system", "commit_user"]);
+
+    // Summaries are asked for a batch at a time so that no more than one
+    // batch is spent past the 3000 word cap.
+    let concurrency = analysis_concurrency();
+    'batches: for batch in inp_vec.chunks_mut(concurrency) {
+        if commits_summaries.split_whitespace().count() > 3000 {
+            break;
+        }
+        let results = map_bounded(batch.iter(), concurrency, |commit_obj| {
+            let token = token.clone();
+            async move {
+                // Commit html_urls end in the full SHA.
+                let sha = commit_obj.source_url.rsplit('/').next().unwrap_or_default();
+                let key = cache::commit_key(stage, prompts_hash, sha);
+                if let Some(gm) = cache::get_memory(store, &key) {
+                    return Some(Ok(gm.payload));
+                }
+                if !client.has_budget(RATE_LIMIT_RESERVE + concurrency as u32) || llm.over_budget()
+                {
+                    return None;
                 }
-                commits_summaries.push_str(&format!("{} {}\n", commit_obj.date, summary));
+                let res = analyze_commit_integrated(
+                    client,
+                    llm,
+                    stage,
+                    prompts,
+                    &commit_obj.name,
+                    &commit_obj.tag_line,
+                    &commit_obj.source_url,
+                    _turbo,
+                    is_sparce,
+                    token,
+                )
+                .await;
+                if let Ok(summary) = &res {
+                    cache::set_memory(
+                        store,
+                        &key,
+                        &GitMemory {
+                            payload: summary.clone(),
+                            ..commit_obj.clone()
+                        },
+                    );
+                }
+                Some(res)
+            }
+        })
+        .await;
 
-                processed_count += 1;
+        for (commit_obj, result) in batch.iter_mut().zip(results) {
+            if commits_summaries.split_whitespace().count() > 3000 {
+                break 'batches;
             }
-            None => {
-                log::error!(
-                    "Error analyzing commit {:?} for user {}",
-                    commit_obj.source_url,
-                    commit_obj.name
-                );
+            match result {
+                None => {
+                    log::warn!(
+                        "{}, stopping after {processed_count} commits",
+                        stop_reason(llm)
+                    );
+                    break 'batches;
+                }
+                Some(Ok(summary)) => {
+                    commits_summaries.push_str(&format!(
+                        "{} {} {}\n",
+                        commit_obj.date, commit_obj.source_url, summary
+                    ));
+                    commit_obj.payload = summary;
+
+                    processed_count += 1;
+                }
+                Some(Err(e)) => {
+                    log::error!(
+                        "Error analyzing commit {:?} for user {}: {}",
+                        commit_obj.source_url,
+                        commit_obj.name,
+                        e
+                    );
+                    last_error = Some(e);
+                }
             }
         }
     }
 
     if processed_count == 0 {
-        log::error!("No commits processed");
-        return None;
+        if let Some(e) = last_error {
+            log::error!("No commits processed");
+            return Err(e);
+        }
     }
 
-    Some(commits_summaries)
+    Ok(commits_summaries)
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn correlate_commits_issues_discussions(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
     _profile_data: Option<&str>,
     _commits_summary: Option<&str>,
     _issues_summary: Option<&str>,
+    _pulls_summary: Option<&str>,
+    _reviews_summary: Option<&str>,
     _discussions_summary: Option<&str>,
+    _links: Option<&str>,
     target_person: Option<&str>,
+    period: &str,
     total_input_entry_count: u16,
-) -> Option<String> {
-    let total_space = 16000; // 16k tokens
+) -> Result<String, AnalyzerError> {
+    let total_space = stage.input_budget();
 
-    let _total_ratio = 11.0; // 1 + 4 + 4 + 2
+    let _total_ratio = 18.0; // 1 + 4 + 4 + 4 + 2 + 2 + 1
     let profile_ratio = 1.0;
     let commit_ratio = 4.0;
     let issue_ratio = 4.0;
+    let pull_ratio = 4.0;
+    let review_ratio = 2.0;
     let discussion_ratio = 2.0;
+    let link_ratio = 1.0;
 
     let available_ratios = [
         _profile_data.map(|_| profile_ratio),
         _commits_summary.map(|_| commit_ratio),
         _issues_summary.map(|_| issue_ratio),
+        _pulls_summary.map(|_| pull_ratio),
+        _reviews_summary.map(|_| review_ratio),
         _discussions_summary.map(|_| discussion_ratio),
+        _links.map(|_| link_ratio),
     ];
 
     let total_available_ratio: f32 = available_ratios.iter().filter_map(|&x| x).sum();
@@ -832,10 +1072,12 @@ pub async fn correlate_commits_issues_discussions(
     let profile_space = _profile_data.map_or(0, |_| compute_space(profile_ratio));
     let commit_space = _commits_summary.map_or(0, |_| compute_space(commit_ratio));
     let issue_space = _issues_summary.map_or(0, |_| compute_space(issue_ratio));
+    let pull_space = _pulls_summary.map_or(0, |_| compute_space(pull_ratio));
+    let review_space = _reviews_summary.map_or(0, |_| compute_space(review_ratio));
     let discussion_space = _discussions_summary.map_or(0, |_| compute_space(discussion_ratio));
+    let link_space = _links.map_or(0, |_| compute_space(link_ratio));
 
-    let trim_to_allocated_space =
-        |source: &str, space: usize| -> String { source.chars().take(space * 3).collect() };
+    let trim_to_allocated_space = |source: &str, space: usize| stage.fit_to(source, space);
 
     let profile_str = _profile_data.map_or("".to_string(), |x| {
         format!(
@@ -849,6 +1091,12 @@ pub async fn correlate_commits_issues_discussions(
     let issues_str = _issues_summary.map_or("".to_string(), |x| {
         format!("issue post: {}", trim_to_allocated_space(x, issue_space))
     });
+    let pulls_str = _pulls_summary.map_or("".to_string(), |x| {
+        format!("pull requests: {}", trim_to_allocated_space(x, pull_space))
+    });
+    let reviews_str = _reviews_summary.map_or("".to_string(), |x| {
+        format!("code reviews: {}", trim_to_allocated_space(x, review_space))
+    });
     let discussions_str = _discussions_summary.map_or("".to_string(), |x| {
         format!(
             "discussion posts: {}",
@@ -856,91 +1104,97 @@ pub async fn correlate_commits_issues_discussions(
         )
     });
 
-    let target_str = target_person.map_or("key participants'".to_string(), |t| format!("{t}'s"));
-
-    let sys_prompt_1 =
-        "Analyze the GitHub activity data and profile data over the week to detect both key impactful contributions and connections between commits, issues, and discussions. Highlight specific code changes, resolutions, and improvements. Furthermore, trace evidence of commits addressing specific issues, discussions leading to commits, or issues spurred by discussions. The aim is to map out both the impactful technical advancements and the developmental narrative of the project.";
+    let links_str = _links.map_or("".to_string(), |x| {
+        format!(
+            "These links between commits, pull requests and issues were read from GitHub, treat them as facts: {}.",
+            trim_to_allocated_space(x, link_space)
+        )
+    });
 
-    let usr_prompt_1 = &format!(
-        "From {profile_str}, {commits_str}, {issues_str}, and {discussions_str}, detail {target_str} significant technical contributions. Enumerate individual tasks, code enhancements, and bug resolutions, emphasizing impactful contributions. Concurrently, identify connections: commits that appear to resolve specific issues, discussions that may have catalyzed certain commits, or issues influenced by preceding discussions. Extract tangible instances showcasing both impact and interconnections within the week."
-    );
+    let target_str = target_person.map_or("key participants'".to_string(), |t| format!("{t}'s"));
 
-    let (gen_1_size, gen_2_size, gen_2_reminder) = match total_input_entry_count {
-        0..=3 => (384, 96, 250),
-        4..=14 => (512, 350, 350),
-        15.. => (1024, 500, 500),
+    let sys_prompt_1 = &prompts.render("synthesis_system", &[("period", period)])?;
+    let usr_prompt_1 = &prompts.render(
+        "synthesis_user",
+        &[
+            ("profile", &profile_str),
+            ("commits", &commits_str),
+            ("pull_requests", &pulls_str),
+            ("reviews", &reviews_str),
+            ("issues", &issues_str),
+            ("discussions", &discussions_str),
+            ("links", &links_str),
+            ("target", &target_str),
+            ("period", period),
+        ],
+    )?;
+
+    let (gen_1_size, gen_2_size) = match total_input_entry_count {
+        0..=3 => (384, 256),
+        4..=14 => (512, 350),
+        15.. => (1024, 500),
     };
 
-    // let usr_prompt_2 = &format!(
-    //     "Merge the identified impactful technical contributions and their interconnections into a coherent summary for {target_str} over the week. Describe how these contributions align with the project's technical objectives. Pinpoint recurring technical patterns or trends and shed light on the synergy between individual efforts and their collective progression. Detail both the weight of each contribution and their interconnectedness in shaping the project, please use bullet-points format in your reply. Limit to less than {gen_2_reminder} tokens."
-    // );
-    // let usr_prompt_2 = &format!(
-    //     "If applicable, summarize the key technical contributions made by {target_str} this week in bullet-point format. Address the following points within a token limit of {gen_2_reminder}. If no information is available for a point, leave it blank:
-    //     - Highlight impactful contributions and their interconnections.
-    //     - Explain alignment with the project's goals.
-    //     - Identify recurring patterns or trends.
-    //     - Discuss synergy between individual and collective advancement.
-    //     - Comment objectively on the significance of each contribution."
-    // );
-    let usr_prompt_2 = &format!(
-        r#"Analyze the key technical contributions made by {target_str} this week. Consider the following points and express the information in JSON format. If no information is available for a point, leave it blank.
-        - Highlight impactful contributions and their interconnections.
-        - Explain alignment with the project's goals.
-        - Identify recurring patterns or trends.
-        - Discuss synergy between individual and collective advancement.
-        - Comment objectively on the significance of each contribution. 
-        ```
-        {{
-          "impactful": "",
-          "alignment": "",
-          "patterns": "",
-          "synergy": "",
-          "significance": ""
-        }}
-        ```"#,
-    );
-    chain_of_chat(
-        sys_prompt_1,
-        usr_prompt_1,
-        "correlate-99",
-        gen_1_size,
-        usr_prompt_2,
-        gen_2_size,
-        "correlate_commits_issues_discussions",
-    )
-    .await
+    let usr_prompt_2 = &prompts.render(
+        "synthesis_followup",
+        &[
+            ("target", &target_str),
+            ("period", period),
+            ("schema", SYNTHESIS_SCHEMA),
+        ],
+    )?;
+
+    let req_1 = stage
+        .request("correlate-99")
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1)
+        .with_max_tokens(gen_1_size.min(stage.max_output_tokens));
+    let res_1 = generate(llm, &req_1, "correlate_commits_issues_discussions, step 1").await?;
+
+    // Only the second step answers in JSON.
+    let req_2 = req_1
+        .with_assistant(&res_1)
+        .with_user(usr_prompt_2)
+        .with_max_tokens(gen_2_size.min(stage.max_output_tokens))
+        .with_json_output(stage.json_mode);
+    generate(llm, &req_2, "correlate_commits_issues_discussions, step 2").await
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn correlate_user_and_home_project(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
     home_repo_data: &str,
     user_profile: &str,
     issues_data: &str,
     repos_data: &str,
     discussion_data: &str,
-) -> Option<String> {
-    let home_repo_data = home_repo_data.chars().take(6000).collect::<String>();
-    let user_profile = user_profile.chars().take(4000).collect::<String>();
-    let issues_data = issues_data.chars().take(9000).collect::<String>();
-    let repos_data = repos_data.chars().take(6000).collect::<String>();
-    let discussion_data = discussion_data.chars().take(4000).collect::<String>();
-
-    let sys_prompt_1 = &format!(
-        "First, let's analyze and understand the provided Github data in a step-by-step manner. Begin by evaluating the user's activity based on their most active repositories, languages used, issues they're involved in, and discussions they've participated in. Concurrently, grasp the characteristics and requirements of the home project. Your aim is to identify overlaps or connections between the user's skills or activities and the home project's needs."
-    );
+) -> Result<String, AnalyzerError> {
+    // The input budget is shared 6:4:9:6:4 between the five sources.
+    let share = |parts: usize| stage.input_budget() * parts / 29;
+    let home_repo_data = stage.fit_to(home_repo_data, share(6));
+    let user_profile = stage.fit_to(user_profile, share(4));
+    let issues_data = stage.fit_to(issues_data, share(9));
+    let repos_data = stage.fit_to(repos_data, share(6));
+    let discussion_data = stage.fit_to(discussion_data, share(4));
+
+    let sys_prompt_1 = &prompts.render("home_project_system", &[])?;
+    let usr_prompt_1 = &prompts.render(
+        "home_project_user",
+        &[
+            ("user_profile", &user_profile),
+            ("repos", &repos_data),
+            ("issues", &issues_data),
+            ("discussions", &discussion_data),
+            ("home_repo", &home_repo_data),
+        ],
+    )?;
+    let usr_prompt_2 = &prompts.render("home_project_followup", &[])?;
 
-    let usr_prompt_1 = &format!(
-        "Using a structured approach, analyze the given data: User Profile: {} Active Repositories: {} Issues Involved: {} Discussions Participated: {} Home project's characteristics: {} Identify patterns in the user's activity and spot potential synergies with the home project. Pay special attention to the programming languages they use, especially if they align with the home project's requirements. Derive insights from their interactions and the data provided.",
-        user_profile,
-        repos_data,
-        issues_data,
-        discussion_data,
-        home_repo_data
-    );
-
-    let usr_prompt_2 = &format!(
-        "Now, using the insights from your step-by-step analysis, craft a concise bullet-point summary that underscores: - The user's main areas of expertise and interest. - The relevance of their preferred languages or technologies to the home project. - Their potential contributions to the home project, based on their skills and interactions. Ensure the summary is clear, insightful, and remains under 256 tokens. Emphasize any evident alignments between the user's skills and the project's needs."
-    );
     chain_of_chat(
+        llm,
+        stage,
         sys_prompt_1,
         usr_prompt_1,
         "correlate-user-home",
@@ -0,0 +1,132 @@
+use crate::error::AnalyzerError;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+
+const DEFAULT_DAYS: u16 = 7;
+
+/// The reporting window every fetcher and prompt of a report works on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl Default for DateRange {
+    fn default() -> Self {
+        DateRange::last_days(DEFAULT_DAYS)
+    }
+}
+
+impl DateRange {
+    pub fn new(from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Self, AnalyzerError> {
+        if from >= to {
+            return Err(AnalyzerError::InvalidInput(format!(
+                "the start of the period ({}) must be before its end ({})",
+                from.format("%Y-%m-%d"),
+                to.format("%Y-%m-%d")
+            )));
+        }
+        Ok(DateRange { from, to })
+    }
+
+    /// The `days` days leading up to now.
+    pub fn last_days(days: u16) -> Self {
+        let to = Utc::now();
+        DateRange {
+            from: to - Duration::days(days as i64),
+            to,
+        }
+    }
+
+    /// Builds the window from the webhook's `days`, `from` and `to` params.
+    /// `from`/`to` take `YYYY-MM-DD` or RFC 3339; a missing `to` means now
+    /// and a missing `from` means `days` (default 7) before `to`. Giving both
+    /// `from` and `days` is an error.
+    pub fn from_params(
+        days: Option<&str>,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<Self, AnalyzerError> {
+        if let (Some(days), Some(from)) = (days, from) {
+            return Err(AnalyzerError::InvalidInput(format!(
+                "give either days or from, not both: days={days}, from={from}"
+            )));
+        }
+        let days = match days {
+            Some(d) => d
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .filter(|d| *d > 0)
+                .ok_or_else(|| {
+                    AnalyzerError::InvalidInput(format!("days must be a positive number: {d}"))
+                })?,
+            None => DEFAULT_DAYS,
+        };
+        let to = match to {
+            Some(to) => parse_date(to, true)?,
+            None => Utc::now(),
+        };
+        let from = match from {
+            Some(from) => parse_date(from, false)?,
+            None => to - Duration::days(days as i64),
+        };
+        DateRange::new(from, to)
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.to - self.from).num_days().max(1)
+    }
+
+    pub fn since_str(&self) -> String {
+        self.from.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    pub fn until_str(&self) -> String {
+        self.to.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+    }
+
+    /// GitHub search qualifier for items updated within the window.
+    pub fn updated_qualifier(&self) -> String {
+        format!("updated:{}..{}", self.since_str(), self.until_str())
+    }
+
+    pub fn contains(&self, at: &DateTime<Utc>) -> bool {
+        self.from <= *at && *at <= self.to
+    }
+
+    /// How prompts refer to the window, e.g. "the past week".
+    pub fn period(&self) -> String {
+        if Utc::now() - self.to > Duration::hours(1) {
+            return format!(
+                "the period from {} to {}",
+                self.from.format("%Y-%m-%d"),
+                self.to.format("%Y-%m-%d")
+            );
+        }
+        match self.days() {
+            1 => "the past day".to_string(),
+            7 => "the past week".to_string(),
+            14 => "the past two weeks".to_string(),
+            28..=31 => "the past month".to_string(),
+            n => format!("the past {n} days"),
+        }
+    }
+}
+
+fn parse_date(input: &str, end_of_day: bool) -> Result<DateTime<Utc>, AnalyzerError> {
+    let input = input.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| {
+        AnalyzerError::InvalidInput(format!(
+            "unrecognised date {input}, expected YYYY-MM-DD or RFC 3339"
+        ))
+    })?;
+    let time = match end_of_day {
+        true => NaiveTime::from_hms_opt(23, 59, 59),
+        false => NaiveTime::from_hms_opt(0, 0, 0),
+    }
+    .unwrap_or_default();
+    Ok(Utc.from_utc_datetime(&date.and_time(time)))
+}
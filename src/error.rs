@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalyzerError {
+    InvalidInput(String),
+    NotFound(String),
+    Unauthorized(String),
+    RateLimited { reset_at: Option<DateTime<Utc>> },
+    Http { status: u16, url: String },
+    Transport(String),
+    Decode { context: String },
+    GraphQl(String),
+    Llm(String),
+}
+
+impl AnalyzerError {
+    /// Maps a non-success GitHub status code for `url` onto the matching variant.
+    pub fn from_status(status: u16, url: &str) -> Self {
+        match status {
+            401 | 403 => AnalyzerError::Unauthorized(url.to_string()),
+            404 | 410 => AnalyzerError::NotFound(url.to_string()),
+            429 => AnalyzerError::RateLimited { reset_at: None },
+            _ => AnalyzerError::Http {
+                status,
+                url: url.to_string(),
+            },
+        }
+    }
+
+    pub fn decode(context: &str, err: impl fmt::Display) -> Self {
+        AnalyzerError::Decode {
+            context: format!("{context}: {err}"),
+        }
+    }
+
+    /// Status code the webhook should answer with when a report fails with this error.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            AnalyzerError::InvalidInput(_) => 400,
+            AnalyzerError::NotFound(_) => 404,
+            AnalyzerError::Unauthorized(_) => 401,
+            AnalyzerError::RateLimited { .. } => 429,
+            AnalyzerError::Http { .. }
+            | AnalyzerError::Transport(_)
+            | AnalyzerError::Decode { .. }
+            | AnalyzerError::GraphQl(_)
+            | AnalyzerError::Llm(_) => 502,
+        }
+    }
+}
+
+impl fmt::Display for AnalyzerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnalyzerError::InvalidInput(msg) => write!(f, "invalid request: {msg}"),
+            AnalyzerError::NotFound(what) => write!(
+                f,
+                "{what} was not found, the owner/repo may be invalid or the target is private"
+            ),
+            AnalyzerError::Unauthorized(what) => {
+                write!(f, "the GitHub token is not authorized to access {what}")
+            }
+            AnalyzerError::RateLimited {
+                reset_at: Some(reset_at),
+            } => write!(
+                f,
+                "GitHub rate limit exceeded, it resets at {}",
+                reset_at.format("%Y-%m-%dT%H:%M:%SZ")
+            ),
+            AnalyzerError::RateLimited { reset_at: None } => {
+                write!(f, "GitHub rate limit exceeded")
+            }
+            AnalyzerError::Http { status, url } => {
+                write!(f, "GitHub returned http status {status} for {url}")
+            }
+            AnalyzerError::Transport(msg) => write!(f, "failed to reach GitHub: {msg}"),
+            AnalyzerError::Decode { context } => {
+                write!(f, "failed to decode GitHub response for {context}")
+            }
+            AnalyzerError::GraphQl(msg) => write!(f, "GitHub GraphQL query failed: {msg}"),
+            AnalyzerError::Llm(msg) => write!(f, "LLM generation failed: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for AnalyzerError {}
@@ -0,0 +1,557 @@
+use crate::error::AnalyzerError;
+use crate::http::{HttpRequest, HttpResponse, HttpTransport, Method, WasiHttp};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_WEB_URL: &str = "https://github.com";
+const DEFAULT_USER_AGENT: &str = "flows-network connector";
+const DEFAULT_API_VERSION: &str = "2022-11-28";
+const DEFAULT_MAX_RETRIES: u32 = 3;
+const DEFAULT_MAX_WAIT_SECS: u64 = 60;
+const DEFAULT_SEARCH_LIMIT: usize = 300;
+/// Requests kept in hand when the analyzers decide whether to fetch more.
+pub const RATE_LIMIT_RESERVE: u32 = 10;
+
+/// What GitHub reported about a rate limit bucket (`core`, `graphql`,
+/// `search`, ...) on the last response that carried the headers.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateBudget {
+    pub limit: Option<u32>,
+    pub remaining: u32,
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+/// Single entry point for every request the crate makes to GitHub, so the
+/// whole crate can be pointed at GitHub Enterprise or a mock server at once.
+#[derive(Clone)]
+pub struct GitHubClient {
+    token: String,
+    base_url: String,
+    graphql_url: String,
+    web_url: String,
+    user_agent: String,
+    api_version: String,
+    max_retries: u32,
+    max_wait: Duration,
+    search_limit: usize,
+    budgets: Arc<Mutex<HashMap<String, RateBudget>>>,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl fmt::Debug for GitHubClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GitHubClient")
+            .field("base_url", &self.base_url)
+            .field("graphql_url", &self.graphql_url)
+            .field("web_url", &self.web_url)
+            .field("user_agent", &self.user_agent)
+            .field("api_version", &self.api_version)
+            .field("max_retries", &self.max_retries)
+            .field("max_wait", &self.max_wait)
+            .field("search_limit", &self.search_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GitHubClient {
+    pub fn new(token: &str) -> Self {
+        GitHubClient {
+            token: token.to_string(),
+            base_url: DEFAULT_API_URL.to_string(),
+            graphql_url: format!("{DEFAULT_API_URL}/graphql"),
+            web_url: DEFAULT_WEB_URL.to_string(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            api_version: DEFAULT_API_VERSION.to_string(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            max_wait: Duration::from_secs(DEFAULT_MAX_WAIT_SECS),
+            search_limit: DEFAULT_SEARCH_LIMIT,
+            budgets: Arc::new(Mutex::new(HashMap::new())),
+            transport: Arc::new(WasiHttp),
+        }
+    }
+
+    /// Builds a client for `token`, honouring the optional `github_api_url`,
+    /// `github_graphql_url`, `github_web_url` and `github_search_limit`
+    /// environment overrides.
+    pub fn from_env(token: &str) -> Self {
+        let mut client = GitHubClient::new(token);
+        if let Ok(base_url) = env::var("github_api_url") {
+            client = client.with_base_url(&base_url);
+        }
+        if let Ok(graphql_url) = env::var("github_graphql_url") {
+            client = client.with_graphql_url(&graphql_url);
+        }
+        if let Ok(web_url) = env::var("github_web_url") {
+            client = client.with_web_url(&web_url);
+        }
+        if let Some(limit) = env::var("github_search_limit")
+            .ok()
+            .and_then(|limit| limit.parse().ok())
+        {
+            client = client.with_search_limit(limit);
+        }
+        client
+    }
+
+    /// Sets the REST base url; the GraphQL endpoint and the web url follow
+    /// it unless set explicitly afterwards (`https://ghe.host/api/v3` ->
+    /// `https://ghe.host/api/graphql` and `https://ghe.host`).
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        let base_url = base_url.trim_end_matches('/');
+        self.graphql_url = match base_url.strip_suffix("/v3") {
+            Some(api_root) => format!("{api_root}/graphql"),
+            None => format!("{base_url}/graphql"),
+        };
+        self.web_url = match base_url {
+            DEFAULT_API_URL => DEFAULT_WEB_URL.to_string(),
+            _ => base_url
+                .strip_suffix("/api/v3")
+                .unwrap_or(base_url)
+                .to_string(),
+        };
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn with_graphql_url(mut self, graphql_url: &str) -> Self {
+        self.graphql_url = graphql_url.to_string();
+        self
+    }
+
+    /// Where repositories are browsed, for the links the reports build.
+    pub fn with_web_url(mut self, web_url: &str) -> Self {
+        self.web_url = web_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
+    }
+
+    pub fn with_api_version(mut self, api_version: &str) -> Self {
+        self.api_version = api_version.to_string();
+        self
+    }
+
+    /// How many times a rate limited or 5xx request is retried before giving up.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Longest single pause the client accepts while waiting for a rate limit
+    /// to reset; beyond that the request fails with `RateLimited` right away.
+    pub fn with_max_wait(mut self, max_wait: Duration) -> Self {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Most nodes a GraphQL `search` walks through before it stops paging.
+    pub fn with_search_limit(mut self, search_limit: usize) -> Self {
+        self.search_limit = search_limit;
+        self
+    }
+
+    /// Sends the requests through `transport` instead of [`WasiHttp`].
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn graphql_url(&self) -> &str {
+        &self.graphql_url
+    }
+
+    pub fn web_url(&self) -> &str {
+        &self.web_url
+    }
+
+    /// Last known budget for a rate limit bucket, `None` until a response for
+    /// it has been seen. Clones of the client share the same budgets.
+    pub fn rate_budget(&self, resource: &str) -> Option<RateBudget> {
+        self.budgets.lock().ok()?.get(resource).copied()
+    }
+
+    /// Whether more than `reserve` REST (`core`) requests are left; an
+    /// unknown budget counts as available.
+    pub fn has_budget(&self, reserve: u32) -> bool {
+        match self.rate_budget("core") {
+            Some(budget) => budget.remaining > reserve,
+            None => true,
+        }
+    }
+
+    /// Walks a REST list endpoint page by page, see [`Paginator`].
+    pub fn paginate<T: DeserializeOwned>(&self, path: &str, context: &str) -> Paginator<'_, T> {
+        Paginator {
+            client: self,
+            next_url: Some(self.api_url(path)),
+            context: context.to_string(),
+            items_key: None,
+            max_items: None,
+            fetched: 0,
+            _item: PhantomData,
+        }
+    }
+
+    /// Resolves `path` against the REST base url; absolute urls (e.g. the
+    /// `url` fields GitHub returns) are passed through untouched.
+    pub fn api_url(&self, path: &str) -> String {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            path.to_string()
+        } else {
+            format!("{}/{}", self.base_url, path.trim_start_matches('/'))
+        }
+    }
+
+    pub async fn get(&self, path: &str) -> Result<Vec<u8>, AnalyzerError> {
+        self.get_with_header(path).await.map(|res| res.body)
+    }
+
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        context: &str,
+    ) -> Result<T, AnalyzerError> {
+        let body = self.get(path).await?;
+        serde_json::from_slice::<T>(&body).map_err(|e| AnalyzerError::decode(context, e))
+    }
+
+    /// Fetches a non-JSON resource such as a commit `.patch`.
+    pub async fn get_text(&self, url: &str) -> Result<String, AnalyzerError> {
+        let url = self.api_url(url);
+        let res = self.send(Method::Get, &url, "text/plain", None).await?;
+        Ok(String::from_utf8_lossy(&res.body).to_string())
+    }
+
+    /// Fetches a pull request or commit (`repos/o/r/pulls/1`) as a unified diff.
+    pub async fn get_diff(&self, path: &str) -> Result<String, AnalyzerError> {
+        let url = self.api_url(path);
+        let res = self
+            .send(Method::Get, &url, "application/vnd.github.diff", None)
+            .await?;
+        Ok(String::from_utf8_lossy(&res.body).to_string())
+    }
+
+    pub async fn get_with_header(&self, path: &str) -> Result<HttpResponse, AnalyzerError> {
+        let url = self.api_url(path);
+        self.send(Method::Get, &url, "application/vnd.github+json", None)
+            .await
+    }
+
+    pub async fn post_graphql(&self, query: &str) -> Result<Vec<u8>, AnalyzerError> {
+        self.post_graphql_with(query, &Value::Null).await
+    }
+
+    pub async fn post_graphql_with(
+        &self,
+        query: &str,
+        variables: &Value,
+    ) -> Result<Vec<u8>, AnalyzerError> {
+        let body = json!({ "query": query, "variables": variables }).to_string();
+        let url = self.graphql_url.clone();
+        self.send(
+            Method::Post,
+            &url,
+            "application/json",
+            Some(body.as_bytes()),
+        )
+        .await
+        .map(|res| res.body)
+    }
+
+    /// Runs a GraphQL query with `variables` and returns its `data`. Errors
+    /// reported alongside partial data are logged, without data they fail.
+    pub async fn graphql<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: Value,
+        context: &str,
+    ) -> Result<T, AnalyzerError> {
+        #[derive(Deserialize)]
+        struct GraphQlResponse<T> {
+            data: Option<T>,
+            errors: Option<Vec<GraphQlError>>,
+        }
+
+        #[derive(Deserialize)]
+        struct GraphQlError {
+            message: String,
+        }
+
+        let body = self.post_graphql_with(query, &variables).await?;
+        let res = serde_json::from_slice::<GraphQlResponse<T>>(&body)
+            .map_err(|e| AnalyzerError::decode(context, e))?;
+        let errors = res
+            .errors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| e.message)
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        match res.data {
+            Some(data) => {
+                if !errors.is_empty() {
+                    log::warn!("GraphQL {context} returned partial data: {errors}");
+                }
+                Ok(data)
+            }
+            None if !errors.is_empty() => Err(AnalyzerError::GraphQl(errors)),
+            None => Err(AnalyzerError::decode(context, "response has no data")),
+        }
+    }
+
+    /// Walks a GraphQL `search` connection by `pageInfo.endCursor` until it
+    /// ends or the client's search limit is reached. `query` must declare
+    /// `$query: String!, $first: Int!, $after: String` and select
+    /// `search(...) { edges { node { ... } } pageInfo { endCursor hasNextPage } }`.
+    pub async fn graphql_search<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        search_query: &str,
+        context: &str,
+    ) -> Result<Vec<T>, AnalyzerError> {
+        self.graphql_search_up_to(query, search_query, self.search_limit, context)
+            .await
+    }
+
+    /// [`graphql_search`](Self::graphql_search) stopping after `limit` nodes,
+    /// or the client's search limit if that is lower.
+    pub async fn graphql_search_up_to<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        search_query: &str,
+        limit: usize,
+        context: &str,
+    ) -> Result<Vec<T>, AnalyzerError> {
+        #[derive(Deserialize)]
+        struct SearchData<T> {
+            search: Connection<T>,
+        }
+
+        #[derive(Deserialize)]
+        struct Connection<T> {
+            edges: Option<Vec<Option<Edge<T>>>>,
+            #[serde(rename = "pageInfo")]
+            page_info: Option<PageInfo>,
+        }
+
+        #[derive(Deserialize)]
+        struct Edge<T> {
+            node: Option<T>,
+        }
+
+        #[derive(Deserialize)]
+        struct PageInfo {
+            #[serde(rename = "endCursor")]
+            end_cursor: Option<String>,
+            #[serde(rename = "hasNextPage")]
+            has_next_page: Option<bool>,
+        }
+
+        let mut nodes = Vec::new();
+        let mut cursor: Option<String> = None;
+        let limit = limit.min(self.search_limit);
+        while nodes.len() < limit {
+            let first = (limit - nodes.len()).min(100);
+            let variables = json!({ "query": search_query, "first": first, "after": cursor });
+            let page = self
+                .graphql::<SearchData<T>>(query, variables, context)
+                .await?;
+
+            nodes.extend(
+                page.search
+                    .edges
+                    .unwrap_or_default()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|edge| edge.node),
+            );
+            cursor = match page.search.page_info {
+                Some(PageInfo {
+                    has_next_page: Some(true),
+                    end_cursor: Some(end_cursor),
+                }) => Some(end_cursor),
+                _ => break,
+            };
+        }
+        Ok(nodes)
+    }
+
+    /// Sends one request, recording the rate limit headers and retrying
+    /// rate limited and 5xx responses with exponential backoff.
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        accept: &str,
+        body: Option<&[u8]>,
+    ) -> Result<HttpResponse, AnalyzerError> {
+        let mut request = HttpRequest::new(method, url)
+            .with_header("User-Agent", &self.user_agent)
+            .with_header("Accept", accept)
+            .with_header("X-GitHub-Api-Version", &self.api_version)
+            .with_header("Authorization", &format!("Bearer {}", self.token));
+        if let Some(body) = body {
+            request = request
+                .with_header("Content-Type", "application/json")
+                .with_body(body);
+        }
+        let mut attempt = 0;
+
+        loop {
+            let res = self.transport.send(&request).await?;
+
+            let budget = self.record_budget(&res);
+            let status = res.status;
+            if res.is_success() {
+                return Ok(res);
+            }
+
+            let retry_after = header_u64(&res, "Retry-After").map(Duration::from_secs);
+            let rate_limited = status == 429
+                || (status == 403
+                    && (retry_after.is_some()
+                        || budget.is_some_and(|b| b.remaining == 0)
+                        || String::from_utf8_lossy(&res.body).contains("rate limit")));
+
+            let wait = if rate_limited {
+                let reset_at = budget.and_then(|b| b.reset_at);
+                let wait = retry_after.or_else(|| {
+                    reset_at.map(|reset_at| {
+                        (reset_at - Utc::now()).to_std().unwrap_or_default()
+                            + Duration::from_secs(1)
+                    })
+                });
+                match wait {
+                    Some(wait) if wait <= self.max_wait && attempt < self.max_retries => wait,
+                    None if attempt < self.max_retries => backoff(attempt),
+                    _ => return Err(AnalyzerError::RateLimited { reset_at }),
+                }
+            } else if status >= 500 && attempt < self.max_retries {
+                backoff(attempt)
+            } else {
+                return Err(AnalyzerError::from_status(status, url));
+            };
+
+            attempt += 1;
+            log::warn!(
+                "GitHub answered {status} for {url}, retry {attempt}/{} in {}s",
+                self.max_retries,
+                wait.as_secs()
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    fn record_budget(&self, res: &HttpResponse) -> Option<RateBudget> {
+        let remaining = header_u64(res, "X-RateLimit-Remaining")? as u32;
+        let budget = RateBudget {
+            limit: header_u64(res, "X-RateLimit-Limit").map(|limit| limit as u32),
+            remaining,
+            reset_at: header_u64(res, "X-RateLimit-Reset")
+                .and_then(|secs| Utc.timestamp_opt(secs as i64, 0).single()),
+        };
+        let resource = res
+            .header("X-RateLimit-Resource")
+            .unwrap_or("core")
+            .to_string();
+        if let Ok(mut budgets) = self.budgets.lock() {
+            budgets.insert(resource, budget);
+        }
+        Some(budget)
+    }
+}
+
+/// Typed pages of a REST list endpoint, following the `Link: rel="next"`
+/// header until it runs out or `max_items` have been yielded.
+pub struct Paginator<'a, T> {
+    client: &'a GitHubClient,
+    next_url: Option<String>,
+    context: String,
+    items_key: Option<&'static str>,
+    max_items: Option<usize>,
+    fetched: usize,
+    _item: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> Paginator<'a, T> {
+    /// For endpoints that wrap the list in an object, e.g. `items` for search.
+    pub fn items_at(mut self, key: &'static str) -> Self {
+        self.items_key = Some(key);
+        self
+    }
+
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Next page of items, `None` once the listing or the cap is exhausted.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>, AnalyzerError> {
+        let url = match self.next_url.take() {
+            Some(url) if self.max_items.is_none_or(|max| self.fetched < max) => url,
+            _ => return Ok(None),
+        };
+        let res = self.client.get_with_header(&url).await?;
+        let mut items = match self.items_key {
+            None => serde_json::from_slice::<Vec<T>>(&res.body),
+            Some(key) => serde_json::from_slice::<serde_json::Value>(&res.body)
+                .and_then(|mut page| serde_json::from_value(page[key].take())),
+        }
+        .map_err(|e| AnalyzerError::decode(&self.context, e))?;
+
+        if let Some(max) = self.max_items {
+            items.truncate(max - self.fetched);
+        }
+        self.fetched += items.len();
+        if !items.is_empty() {
+            self.next_url = next_page_url(&res);
+        }
+        Ok(Some(items))
+    }
+
+    /// Gathers every remaining item into one list.
+    pub async fn collect(mut self) -> Result<Vec<T>, AnalyzerError> {
+        let mut all = Vec::new();
+        while let Some(items) = self.next_page().await? {
+            all.extend(items);
+        }
+        Ok(all)
+    }
+}
+
+/// Url of the `rel="next"` page announced in a response's `Link` header.
+pub fn next_page_url(res: &HttpResponse) -> Option<String> {
+    res.header("Link")?
+        .split(',')
+        .find(|link| link.contains("rel=\"next\""))
+        .and_then(|link| link.split(';').next())
+        .map(|url| url.trim_matches(&[' ', '<', '>'] as &[char]).to_string())
+}
+
+fn header_u64(res: &HttpResponse, name: &str) -> Option<u64> {
+    res.header(name)?.trim().parse().ok()
+}
+
+pub(crate) fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(5))
+}
@@ -1,18 +1,25 @@
 use std::ascii::AsciiExt;
 
-use crate::octocrab_compat::{Comment, Issue, Repository, User};
+use crate::date_range::DateRange;
+use crate::error::AnalyzerError;
+use crate::github_client::{GitHubClient, RATE_LIMIT_RESERVE};
+use crate::kv_store::KvStore;
+use crate::llm::{generate, LlmClient};
+use crate::octocrab_compat::{
+    Comment, Issue, IssueEvent, Release, Repository, Review, ReviewComment, User,
+};
+use crate::pipeline_config::PipelineConfig;
+use crate::prompts::PromptRegistry;
 use crate::utils::*;
-use chrono::{DateTime, Duration, NaiveDate, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use derivative::Derivative;
-use http_req::response::Response;
-use openai_flows::{
-    self,
-    chat::{self, ChatOptions},
-    OpenAIFlows,
-};
 use serde::{Deserialize, Serialize};
-use serde_json;
-use store_flows::{get, set};
+use serde_json::{self, json};
+use std::collections::HashMap;
+
+const MAX_ISSUES_IN_RANGE: usize = 100;
+/// Every discussion found is summarized, so the search stops at one page.
+const MAX_DISCUSSIONS: usize = 100;
 
 #[derive(Derivative, Serialize, Deserialize, Debug, Clone)]
 pub struct GitMemory {
@@ -31,23 +38,87 @@ pub struct GitMemory {
 pub enum MemoryType {
     Commit,
     Issue,
+    PullRequest,
     Discussion,
     Meta,
 }
 
-pub async fn get_user_profile(github_token: &str, user: &str) -> Option<User> {
-    let user_profile_url = format!("https://api.github.com/users/{user}");
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PullRequest {
+    /// `owner/repo` the pull request belongs to.
+    pub repository: String,
+    pub number: u64,
+    pub title: String,
+    pub html_url: String,
+    pub author: String,
+    pub body: String,
+    /// `OPEN`, `CLOSED` or `MERGED`.
+    pub state: String,
+    pub created_at: DateTime<Utc>,
+    pub merged_at: Option<DateTime<Utc>>,
+    pub additions: u64,
+    pub deletions: u64,
+    pub changed_files: u64,
+    pub reviewers: Vec<String>,
+    /// Urls of the issues the pull request closes.
+    pub linked_issues: Vec<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
 
-    match github_http_fetch(&github_token, &user_profile_url).await {
-        Some(res) => serde_json::from_slice::<User>(res.as_slice()).ok(),
+/// A commit as the commits and compare endpoints list it.
+#[derive(Serialize, Deserialize, Debug)]
+struct GithubCommit {
+    sha: String,
+    html_url: String,
+    author: Option<CommitAuthor>,    // made nullable
+    committer: Option<CommitAuthor>, // made nullable
+    commit: CommitDetails,
+}
 
-        None => {
-            log::error!("Github user not found.");
-            None
-        }
+#[derive(Debug, Deserialize, Serialize, Clone)]
+struct CommitAuthor {
+    login: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CommitDetails {
+    author: CommitUserDetails,
+    message: String,
+    // committer: CommitUserDetails,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct CommitUserDetails {
+    date: Option<DateTime<Utc>>,
+}
+
+impl GithubCommit {
+    /// `None` for commits without an author date.
+    fn to_memory(&self) -> Option<GitMemory> {
+        let commit_date = self.commit.author.date?;
+        Some(GitMemory {
+            memory_type: MemoryType::Commit,
+            name: self.author.clone().map_or(String::new(), |au| au.login),
+            tag_line: self.commit.message.clone(),
+            source_url: self.html_url.clone(),
+            payload: String::from(""),
+            date: commit_date.date_naive(),
+        })
     }
 }
-pub async fn get_user_data_by_login(github_token: &str, login: &str) -> Option<String> {
+
+pub async fn get_user_profile(client: &GitHubClient, user: &str) -> Result<User, AnalyzerError> {
+    let user_profile_url = format!("users/{user}");
+
+    client
+        .get_json::<User>(&user_profile_url, "user profile")
+        .await
+}
+pub async fn get_user_data_by_login(
+    client: &GitHubClient,
+    login: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct User {
         name: Option<String>,
@@ -69,19 +140,12 @@ pub async fn get_user_data_by_login(github_token: &str, login: &str) -> Option<S
         repository_owner: Option<User>,
     }
 
-    #[derive(Debug, Deserialize)]
-    struct UserRoot {
-        data: Option<RepositoryOwner>,
-    }
-
-    let base_url = "https://api.github.com/graphql";
     let mut out = String::from("USER_profile: \n");
 
-    let query = format!(
-        r#"
-        query {{
-            repositoryOwner(login: "{login}") {{
-                ... on User {{
+    let query = r#"
+        query($login: String!) {
+            repositoryOwner(login: $login) {
+                ... on User {
                     name
                     login
                     url
@@ -91,119 +155,99 @@ pub async fn get_user_data_by_login(github_token: &str, login: &str) -> Option<S
                     location
                     createdAt
                     email
-                }}
-            }}
-        }}
-        "#
-    );
+                }
+            }
+        }
+        "#;
 
-    match github_http_post(&github_token, base_url, &&query).await {
-        None => {
-            log::info!("Failed to send the request to get UserRoot: {}", base_url);
-            return None;
+    let results = client
+        .graphql::<RepositoryOwner>(query, json!({ "login": login }), "UserRoot")
+        .await?;
+
+    let user = match results.repository_owner {
+        Some(user) => user,
+        None => return Err(AnalyzerError::NotFound(format!("user {login}"))),
+    };
+
+    let login_str = match &user.login {
+        Some(login) => format!("Login: {},", login),
+        None => return Err(AnalyzerError::NotFound(format!("user {login}"))),
+    };
+
+    let name_str = match &user.name {
+        Some(name) => format!("Name: {},", name),
+        None => String::new(),
+    };
+
+    let url_str = match &user.url {
+        Some(url) => format!("Url: {},", url),
+        None => String::new(),
+    };
+
+    let twitter_str = match &user.twitter_username {
+        Some(twitter) => format!("Twitter: {},", twitter),
+        None => String::new(),
+    };
+
+    let bio_str = match &user.bio {
+        Some(bio) if bio.is_empty() => String::new(),
+        Some(bio) => format!("Bio: {},", bio),
+        None => String::new(),
+    };
+
+    let company_str = match &user.company {
+        Some(company) => format!("Company: {},", company),
+        None => String::new(),
+    };
+
+    let location_str = match &user.location {
+        Some(location) => format!("Location: {},", location),
+        None => String::new(),
+    };
+
+    let date_str = match &user.created_at {
+        Some(date) => {
+            format!("Created At: {},", date.date_naive().to_string())
         }
-        Some(res) => match serde_json::from_slice::<UserRoot>(res.as_slice()) {
-            Err(e) => {
-                log::error!("Failed to parse the response for UserRoot: {}", e);
-                return None;
-            }
-            Ok(results) => {
-                if let Some(repository_owner) = &results.data {
-                    if let Some(user) = &repository_owner.repository_owner {
-                        let login_str = match &user.login {
-                            Some(login) => format!("Login: {},", login),
-                            None => {
-                                return None;
-                            }
-                        };
-
-                        let name_str = match &user.name {
-                            Some(name) => format!("Name: {},", name),
-                            None => String::new(),
-                        };
-
-                        let url_str = match &user.url {
-                            Some(url) => format!("Url: {},", url),
-                            None => String::new(),
-                        };
-
-                        let twitter_str = match &user.twitter_username {
-                            Some(twitter) => format!("Twitter: {},", twitter),
-                            None => String::new(),
-                        };
-
-                        let bio_str = match &user.bio {
-                            Some(bio) if bio.is_empty() => String::new(),
-                            Some(bio) => format!("Bio: {},", bio),
-                            None => String::new(),
-                        };
-
-                        let company_str = match &user.company {
-                            Some(company) => format!("Company: {},", company),
-                            None => String::new(),
-                        };
-
-                        let location_str = match &user.location {
-                            Some(location) => format!("Location: {},", location),
-                            None => String::new(),
-                        };
-
-                        let date_str = match &user.created_at {
-                            Some(date) => {
-                                format!("Created At: {},", date.date_naive().to_string())
-                            }
-                            None => String::new(),
-                        };
+        None => String::new(),
+    };
 
-                        let email_str = match &user.email {
-                            Some(email) => format!("Email: {}", email),
-                            None => String::new(),
-                        };
+    let email_str = match &user.email {
+        Some(email) => format!("Email: {}", email),
+        None => String::new(),
+    };
 
-                        out.push_str(
-                                &format!(
-                                    "{name_str} {login_str} {url_str} {twitter_str} {bio_str} {company_str} {location_str} {date_str} {email_str}\n"
-                                )
-                            );
-                    }
-                }
-            }
-        },
-    }
+    out.push_str(&format!(
+        "{name_str} {login_str} {url_str} {twitter_str} {bio_str} {company_str} {location_str} {date_str} {email_str}\n"
+    ));
 
-    Some(out)
+    Ok(out)
 }
 pub async fn get_community_profile_data(
-    github_token: &str,
+    client: &GitHubClient,
     owner: &str,
     repo: &str,
-) -> Option<String> {
+) -> Result<String, AnalyzerError> {
     #[derive(Deserialize, Debug)]
     struct CommunityProfile {
         description: String,
         // documentation: Option<String>,
     }
 
-    let community_profile_url =
-        format!("https://api.github.com/repos/{owner}/{repo}/community/profile");
+    let community_profile_url = format!("repos/{owner}/{repo}/community/profile");
 
-    match github_http_fetch(&github_token, &community_profile_url).await {
-        Some(res) => match serde_json::from_slice::<CommunityProfile>(&res) {
-            Ok(profile) => {
-                return Some(format!("Description: {}", profile.description));
-            }
-            Err(e) => log::error!("Error parsing Community Profile: {:?}", e),
-        },
-        None => log::error!("Community profile not found for {}/{}.", owner, repo),
-    }
-    None
+    let profile = client
+        .get_json::<CommunityProfile>(&community_profile_url, "community profile")
+        .await?;
+    Ok(format!("Description: {}", profile.description))
 }
 pub async fn is_code_contributor(
-    github_token: &str,
+    client: &GitHubClient,
+    store: &dyn KvStore,
     owner: &str,
     repo: &str,
     user_name: &str,
-) -> bool {
+) -> Result<bool, AnalyzerError> {
     use std::hash::Hasher;
     use twox_hash::XxHash;
     let repo_string = format!("{owner}/{repo}");
@@ -211,254 +255,493 @@ pub async fn is_code_contributor(
     hasher.write(repo_string.as_bytes());
     let hash = hasher.finish();
     let key = &format!("{:x}", hash);
-    match get(key)
+    match store
+        .get(key)
         .and_then(|val| serde_json::from_value::<std::collections::HashSet<String>>(val).ok())
     {
-        Some(set) => set.contains(user_name),
-        None => match get_contributors(github_token, owner, repo).await {
-            Some(contributors) => {
-                set(
-                    key,
-                    serde_json::to_value(contributors.clone()).unwrap_or_default(),
-                    None,
-                );
-                return contributors.contains(&user_name.to_owned());
-            }
-            None => {
-                log::error!("Github contributors not found.");
-                return false;
-            }
-        },
+        Some(set) => Ok(set.contains(user_name)),
+        None => {
+            let contributors = get_contributors(client, owner, repo).await?;
+            store.set(
+                key,
+                serde_json::to_value(contributors.clone()).unwrap_or_default(),
+            );
+            Ok(contributors.contains(&user_name.to_owned()))
+        }
     }
 }
 
-pub async fn get_contributors(github_token: &str, owner: &str, repo: &str) -> Option<Vec<String>> {
+pub async fn get_contributors(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+) -> Result<Vec<String>, AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct GithubUser {
         login: String,
     }
 
-    let url = format!(
-        "https://api.github.com/repos/{}/{}/contributors",
-        owner, repo
-    );
-    let mut contributors = Vec::new();
+    let url = format!("repos/{}/{}/contributors?per_page=100", owner, repo);
+    let contributors = client
+        .paginate::<GithubUser>(&url, "contributors")
+        .collect()
+        .await?
+        .into_iter()
+        .map(|user| user.login)
+        .collect::<Vec<String>>();
 
-    let mut current_url = url.to_owned();
-    loop {
-        let response_result: Result<(Response, Vec<u8>), Box<dyn std::error::Error>> =
-            github_fetch_with_header(&github_token, &current_url);
-        match response_result {
-            Err(e) => {
-                log::error!(
-                    "Error getting response for request to get contributors: {:?}",
-                    e
-                );
-                return None;
-            }
-            Ok((res, body)) => {
-                let status = res.status_code(); // Check the status code
-                if !status.is_success() {
-                    log::error!(
-                        "Request to get contributors, unexpected status code: {:?}",
-                        status
-                    );
-                    return None;
-                }
-
-                let new_contributors: Vec<GithubUser> =
-                    match serde_json::from_slice(body.as_slice()) {
-                        Ok(contributors) => contributors,
-                        Err(err) => {
-                            log::error!("Error parsing contributors: {:?}", err);
-                            return None;
-                        }
-                    };
-
-                contributors.extend(new_contributors.into_iter().map(|user| user.login));
-
-                // Handle pagination
-                let res_headers = res.headers();
-                let link_header = res_headers.get("Link");
-                match link_header {
-                    Some(header) => {
-                        let next_link_temp: Option<String> = header
-                            .as_str()
-                            .split(',')
-                            .filter_map(|link| {
-                                if link.contains("rel=\"next\"") {
-                                    link.split(';').next().map(|url| {
-                                        url.trim_matches(&[' ', '<', '>'] as &[char]).to_string()
-                                    })
-                                } else {
-                                    None
-                                }
-                            })
-                            .next();
-
-                        let next_link = next_link_temp.as_deref();
+    Ok(contributors)
+}
 
-                        if let Some(link) = next_link {
-                            current_url = link.to_string();
-                        } else {
-                            break;
-                        }
-                    }
-                    None => {
-                        break;
-                    }
-                }
-            }
+fn decode_readme(content: Option<String>) -> Result<String, AnalyzerError> {
+    let content = match content {
+        Some(c) => c,
+        None => {
+            return Err(AnalyzerError::Decode {
+                context: "readme: content field is null".to_string(),
+            })
         }
-    }
+    };
+    let cleaned_content = content.replace("\n", "");
+    let decoded_content = base64::decode(&cleaned_content)
+        .map_err(|e| AnalyzerError::decode("readme base64 content", e))?;
+    let out = String::from_utf8(decoded_content)
+        .map_err(|e| AnalyzerError::decode("readme utf8 content", e))?;
 
-    Some(contributors)
+    Ok(format!("Readme: {}", out))
 }
 
-pub async fn get_readme(github_token: &str, owner: &str, repo: &str) -> Option<String> {
+pub async fn get_readme(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Deserialize, Debug)]
     struct GithubReadme {
         content: Option<String>,
     }
 
-    let readme_url = format!("https://api.github.com/repos/{owner}/{repo}/readme");
-
-    match github_http_fetch(&github_token, &readme_url).await {
-        Some(res) => match serde_json::from_slice::<GithubReadme>(&res) {
-            Ok(readme) => {
-                if let Some(c) = readme.content {
-                    let cleaned_content = c.replace("\n", "");
-                    match base64::decode(&cleaned_content) {
-                        Ok(decoded_content) => match String::from_utf8(decoded_content) {
-                            Ok(out) => {
-                                return Some(format!("Readme: {}", out));
-                            }
-                            Err(e) => {
-                                log::error!("Failed to convert cleaned readme to String: {:?}", e);
-                                return None;
-                            }
-                        },
-                        Err(e) => {
-                            log::error!("Error decoding base64 content: {:?}", e);
-                            None
-                        }
-                    }
-                } else {
-                    log::error!("Content field in readme is null.");
-                    None
-                }
-            }
-            Err(e) => {
-                log::error!("Error parsing Readme: {:?}", e);
-                None
-            }
-        },
-        None => {
-            log::error!("Github readme not found.");
-            None
-        }
-    }
+    let readme_url = format!("repos/{owner}/{repo}/readme");
+
+    let readme = client
+        .get_json::<GithubReadme>(&readme_url, "readme")
+        .await?;
+    decode_readme(readme.content)
 }
-pub async fn get_readme_owner_repo(github_token: &str, about_repo: &str) -> Option<String> {
+pub async fn get_readme_owner_repo(
+    client: &GitHubClient,
+    about_repo: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Deserialize, Debug)]
     struct GithubReadme {
         content: Option<String>,
     }
 
-    let readme_url = format!("https://api.github.com/repos/{about_repo}/readme");
-
-    match github_http_fetch(&github_token, &readme_url).await {
-        Some(res) => {
-            let text = String::from_utf8(res.clone()).unwrap_or_default(); // Fixed this line
-            slack_flows::send_message_to_channel("ik8", "ch_pro", text).await;
-
-            match serde_json::from_slice::<GithubReadme>(&res) {
-                Ok(readme) => {
-                    if let Some(c) = readme.content {
-                        let cleaned_content = c.replace("\n", "");
-                        match base64::decode(&cleaned_content) {
-                            Ok(decoded_content) => match String::from_utf8(decoded_content) {
-                                Ok(out) => {
-                                    return Some(format!("Readme: {}", out));
-                                }
-                                Err(e) => {
-                                    log::error!(
-                                        "Failed to convert cleaned readme to String: {:?}",
-                                        e
-                                    );
-                                    return None;
-                                }
-                            },
-                            Err(e) => {
-                                log::error!("Error decoding base64 content: {:?}", e);
-                                None
-                            }
-                        }
-                    } else {
-                        log::error!("Content field in readme is null.");
-                        None
-                    }
-                }
-                Err(e) => {
-                    log::error!("Error parsing Readme: {:?}", e);
-                    None
-                }
-            }
-        }
-        None => {
-            log::error!("Github readme not found.");
-            None
-        }
-    }
+    let readme_url = format!("repos/{about_repo}/readme");
+
+    let res = client.get(&readme_url).await?;
+    log::debug!("readme of {about_repo}: {}", String::from_utf8_lossy(&res));
+
+    let readme = serde_json::from_slice::<GithubReadme>(&res)
+        .map_err(|e| AnalyzerError::decode("readme", e))?;
+    decode_readme(readme.content)
 }
 pub async fn get_issues_in_range(
-    github_token: &str,
+    client: &GitHubClient,
     owner: &str,
     repo: &str,
     user_name: Option<String>,
-    range: u16,
+    range: &DateRange,
     token: Option<String>,
-) -> Option<(usize, Vec<Issue>)> {
-    #[derive(Debug, Deserialize)]
-    struct Page<T> {
-        pub items: Vec<T>,
-        pub total_count: Option<u64>,
-    }
-
-    let n_days_ago = (Utc::now() - Duration::days(range as i64))
-        .format("%Y-%m-%dT%H:%M:%SZ")
-        .to_string();
-
+) -> Result<(usize, Vec<Issue>), AnalyzerError> {
     let user_str = user_name.map_or(String::new(), |u| format!("involves:{}", u));
+    let updated_str = range.updated_qualifier();
 
-    let query = format!("repo:{owner}/{repo} is:issue {user_str} updated:>{n_days_ago}");
+    let query = format!("repo:{owner}/{repo} is:issue {user_str} {updated_str}");
     let encoded_query = urlencoding::encode(&query);
     let token_str = match token {
         None => String::new(),
         Some(t) => format!("&token={}", t.as_str()),
     };
     let url_str = format!(
-        "https://api.github.com/search/issues?q={}&sort=updated&order=desc&per_page=100{token_str}",
+        "search/issues?q={}&sort=updated&order=desc&per_page=100{token_str}",
         encoded_query
     );
 
-    let mut issue_vec = vec![];
-    match github_http_fetch(&github_token, &url_str).await {
-        Some(res) => match serde_json::from_slice::<Page<Issue>>(res.as_slice()) {
+    let issue_vec = client
+        .paginate::<Issue>(&url_str, "issues search")
+        .items_at("items")
+        .max_items(MAX_ISSUES_IN_RANGE)
+        .collect()
+        .await?;
+    let count = issue_vec.len();
+    Ok((count, issue_vec))
+}
+
+pub async fn get_pull_requests_in_range(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    user_name: Option<String>,
+    range: &DateRange,
+) -> Result<(usize, Vec<PullRequest>), AnalyzerError> {
+    let user_str = user_name.map_or(String::new(), |u| format!("involves:{}", u));
+    let updated_str = range.updated_qualifier();
+    let search_query = format!("repo:{owner}/{repo} is:pr {user_str} {updated_str}");
+
+    let pull_vec = search_pull_requests(client, owner, repo, &search_query).await?;
+    let count = pull_vec.len();
+    Ok((count, pull_vec))
+}
+
+async fn search_pull_requests(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    search_query: &str,
+) -> Result<Vec<PullRequest>, AnalyzerError> {
+    #[derive(Debug, Deserialize)]
+    struct Login {
+        login: Option<String>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Nodes<T> {
+        nodes: Option<Vec<Option<T>>>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Review {
+        author: Option<Login>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct ReviewRequest {
+        #[serde(rename = "requestedReviewer")]
+        requested_reviewer: Option<Login>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct LinkedIssue {
+        url: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Label {
+        name: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Repo {
+        #[serde(rename = "nameWithOwner")]
+        name_with_owner: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Node {
+        number: Option<u64>,
+        title: Option<String>,
+        url: Option<String>,
+        body: Option<String>,
+        state: Option<String>,
+        created_at: Option<DateTime<Utc>>,
+        merged_at: Option<DateTime<Utc>>,
+        additions: Option<u64>,
+        deletions: Option<u64>,
+        changed_files: Option<u64>,
+        author: Option<Login>,
+        repository: Option<Repo>,
+        reviews: Option<Nodes<Review>>,
+        review_requests: Option<Nodes<ReviewRequest>>,
+        closing_issues_references: Option<Nodes<LinkedIssue>>,
+        labels: Option<Nodes<Label>>,
+    }
+
+    fn nodes<T>(connection: Option<Nodes<T>>) -> impl Iterator<Item = T> {
+        connection
+            .and_then(|c| c.nodes)
+            .unwrap_or_default()
+            .into_iter()
+            .flatten()
+    }
+
+    let query = r#"
+        query($query: String!, $first: Int!, $after: String) {
+            search(query: $query, type: ISSUE, first: $first, after: $after) {
+                edges {
+                    node {
+                        ... on PullRequest {
+                            number
+                            title
+                            url
+                            body
+                            state
+                            createdAt
+                            mergedAt
+                            additions
+                            deletions
+                            changedFiles
+                            author {
+                                login
+                            }
+                            repository {
+                                nameWithOwner
+                            }
+                            reviews(first: 100) {
+                                nodes {
+                                    author {
+                                        login
+                                    }
+                                }
+                            }
+                            reviewRequests(first: 20) {
+                                nodes {
+                                    requestedReviewer {
+                                        ... on User {
+                                            login
+                                        }
+                                    }
+                                }
+                            }
+                            closingIssuesReferences(first: 20) {
+                                nodes {
+                                    url
+                                }
+                            }
+                            labels(first: 20) {
+                                nodes {
+                                    name
+                                }
+                            }
+                        }
+                    }
+                }
+                pageInfo {
+                    endCursor
+                    hasNextPage
+                }
+            }
+        }
+    "#;
+
+    let nodes_vec = client
+        .graphql_search::<Node>(query, search_query, "pull request search")
+        .await?;
+
+    let mut pull_vec = Vec::new();
+    for node in nodes_vec {
+        let (Some(number), Some(html_url), Some(created_at)) =
+            (node.number, node.url, node.created_at)
+        else {
+            continue;
+        };
+        let author = node.author.and_then(|a| a.login).unwrap_or_default();
+
+        let mut reviewers = Vec::<String>::new();
+        let review_logins = nodes(node.reviews).filter_map(|r| r.author.and_then(|a| a.login));
+        let requested_logins =
+            nodes(node.review_requests).filter_map(|r| r.requested_reviewer.and_then(|a| a.login));
+        for login in review_logins.chain(requested_logins) {
+            if login != author && !reviewers.contains(&login) {
+                reviewers.push(login);
+            }
+        }
+
+        pull_vec.push(PullRequest {
+            repository: node
+                .repository
+                .map_or(format!("{owner}/{repo}"), |r| r.name_with_owner),
+            number,
+            title: node.title.unwrap_or_default(),
+            html_url,
+            author,
+            body: node.body.unwrap_or_default(),
+            state: node.state.unwrap_or_default(),
+            created_at,
+            merged_at: node.merged_at,
+            additions: node.additions.unwrap_or(0),
+            deletions: node.deletions.unwrap_or(0),
+            changed_files: node.changed_files.unwrap_or(0),
+            reviewers,
+            linked_issues: nodes(node.closing_issues_references)
+                .map(|issue| issue.url)
+                .collect(),
+            labels: nodes(node.labels).map(|label| label.name).collect(),
+        });
+    }
+
+    Ok(pull_vec)
+}
+
+/// Reviews and review comments left on one pull request within a report window.
+#[derive(Debug, Clone)]
+pub struct PullReviews {
+    pub pull: PullRequest,
+    pub reviews: Vec<Review>,
+    pub comments: Vec<ReviewComment>,
+}
+
+pub async fn get_pull_reviews(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    number: u64,
+) -> Result<Vec<Review>, AnalyzerError> {
+    let url = format!("repos/{owner}/{repo}/pulls/{number}/reviews?per_page=100");
+    client
+        .paginate::<Review>(&url, "pull request reviews")
+        .collect()
+        .await
+}
+
+pub async fn get_pull_review_comments(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    number: u64,
+) -> Result<Vec<ReviewComment>, AnalyzerError> {
+    let url = format!("repos/{owner}/{repo}/pulls/{number}/comments?per_page=100");
+    client
+        .paginate::<ReviewComment>(&url, "pull request review comments")
+        .collect()
+        .await
+}
+
+/// Review activity on the repo's pull requests within `range`, limited to
+/// reviews written by `user_name` when given. The count is the number of
+/// reviews found.
+pub async fn get_reviews_in_range(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    user_name: Option<String>,
+    range: &DateRange,
+) -> Result<(usize, Vec<PullReviews>), AnalyzerError> {
+    let updated_str = range.updated_qualifier();
+    let user_str = user_name
+        .as_ref()
+        .map_or(String::new(), |u| format!("reviewed-by:{}", u));
+    let search_query = format!("repo:{owner}/{repo} is:pr {user_str} {updated_str}");
+
+    let by_target = |login: Option<&str>| match &user_name {
+        Some(user_name) => login == Some(user_name.as_str()),
+        None => true,
+    };
+
+    let mut count = 0;
+    let mut reviews_vec = Vec::new();
+    for pull in search_pull_requests(client, owner, repo, &search_query).await? {
+        if !client.has_budget(RATE_LIMIT_RESERVE) {
+            log::warn!("GitHub rate limit nearly exhausted, stopping review collection");
+            break;
+        }
+        let fetched = match get_pull_reviews(client, owner, repo, pull.number).await {
+            Ok(reviews) => get_pull_review_comments(client, owner, repo, pull.number)
+                .await
+                .map(|comments| (reviews, comments)),
+            Err(e) => Err(e),
+        };
+        let (reviews, comments) = match fetched {
+            Ok(fetched) => fetched,
             Err(e) => {
-                log::error!("error: {:?}", e);
+                log::warn!(
+                    "failed to get the reviews of {owner}/{repo}#{}, skipping it: {e}",
+                    pull.number
+                );
+                continue;
+            }
+        };
+        let reviews = reviews
+            .into_iter()
+            .filter(|r| r.submitted_at.is_some_and(|at| range.contains(&at)))
+            .filter(|r| by_target(r.user.as_ref().map(|u| u.login.as_str())))
+            .collect::<Vec<Review>>();
+        let comments = comments
+            .into_iter()
+            .filter(|c| range.contains(&c.created_at))
+            .filter(|c| by_target(c.user.as_ref().map(|u| u.login.as_str())))
+            .collect::<Vec<ReviewComment>>();
+
+        if reviews.is_empty() && comments.is_empty() {
+            continue;
+        }
+        count += reviews.len();
+        reviews_vec.push(PullReviews {
+            pull,
+            reviews,
+            comments,
+        });
+    }
+
+    Ok((count, reviews_vec))
+}
+
+/// The timeline of issue `number`, oldest first: labels, assignments,
+/// references from commits and pull requests, closing and reopening.
+/// Entries that are not [`IssueEvent`]s, such as the commits of a pull
+/// request, are left out.
+pub async fn get_issue_timeline(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    number: u64,
+) -> Result<Vec<IssueEvent>, AnalyzerError> {
+    let url = format!("repos/{owner}/{repo}/issues/{number}/timeline?per_page=100");
+    let items = client
+        .paginate::<serde_json::Value>(&url, "issue timeline")
+        .collect()
+        .await?;
+    Ok(items
+        .into_iter()
+        .filter_map(|item| serde_json::from_value::<IssueEvent>(item).ok())
+        .collect())
+}
+
+/// The timelines of the first 21 `issues`, the ones the report analyzes,
+/// by issue number. Issues whose timeline cannot be fetched are left out.
+pub async fn get_issue_timelines(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    issues: &[Issue],
+) -> HashMap<u64, Vec<IssueEvent>> {
+    let concurrency = analysis_concurrency();
+    let fetched = map_bounded(issues.iter().take(21), concurrency, |issue| async move {
+        if !client.has_budget(RATE_LIMIT_RESERVE + concurrency as u32) {
+            return None;
+        }
+        Some((
+            issue.number,
+            get_issue_timeline(client, owner, repo, issue.number).await,
+        ))
+    })
+    .await;
+
+    let mut timelines = HashMap::new();
+    for fetch in fetched {
+        match fetch {
+            None => {
+                log::warn!(
+                    "GitHub rate limit nearly exhausted, skipping the remaining issue timelines"
+                );
+                break;
+            }
+            Some((number, Ok(events))) => {
+                timelines.insert(number, events);
             }
-            Ok(issue_page) => {
-                issue_vec = issue_page.items;
+            Some((number, Err(e))) => {
+                log::warn!("failed to get the timeline of {owner}/{repo}#{number}: {e}")
             }
-        },
-        None => log::error!("Github issues not found."),
+        }
     }
-    let count = issue_vec.len();
-    Some((count, issue_vec))
+    timelines
 }
 
-pub async fn get_issue_texts(github_token: &str, issue: &Issue) -> Option<String> {
+pub async fn get_issue_texts(
+    client: &GitHubClient,
+    issue: &Issue,
+) -> Result<String, AnalyzerError> {
     let issue_creator_name = &issue.user.login;
     let issue_title = &issue.title;
     let issue_body = match &issue.body {
@@ -479,211 +762,238 @@ pub async fn get_issue_texts(github_token: &str, issue: &Issue) -> Option<String
         issue_creator_name, issue_title, labels, issue_body
     );
 
-    let mut current_page = 1;
-    loop {
-        let url_str = format!("{}/comments?&page={}", issue_url, current_page);
-
-        match github_http_fetch(&github_token, &url_str).await {
-            Some(res) => match serde_json::from_slice::<Vec<Comment>>(res.as_slice()) {
-                Err(_e) => {
-                    log::error!(
-                        "Error parsing Vec<Comment> at page {}: {:?}",
-                        current_page,
-                        _e
-                    );
-                    break;
-                }
-                Ok(comments_obj) => {
-                    if comments_obj.is_empty() {
-                        break;
-                    }
-                    for comment in &comments_obj {
-                        let comment_body = match &comment.body {
-                            Some(body) => squeeze_fit_remove_quoted(body, 300, 0.6),
-                            None => "".to_string(),
-                        };
-                        let commenter = &comment.user.login;
-                        let commenter_input = format!("{} commented: {}", commenter, comment_body);
-                        if all_text_from_issue.len() > 45_000 {
-                            break;
-                        }
-                        all_text_from_issue.push_str(&commenter_input);
-                    }
-                }
-            },
-            None => {
-                break;
+    let mut comments = client.paginate::<Comment>(
+        &format!("{}/comments?per_page=100", issue_url),
+        "issue comments",
+    );
+    'pages: while let Some(comments_obj) = comments.next_page().await? {
+        for comment in &comments_obj {
+            let comment_body = match &comment.body {
+                Some(body) => squeeze_fit_remove_quoted(body, 300, 0.6),
+                None => "".to_string(),
+            };
+            let commenter = &comment.user.login;
+            let commenter_input = format!("{} commented: {}", commenter, comment_body);
+            if all_text_from_issue.len() > 45_000 {
+                break 'pages;
             }
+            all_text_from_issue.push_str(&commenter_input);
         }
-
-        current_page += 1;
     }
 
-    Some(all_text_from_issue)
+    Ok(all_text_from_issue)
 }
 
 pub async fn get_commits_in_range(
-    github_token: &str,
+    client: &GitHubClient,
     owner: &str,
     repo: &str,
     user_name: Option<String>,
-    range: u16,
+    range: &DateRange,
+    branch: Option<String>,
     token: Option<String>,
-) -> Option<(usize, Vec<GitMemory>, Vec<GitMemory>)> {
-    #[derive(Debug, Deserialize, Serialize, Clone)]
-    struct User {
-        login: String,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    struct GithubCommit {
-        sha: String,
-        html_url: String,
-        author: Option<User>,    // made nullable
-        committer: Option<User>, // made nullable
-        commit: CommitDetails,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    struct CommitDetails {
-        author: CommitUserDetails,
-        message: String,
-        // committer: CommitUserDetails,
-    }
-
-    #[derive(Serialize, Deserialize, Debug)]
-    struct CommitUserDetails {
-        date: Option<DateTime<Utc>>,
-    }
+) -> Result<(usize, Vec<GitMemory>, Vec<GitMemory>), AnalyzerError> {
     let token_str = match &token {
         None => String::from(""),
         Some(t) => format!("&token={}", t.as_str()),
     };
-    let base_commit_url =
-        format!("https://api.github.com/repos/{owner}/{repo}/commits?&per_page=100{token_str}");
+    let branch_str = match &branch {
+        None => String::new(),
+        Some(b) => format!("&sha={}", urlencoding::encode(b)),
+    };
+    let since = range.since_str();
+    let until = range.until_str();
+    let commits_url = format!(
+        "repos/{owner}/{repo}/commits?since={since}&until={until}&per_page=100{branch_str}{token_str}"
+    );
 
     let mut git_memory_vec = vec![];
     let mut weekly_git_memory_vec = vec![];
-    let now = Utc::now();
-    let n_days_ago = (now - Duration::days(range as i64)).date_naive();
-    match github_http_fetch(&github_token, &base_commit_url).await {
-        None => {
-            log::error!("Error fetching commits");
-        }
-        Some(res) => match serde_json::from_slice::<Vec<GithubCommit>>(res.as_slice()) {
-            Err(e) => {
-                log::error!("Error parsing commits: {:?}", e);
-            }
-            Ok(commits) => {
-                for commit in commits {
-                    if let Some(commit_date) = &commit.commit.author.date {
-                        if commit_date.date_naive() <= n_days_ago {
-                            continue;
-                        }
-                        weekly_git_memory_vec.push(GitMemory {
-                            memory_type: MemoryType::Commit,
-                            name: commit.author.clone().map_or(String::new(), |au| au.login),
-                            tag_line: commit.commit.message.clone(),
-                            source_url: commit.html_url.clone(),
-                            payload: String::from(""),
-                            date: commit_date.date_naive(),
-                        });
-                        if let Some(user_name) = &user_name {
-                            if let Some(author) = &commit.author {
-                                if author.login.as_str() == user_name {
-                                    git_memory_vec.push(GitMemory {
-                                        memory_type: MemoryType::Commit,
-                                        name: author.login.clone(),
-                                        tag_line: commit.commit.message.clone(),
-                                        source_url: commit.html_url.clone(),
-                                        payload: String::from(""),
-                                        date: commit_date.date_naive(),
-                                    });
-                                }
-                            }
-                        }
-                    }
-                }
+    let commits = client
+        .paginate::<GithubCommit>(&commits_url, "commits")
+        .collect()
+        .await?;
+    for commit in commits {
+        let Some(gm) = commit.to_memory() else {
+            continue;
+        };
+        if let Some(user_name) = &user_name {
+            if gm.name.as_str() == user_name {
+                git_memory_vec.push(gm.clone());
             }
-        },
+        }
+        weekly_git_memory_vec.push(gm);
     }
     if user_name.is_none() {
         git_memory_vec = weekly_git_memory_vec.clone();
     }
     let count = git_memory_vec.len();
-    Some((count, git_memory_vec, weekly_git_memory_vec))
+    Ok((count, git_memory_vec, weekly_git_memory_vec))
 }
 
-pub async fn get_user_repos_in_language(
-    github_token: &str,
-    user: &str,
-    language: &str,
-) -> Option<Vec<Repository>> {
-    #[derive(Debug, Deserialize)]
-    struct Page<T> {
-        pub items: Vec<T>,
-        pub total_count: Option<u64>,
+/// The release published for `tag`, `None` when the tag has no release.
+pub async fn get_release_by_tag(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    tag: &str,
+) -> Result<Option<Release>, AnalyzerError> {
+    let url = format!(
+        "repos/{owner}/{repo}/releases/tags/{}",
+        urlencoding::encode(tag)
+    );
+    match client.get_json::<Release>(&url, "release").await {
+        Ok(release) => Ok(Some(release)),
+        Err(AnalyzerError::NotFound(_)) => Ok(None),
+        Err(e) => Err(e),
     }
+}
 
-    let query = format!("user:{} language:{} sort:stars", user, language);
-    let encoded_query = urlencoding::encode(&query);
+/// The commits reachable from `head` but not from `base`, oldest first.
+/// Both may be tags, branches or SHAs.
+pub async fn compare_refs(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    base: &str,
+    head: &str,
+) -> Result<Vec<GitMemory>, AnalyzerError> {
+    let url = format!(
+        "repos/{owner}/{repo}/compare/{}...{}?per_page=100",
+        urlencoding::encode(base),
+        urlencoding::encode(head)
+    );
+    let commits = client
+        .paginate::<GithubCommit>(&url, &format!("comparison of {base} and {head}"))
+        .items_at("commits")
+        .collect()
+        .await?;
+    Ok(commits.iter().filter_map(GithubCommit::to_memory).collect())
+}
 
-    let mut out: Vec<Repository> = vec![];
-    let mut total_pages = None;
-    let mut current_page = 1;
+/// The merged pull request of `owner/repo` that brought in each of
+/// `commits`, by commit SHA. Commits pushed without a pull request, or whose
+/// pull requests cannot be fetched, are left out.
+pub async fn get_commit_pull_requests(
+    client: &GitHubClient,
+    owner: &str,
+    repo: &str,
+    commits: &[GitMemory],
+) -> HashMap<String, PullRequest> {
+    #[derive(Debug, Deserialize)]
+    struct Login {
+        login: String,
+    }
 
-    loop {
-        let url_str = format!(
-            "https://api.github.com/search/repositories?q={}&page={}",
-            encoded_query, current_page
-        );
+    #[derive(Debug, Deserialize)]
+    struct Label {
+        name: String,
+    }
 
-        match github_http_fetch(&github_token, &url_str).await {
-            Some(res) => match serde_json::from_slice::<Page<Repository>>(res.as_slice()) {
-                Err(_e) => {
-                    log::error!("Error parsing Page<Repository>: {:?}", _e);
-                    break;
-                }
-                Ok(repo_page) => {
-                    if total_pages.is_none() {
-                        if let Some(count) = repo_page.total_count {
-                            total_pages = Some(((count as f64) / 30.0).ceil() as usize);
-                        }
-                    }
+    #[derive(Debug, Deserialize)]
+    struct BaseRepo {
+        full_name: String,
+    }
 
-                    for repo in repo_page.items {
-                        out.push(repo);
-                    }
+    #[derive(Debug, Deserialize)]
+    struct Base {
+        repo: BaseRepo,
+    }
 
-                    current_page += 1;
-                    if current_page > total_pages.unwrap_or(usize::MAX) {
-                        break;
-                    }
-                }
-            },
-            None => {
-                break;
+    #[derive(Debug, Deserialize)]
+    struct Pull {
+        number: u64,
+        title: String,
+        html_url: String,
+        body: Option<String>,
+        created_at: DateTime<Utc>,
+        merged_at: Option<DateTime<Utc>>,
+        user: Option<Login>,
+        #[serde(default)]
+        labels: Vec<Label>,
+        base: Base,
+    }
+
+    let full_name = format!("{owner}/{repo}");
+    let mut pulls = HashMap::new();
+    for commit in commits {
+        if !client.has_budget(RATE_LIMIT_RESERVE) {
+            log::warn!(
+                "GitHub rate limit nearly exhausted, skipping the remaining commit pull requests"
+            );
+            break;
+        }
+        // Commit html_urls end in the full SHA.
+        let sha = commit.source_url.rsplit('/').next().unwrap_or_default();
+        let url = format!("repos/{owner}/{repo}/commits/{sha}/pulls");
+        let found = match client
+            .get_json::<Vec<Pull>>(&url, "commit pull requests")
+            .await
+        {
+            Ok(found) => found,
+            Err(e) => {
+                log::warn!("failed to get the pull requests of commit {sha}: {e}");
+                continue;
             }
+        };
+        let merged = found
+            .into_iter()
+            .find(|pull| pull.merged_at.is_some() && pull.base.repo.full_name == full_name);
+        if let Some(pull) = merged {
+            pulls.insert(
+                sha.to_string(),
+                PullRequest {
+                    repository: full_name.clone(),
+                    number: pull.number,
+                    title: pull.title,
+                    html_url: pull.html_url,
+                    author: pull.user.map_or(String::new(), |u| u.login),
+                    body: pull.body.unwrap_or_default(),
+                    state: "MERGED".to_string(),
+                    created_at: pull.created_at,
+                    merged_at: pull.merged_at,
+                    additions: 0,
+                    deletions: 0,
+                    changed_files: 0,
+                    reviewers: Vec::new(),
+                    linked_issues: Vec::new(),
+                    labels: pull.labels.into_iter().map(|l| l.name).collect(),
+                },
+            );
         }
     }
+    pulls
+}
+
+pub async fn get_user_repos_in_language(
+    client: &GitHubClient,
+    user: &str,
+    language: &str,
+) -> Result<Vec<Repository>, AnalyzerError> {
+    let query = format!("user:{} language:{} sort:stars", user, language);
+    let encoded_query = urlencoding::encode(&query);
+    let url_str = format!("search/repositories?q={}&per_page=100", encoded_query);
+
+    let out = client
+        .paginate::<Repository>(&url_str, "repositories search")
+        .items_at("items")
+        .collect()
+        .await?;
 
     if out.is_empty() {
-        None
+        Err(AnalyzerError::NotFound(format!(
+            "{language} repositories of {user}"
+        )))
     } else {
-        Some(out)
+        Ok(out)
     }
 }
 
 pub async fn get_user_repos_gql(
-    github_token: &str,
+    client: &GitHubClient,
     user_name: &str,
     language: &str,
-) -> Option<String> {
-    #[derive(Debug, Deserialize)]
-    struct Root {
-        data: Data,
-    }
-
+) -> Result<String, AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct Data {
         search: Search,
@@ -724,76 +1034,71 @@ pub async fn get_user_repos_gql(
         total_count: i32,
     }
 
-    let query = format!(
-        r#"
-    query {{
-        search(query: "user:{} language:{}", type: REPOSITORY, first: 100) {{
-            nodes {{
-                ... on Repository {{
+    let query = r#"
+    query($query: String!) {
+        search(query: $query, type: REPOSITORY, first: 100) {
+            nodes {
+                ... on Repository {
                     name
-                    defaultBranchRef {{
-                        target {{
-                            ... on Commit {{
-                                history(first: 0) {{
+                    defaultBranchRef {
+                        target {
+                            ... on Commit {
+                                history(first: 0) {
                                     totalCount
-                                }}
-                            }}
-                        }}
-                    }}
+                                }
+                            }
+                        }
+                    }
                     description
-                    stargazers {{
+                    stargazers {
                         totalCount
-                    }}
-                }}
-            }}
-        }}
-    }}
-    "#,
-        user_name, language
-    );
+                    }
+                }
+            }
+        }
+    }
+    "#;
 
-    let base_url = "https://api.github.com/graphql";
     let mut out = format!("Repos in {language}:\n");
-    match github_http_post(&github_token, base_url, &query).await {
-        None => log::error!("Failed to send the request to {}", base_url.to_string()),
-        Some(response) => match serde_json::from_slice::<Root>(response.as_slice()) {
-            Err(e) => log::error!("Failed to parse the response: {}", e),
-            Ok(repos) => {
-                let mut repos_sorted: Vec<&Node> = repos.data.search.nodes.iter().collect();
-                repos_sorted
-                    .sort_by(|a, b| b.stargazers.total_count.cmp(&a.stargazers.total_count));
-
-                for repo in repos_sorted {
-                    let name_str = format!("Repo: {}", repo.name);
-
-                    let description_str = match &repo.description {
-                        Some(description) => format!("Description: {},", description),
-                        None => String::new(),
-                    };
-
-                    let stars_str = match repo.stargazers.total_count {
-                        0 => String::new(),
-                        count => format!("Stars: {count}"),
-                    };
-
-                    let commits_str = format!(
-                        "Commits: {}",
-                        repo.default_branch_ref.target.history.total_count
-                    );
-
-                    let temp = format!("{name_str} {description_str} {stars_str} {commits_str}\n");
-
-                    out.push_str(&temp);
-                }
+    let search_query = format!("user:{user_name} language:{language}");
+    let repos = client
+        .graphql::<Data>(query, json!({ "query": search_query }), "user repositories")
+        .await?;
 
-                log::info!("Found {} repositories", repos.data.search.nodes.len());
-            }
-        },
+    let mut repos_sorted: Vec<&Node> = repos.search.nodes.iter().collect();
+    repos_sorted.sort_by(|a, b| b.stargazers.total_count.cmp(&a.stargazers.total_count));
+
+    for repo in repos_sorted {
+        let name_str = format!("Repo: {}", repo.name);
+
+        let description_str = match &repo.description {
+            Some(description) => format!("Description: {},", description),
+            None => String::new(),
+        };
+
+        let stars_str = match repo.stargazers.total_count {
+            0 => String::new(),
+            count => format!("Stars: {count}"),
+        };
+
+        let commits_str = format!(
+            "Commits: {}",
+            repo.default_branch_ref.target.history.total_count
+        );
+
+        let temp = format!("{name_str} {description_str} {stars_str} {commits_str}\n");
+
+        out.push_str(&temp);
     }
-    Some(out)
+
+    log::info!("Found {} repositories", repos.search.nodes.len());
+    Ok(out)
 }
 
-pub async fn search_issue(github_token: &str, search_query: &str) -> Option<String> {
+pub async fn search_issue(
+    client: &GitHubClient,
+    search_query: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Debug, Deserialize, Clone)]
     pub struct User {
         login: Option<String>,
@@ -826,247 +1131,133 @@ pub async fn search_issue(github_token: &str, search_query: &str) -> Option<Stri
         updated_at: Option<DateTime<Utc>>,
     }
 
-    #[derive(Debug, Deserialize)]
-    struct IssueNode {
-        node: Option<Issue>,
-    }
-
-    #[derive(Debug, Deserialize, Clone)]
-    struct PageInfo {
-        #[serde(rename = "endCursor")]
-        end_cursor: Option<String>,
-        #[serde(rename = "hasNextPage")]
-        has_next_page: Option<bool>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct SearchResult {
-        edges: Option<Vec<Option<IssueNode>>>,
-        #[serde(rename = "pageInfo")]
-        page_info: Option<PageInfo>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct IssueSearch {
-        search: Option<SearchResult>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct IssueRoot {
-        data: Option<IssueSearch>,
-    }
+    let query = r#"
+        query($query: String!, $first: Int!, $after: String) {
+            search(query: $query, type: ISSUE, first: $first, after: $after) {
+                edges {
+                    node {
+                        ... on Issue {
+                            url
+                            number
+                            state
+                            title
+                            body
+                            author {
+                                login
+                            }
+                            assignees(first: 100) {
+                                edges {
+                                    node {
+                                        login
+                                    }
+                                }
+                            }
+                            authorAssociation
+                            createdAt
+                            updatedAt
+                        }
+                    }
+                }
+                pageInfo {
+                    endCursor
+                    hasNextPage
+                }
+            }
+        }
+    "#;
 
-    let base_url = "https://api.github.com/graphql";
     let mut out = String::from("ISSUES \n");
+    let issues = client
+        .graphql_search::<Issue>(query, search_query, "issue search")
+        .await?;
 
-    let mut cursor = None;
-
-    loop {
-        let query = format!(
-            r#"
-            query {{
-                search(query: "{search_query}", type: ISSUE, first: 100{after}) {{
-                    edges {{
-                        node {{
-                            ... on Issue {{
-                                url
-                                number
-                                state
-                                title
-                                body
-                                author {{
-                                    login
-                                }}
-                                assignees(first: 100) {{
-                                    edges {{
-                                        node {{
-                                            login
-                                        }}
-                                    }}
-                                }}
-                                authorAssociation
-                                createdAt
-                                updatedAt
-                            }}
-                        }}
-                    }}
-                    pageInfo {{
-                        endCursor
-                        hasNextPage
-                      }}
-                }}
-            }}
-            "#,
-            search_query = search_query,
-            after = cursor
-                .as_ref()
-                .map_or(String::new(), |c| format!(r#", after: "{}""#, c))
-        );
-
-        match github_http_post(&github_token, base_url, &query).await {
+    for issue in &issues {
+        let date = match issue.created_at {
+            Some(date) => date.date_naive().to_string(),
             None => {
-                log::error!("Failed to send the request: {}", base_url);
-                break;
+                continue;
             }
-            Some(response) => match serde_json::from_slice::<IssueRoot>(response.as_slice()) {
-                Err(e) => {
-                    log::error!("Failed to parse the response: {}", e);
-                    break;
-                }
-                Ok(results) => {
-                    if let Some(search) = &results.data.as_ref().and_then(|d| d.search.as_ref()) {
-                        if let Some(edges) = &search.edges {
-                            for edge in edges.iter().filter_map(|e| e.as_ref()) {
-                                if let Some(issue) = &edge.node {
-                                    let date = match issue.created_at {
-                                        Some(date) => date.date_naive().to_string(),
-                                        None => {
-                                            continue;
-                                        }
-                                    };
-                                    let title_str = match &issue.title {
-                                        Some(title) => format!("Title: {},", title),
-                                        None => String::new(),
-                                    };
-                                    let url_str = match &issue.url {
-                                        Some(u) => format!("Url: {}", u),
-                                        None => String::new(),
-                                    };
-
-                                    let author_str =
-                                        match issue.clone().author.and_then(|a| a.login) {
-                                            Some(auth) => format!("Author: {},", auth),
-                                            None => String::new(),
-                                        };
-
-                                    let assignees_str = {
-                                        let assignee_names = issue
-                                            .assignees
-                                            .as_ref()
-                                            .and_then(|e| e.edges.as_ref())
-                                            .map_or(Vec::new(), |assignee_edges| {
-                                                assignee_edges
-                                                    .iter()
-                                                    .filter_map(|edge| {
-                                                        edge.as_ref().and_then(|actual_edge| {
-                                                            actual_edge.node.as_ref().and_then(
-                                                                |user| {
-                                                                    user.login.as_ref().map(
-                                                                        |login_str| {
-                                                                            login_str.as_str()
-                                                                        },
-                                                                    )
-                                                                },
-                                                            )
-                                                        })
-                                                    })
-                                                    .collect::<Vec<&str>>()
-                                            });
-
-                                        if !assignee_names.is_empty() {
-                                            format!("Assignees: {},", assignee_names.join(", "))
-                                        } else {
-                                            String::new()
-                                        }
-                                    };
-
-                                    let state_str = match &issue.state {
-                                        Some(s) => format!("State: {},", s),
-                                        None => String::new(),
-                                    };
-
-                                    let body_str = match &issue.body {
-                                        Some(body_text) if body_text.len() > 180 => {
-                                            let truncated_body = body_text
-                                                .chars()
-                                                .take(100)
-                                                .chain(
-                                                    body_text
-                                                        .chars()
-                                                        .skip(body_text.chars().count() - 80),
-                                                )
-                                                .collect::<String>();
-
-                                            format!("Body: {}", truncated_body)
-                                        }
-                                        Some(body_text) => format!("Body: {},", body_text),
-                                        None => String::new(),
-                                    };
+        };
+        let title_str = match &issue.title {
+            Some(title) => format!("Title: {},", title),
+            None => String::new(),
+        };
+        let url_str = match &issue.url {
+            Some(u) => format!("Url: {}", u),
+            None => String::new(),
+        };
 
-                                    let assoc_str = match &issue.author_association {
-                                        Some(association) => {
-                                            format!("Author Association: {}", association)
-                                        }
-                                        None => String::new(),
-                                    };
+        let author_str = match issue.clone().author.and_then(|a| a.login) {
+            Some(auth) => format!("Author: {},", auth),
+            None => String::new(),
+        };
 
-                                    let temp = format!(
-                                            "{title_str} {url_str} Created At: {date} {author_str} {assignees_str}  {state_str} {body_str} {assoc_str}"
-                                        );
+        let assignees_str = {
+            let assignee_names = issue
+                .assignees
+                .as_ref()
+                .and_then(|e| e.edges.as_ref())
+                .map_or(Vec::new(), |assignee_edges| {
+                    assignee_edges
+                        .iter()
+                        .filter_map(|edge| {
+                            edge.as_ref().and_then(|actual_edge| {
+                                actual_edge.node.as_ref().and_then(|user| {
+                                    user.login.as_ref().map(|login_str| login_str.as_str())
+                                })
+                            })
+                        })
+                        .collect::<Vec<&str>>()
+                });
+
+            if !assignee_names.is_empty() {
+                format!("Assignees: {},", assignee_names.join(", "))
+            } else {
+                String::new()
+            }
+        };
 
-                                    out.push_str(&temp);
-                                    out.push_str("\n");
-                                } else {
-                                    continue;
-                                }
-                            }
-                        }
+        let state_str = match &issue.state {
+            Some(s) => format!("State: {},", s),
+            None => String::new(),
+        };
 
-                        if let Some(page_info) = &search.page_info {
-                            if let Some(has_next_page) = page_info.has_next_page {
-                                if has_next_page {
-                                    match &page_info.end_cursor {
-                                        Some(end_cursor) => {
-                                            cursor = Some(end_cursor.clone());
-                                            log::info!(
-                                                    "Fetched a page, moving to next page with cursor: {}",
-                                                    end_cursor
-                                                );
-                                            continue;
-                                        }
-                                        None => {
-                                            log::error!(
-                                                    "Warning: hasNextPage is true, but endCursor is None. This might result in missing data."
-                                                );
-                                            break;
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    break;
-                }
-            },
-        }
-    }
+        let body_str = match &issue.body {
+            Some(body_text) if body_text.len() > 180 => {
+                let truncated_body = body_text
+                    .chars()
+                    .take(100)
+                    .chain(body_text.chars().skip(body_text.chars().count() - 80))
+                    .collect::<String>();
 
-    Some(out)
-}
+                format!("Body: {}", truncated_body)
+            }
+            Some(body_text) => format!("Body: {},", body_text),
+            None => String::new(),
+        };
 
-pub async fn search_repository(github_token: &str, search_query: &str) -> Option<String> {
-    #[derive(Debug, Deserialize)]
-    struct Payload {
-        data: Option<Data>,
-    }
+        let assoc_str = match &issue.author_association {
+            Some(association) => {
+                format!("Author Association: {}", association)
+            }
+            None => String::new(),
+        };
 
-    #[derive(Debug, Deserialize)]
-    struct Data {
-        search: Option<Search>,
-    }
+        let temp = format!(
+                "{title_str} {url_str} Created At: {date} {author_str} {assignees_str}  {state_str} {body_str} {assoc_str}"
+            );
 
-    #[derive(Debug, Deserialize)]
-    struct Search {
-        edges: Option<Vec<Option<Edge>>>,
-        #[serde(rename = "pageInfo")]
-        page_info: Option<PageInfo>,
+        out.push_str(&temp);
+        out.push_str("\n");
     }
 
-    #[derive(Debug, Deserialize)]
-    struct Edge {
-        node: Option<Node>,
-    }
+    Ok(out)
+}
 
+pub async fn search_repository(
+    client: &GitHubClient,
+    search_query: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct Node {
         name: Option<String>,
@@ -1085,174 +1276,98 @@ pub async fn search_repository(github_token: &str, search_query: &str) -> Option
         total_count: Option<u32>,
     }
 
-    #[derive(Debug, Deserialize)]
-    struct PageInfo {
-        #[serde(rename = "endCursor")]
-        end_cursor: Option<String>,
-        #[serde(rename = "hasNextPage")]
-        has_next_page: Option<bool>,
-    }
+    let query = r#"
+        query($query: String!, $first: Int!, $after: String) {
+            search(query: $query, type: REPOSITORY, first: $first, after: $after) {
+                edges {
+                    node {
+                        ... on Repository {
+                            name
+                            description
+                            url
+                            createdAt
+                            stargazers {
+                                totalCount
+                            }
+                            forkCount
+                        }
+                    }
+                }
+                pageInfo {
+                    endCursor
+                    hasNextPage
+                }
+            }
+        }
+    "#;
 
-    let base_url = "https://api.github.com/graphql";
     let mut out = String::from("REPOSITORY \n");
+    let repos = client
+        .graphql_search::<Node>(query, search_query, "repository search")
+        .await?;
 
-    let mut cursor: Option<String> = None;
+    for repo in &repos {
+        let date_str = match &repo.created_at {
+            Some(date) => date.date_naive().to_string(),
+            None => {
+                continue;
+            }
+        };
 
-    loop {
-        let query = format!(
-            r#"
-                query {{
-                    search(query: "{search_query}", type: REPOSITORY, first: 100{after}) {{
-                        edges {{
-                            node {{
-                                ... on Repository {{
-                                    name
-                                    description
-                                    url
-                                    createdAt
-                                    stargazers {{
-                                      totalCount
-                                    }}
-                                    forkCount
-                                }}
-                            }}
-                        }}
-                        pageInfo {{
-                            endCursor
-                            hasNextPage
-                        }}
-                    }}
-                }}
-            "#,
-            search_query = search_query,
-            after = cursor
-                .as_ref()
-                .map_or(String::new(), |c| format!(r#", after: "{}""#, c))
-        );
+        let name_str = match &repo.name {
+            Some(name) => format!("Name: {name},"),
+            None => String::new(),
+        };
 
-        match github_http_post(&github_token, base_url, &query).await {
-            None => {
-                log::error!(
-                    "Failed to send the request to get RepositoryRoot: {}",
-                    base_url
-                );
-                return None;
+        let desc_str = match &repo.description {
+            Some(desc) if desc.len() > 300 => {
+                let truncated_desc = desc
+                    .chars()
+                    .take(180)
+                    .chain(desc.chars().skip(desc.chars().count() - 120))
+                    .collect::<String>();
+
+                format!("Description: {truncated_desc}")
             }
-            Some(response) => match serde_json::from_slice::<Payload>(response.as_slice()) {
-                Err(e) => {
-                    log::error!("Failed to parse the response for RepositoryRoot: {}", e);
-                    return None;
-                }
-                Ok(payload) => {
-                    if let Some(data) = &payload.data {
-                        if let Some(search) = &data.search {
-                            if let Some(edges) = &search.edges {
-                                for edge_option in edges {
-                                    if let Some(edge) = edge_option {
-                                        if let Some(repo) = &edge.node {
-                                            let date_str = match &repo.created_at {
-                                                Some(date) => date.date_naive().to_string(),
-                                                None => {
-                                                    continue;
-                                                }
-                                            };
-
-                                            let name_str = match &repo.name {
-                                                Some(name) => format!("Name: {name},"),
-                                                None => String::new(),
-                                            };
-
-                                            let desc_str = match &repo.description {
-                                                Some(desc) if desc.len() > 300 => {
-                                                    let truncated_desc = desc
-                                                        .chars()
-                                                        .take(180)
-                                                        .chain(
-                                                            desc.chars()
-                                                                .skip(desc.chars().count() - 120),
-                                                        )
-                                                        .collect::<String>();
-
-                                                    format!("Description: {truncated_desc}")
-                                                }
-                                                Some(desc) => format!("Description: {desc},"),
-                                                None => String::new(),
-                                            };
-
-                                            let url_str = match &repo.url {
-                                                Some(url) => format!("Url: {url}"),
-                                                None => String::new(),
-                                            };
-
-                                            let stars_str = match &repo.stargazers {
-                                                Some(sg) => format!(
-                                                    "Stars: {},",
-                                                    sg.total_count.unwrap_or(0)
-                                                ),
-                                                None => String::new(),
-                                            };
-
-                                            let forks_str = match &repo.fork_count {
-                                                Some(fork_count) => format!("Forks: {fork_count}"),
-                                                None => String::new(),
-                                            };
-
-                                            out.push_str(
-                                                    &format!(
-                                                        "{name_str} {desc_str} {url_str} Created At: {date_str} {stars_str} {forks_str}\n"
-                                                    )
-                                                );
-                                        }
-                                    }
-                                }
-                            }
-                            if let Some(page_info) = &search.page_info {
-                                if page_info.has_next_page.unwrap_or(false) {
-                                    cursor = page_info.end_cursor.clone();
-                                } else {
-                                    break;
-                                }
-                            }
-                        }
-                    }
-                }
-            },
+            Some(desc) => format!("Description: {desc},"),
+            None => String::new(),
         };
-    }
 
-    Some(out)
-}
+        let url_str = match &repo.url {
+            Some(url) => format!("Url: {url}"),
+            None => String::new(),
+        };
 
-pub async fn search_discussions_integrated(
-    github_token: &str,
-    search_query: &str,
-    target_person: &Option<String>,
-) -> Option<(String, Vec<GitMemory>)> {
-    #[derive(Debug, Deserialize)]
-    struct DiscussionRoot {
-        data: Option<Data>,
-    }
+        let stars_str = match &repo.stargazers {
+            Some(sg) => format!("Stars: {},", sg.total_count.unwrap_or(0)),
+            None => String::new(),
+        };
 
-    #[derive(Debug, Deserialize)]
-    struct Data {
-        search: Option<Search>,
-    }
+        let forks_str = match &repo.fork_count {
+            Some(fork_count) => format!("Forks: {fork_count}"),
+            None => String::new(),
+        };
 
-    #[derive(Debug, Deserialize)]
-    struct Search {
-        edges: Option<Vec<Option<Edge>>>,
+        out.push_str(&format!(
+            "{name_str} {desc_str} {url_str} Created At: {date_str} {stars_str} {forks_str}\n"
+        ));
     }
 
-    #[derive(Debug, Deserialize)]
-    struct Edge {
-        node: Option<Discussion>,
-    }
+    Ok(out)
+}
 
+pub async fn search_discussions_integrated(
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
+    search_query: &str,
+    target_person: &Option<String>,
+) -> Result<(String, Vec<GitMemory>), AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct Discussion {
         title: Option<String>,
         url: Option<String>,
-        html_url: Option<String>,
         author: Option<Author>,
         body: Option<String>,
         comments: Option<Comments>,
@@ -1283,190 +1398,151 @@ pub async fn search_discussions_integrated(
         login: Option<String>,
     }
 
-    let openai = OpenAIFlows::new();
-
-    let base_url = "https://api.github.com/graphql";
-
-    let query = format!(
-        r#"
-        query {{
-            search(query: "{search_query}", type: DISCUSSION, first: 100) {{
-                edges {{
-                    node {{
-                        ... on Discussion {{
+    let query = r#"
+        query($query: String!, $first: Int!, $after: String) {
+            search(query: $query, type: DISCUSSION, first: $first, after: $after) {
+                edges {
+                    node {
+                        ... on Discussion {
                             title
                             url
-                            html_url
                             body
-                            author {{
+                            author {
                                 login
-                            }}
+                            }
                             createdAt
                             upvoteCount
-                            comments (first: 100) {{
-                                edges {{
-                                    node {{
-                                        author {{
+                            comments (first: 100) {
+                                edges {
+                                    node {
+                                        author {
                                             login
-                                        }}
+                                        }
                                         body
-                                    }}
-                                }}
-                            }}
-                        }}
-                    }}
-                }}
-            }}
-        }}
-        "#,
-        search_query = search_query
-    );
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                pageInfo {
+                    endCursor
+                    hasNextPage
+                }
+            }
+        }
+    "#;
     let mut git_mem_vec = Vec::with_capacity(100);
     let mut text_out = String::from("DISCUSSIONS \n");
 
-    match github_http_post(&github_token, base_url, &query).await {
-        None => {
-            log::error!(
-                "Failed to send the request to get DiscussionRoot: {}",
-                base_url
+    let discussions = client
+        .graphql_search_up_to::<Discussion>(
+            query,
+            search_query,
+            MAX_DISCUSSIONS,
+            "discussion search",
+        )
+        .await?;
+
+    let empty_str = "".to_string();
+
+    for discussion in &discussions {
+        if llm.over_budget() {
+            log::warn!(
+                "token budget used up, stopping after {} discussions",
+                git_mem_vec.len()
             );
-            return None;
+            break;
         }
-        Some(response) => match serde_json::from_slice::<DiscussionRoot>(&response) {
-            Err(e) => {
-                log::error!("Failed to parse the response for DiscussionRoot: {}", e);
-                return None;
-            }
-            Ok(results) => {
-                let empty_str = "".to_string();
-
-                if let Some(search) = results.data?.search {
-                    for edge_option in search.edges?.iter().filter_map(|e| e.as_ref()) {
-                        if let Some(discussion) = &edge_option.node {
-                            let date = discussion.created_at.date_naive();
-                            let title = discussion.title.as_ref().unwrap_or(&empty_str).to_string();
-                            let url = discussion.url.as_ref().unwrap_or(&empty_str).to_string();
-                            let source_url = discussion
-                                .html_url
-                                .as_ref()
-                                .unwrap_or(&empty_str)
-                                .to_string();
-                            let author_login = discussion
-                                .author
-                                .as_ref()
-                                .and_then(|a| a.login.as_ref())
-                                .unwrap_or(&empty_str)
-                                .to_string();
-
-                            let upvotes_str = match discussion.upvote_count {
-                                Some(c) if c > 0 => format!("Upvotes: {}", c),
-                                _ => "".to_string(),
-                            };
-                            let body_text = match discussion.body.as_ref() {
-                                Some(text) => squeeze_fit_remove_quoted(&text, 500, 0.6),
-                                None => "".to_string(),
-                            };
-                            let mut disuccsion_texts = format!(
-                                "Title: '{}' Url: '{}' Body: '{}' Created At: {} {} Author: {}\n",
-                                title, url, body_text, date, upvotes_str, author_login
-                            );
-
-                            if let Some(comments) = &discussion.comments {
-                                if let Some(ref edges) = comments.edges {
-                                    for comment_edge_option in
-                                        edges.iter().filter_map(|e| e.as_ref())
-                                    {
-                                        if let Some(comment) = &comment_edge_option.node {
-                                            let stripped_comment_text = squeeze_fit_remove_quoted(
-                                                &comment.body.as_ref().unwrap_or(&empty_str),
-                                                300,
-                                                0.6,
-                                            );
-                                            let comment_author = comment
-                                                .author
-                                                .as_ref()
-                                                .and_then(|a| a.login.as_ref())
-                                                .unwrap_or(&empty_str);
-                                            disuccsion_texts.push_str(
-                                                &(format!(
-                                                    "{comment_author} comments: '{stripped_comment_text}'\n")),
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                            let disuccsion_texts =
-                                squeeze_fit_post_texts(&disuccsion_texts, 12_000, 0.4);
-
-                            let target_str = match &target_person {
-                                Some(person) => format!("{}'s", person),
-                                None => "key participants'".to_string(),
-                            };
-
-                            let sys_prompt_1 = &format!(
-                                    "Analyze the provided GitHub discussion. Identify the main topic, actions by participants, crucial viewpoints, solutions or consensus reached, and particularly highlight the contributions of specific individuals, especially '{target_str}'. Summarize without being verbose."
-                                );
-
-                            let co = match disuccsion_texts.len() > 12000 {
-                                true => ChatOptions {
-                                    model: chat::ChatModel::GPT35Turbo16K,
-                                    system_prompt: Some(sys_prompt_1),
-                                    restart: true,
-                                    temperature: Some(0.7),
-                                    max_tokens: Some(256),
-                                    ..Default::default()
-                                },
-                                false => ChatOptions {
-                                    model: chat::ChatModel::GPT35Turbo,
-                                    system_prompt: Some(sys_prompt_1),
-                                    restart: true,
-                                    temperature: Some(0.7),
-                                    max_tokens: Some(192),
-                                    ..Default::default()
-                                },
-                            };
-
-                            let usr_prompt_1 = &format!(
-                                    "Analyze the content: {disuccsion_texts}. Briefly summarize the central topic, participants' actions, primary viewpoints, and outcomes. Emphasize the role of '{target_str}' in driving the discussion or reaching a resolution. Aim for a succinct summary that is rich in analysis and under 192 tokens."
-                                );
-
-                            match openai
-                                .chat_completion("discussion99", usr_prompt_1, &co)
-                                .await
-                            {
-                                Ok(r) => {
-                                    text_out.push_str(&(format!("{} {}", url, r.choice)));
-                                    git_mem_vec.push(GitMemory {
-                                        memory_type: MemoryType::Discussion,
-                                        name: author_login,
-                                        tag_line: title,
-                                        source_url: source_url,
-                                        payload: r.choice,
-                                        date: date,
-                                    });
-                                }
+        let date = discussion.created_at.date_naive();
+        let title = discussion.title.as_ref().unwrap_or(&empty_str).to_string();
+        let url = discussion.url.as_ref().unwrap_or(&empty_str).to_string();
+        let author_login = discussion
+            .author
+            .as_ref()
+            .and_then(|a| a.login.as_ref())
+            .unwrap_or(&empty_str)
+            .to_string();
+
+        let upvotes_str = match discussion.upvote_count {
+            Some(c) if c > 0 => format!("Upvotes: {}", c),
+            _ => "".to_string(),
+        };
+        let body_text = match discussion.body.as_ref() {
+            Some(text) => squeeze_fit_remove_quoted(&text, 500, 0.6),
+            None => "".to_string(),
+        };
+        let mut disuccsion_texts = format!(
+            "Title: '{}' Url: '{}' Body: '{}' Created At: {} {} Author: {}\n",
+            title, url, body_text, date, upvotes_str, author_login
+        );
 
-                                Err(_e) => log::error!(
-                                    "Error generating discussion summary #{}: {}",
-                                    url,
-                                    _e
-                                ),
-                            }
-                        }
+        if let Some(comments) = &discussion.comments {
+            if let Some(ref edges) = comments.edges {
+                for comment_edge_option in edges.iter().filter_map(|e| e.as_ref()) {
+                    if let Some(comment) = &comment_edge_option.node {
+                        let stripped_comment_text = squeeze_fit_remove_quoted(
+                            &comment.body.as_ref().unwrap_or(&empty_str),
+                            300,
+                            0.6,
+                        );
+                        let comment_author = comment
+                            .author
+                            .as_ref()
+                            .and_then(|a| a.login.as_ref())
+                            .unwrap_or(&empty_str);
+                        disuccsion_texts.push_str(
+                            &(format!("{comment_author} comments: '{stripped_comment_text}'\n")),
+                        );
                     }
                 }
             }
-        },
-    }
+        }
+        let stage = match config.discussion.fits(&disuccsion_texts) {
+            true => &config.discussion,
+            false => &config.discussion_long,
+        };
+        let disuccsion_texts = stage.fit(&disuccsion_texts);
 
-    if git_mem_vec.is_empty() {
-        None
-    } else {
-        Some((text_out, git_mem_vec))
+        let target_str = match &target_person {
+            Some(person) => format!("{}'s", person),
+            None => "key participants'".to_string(),
+        };
+
+        let sys_prompt_1 = &prompts.render("discussion_system", &[("target", &target_str)])?;
+        let usr_prompt_1 = &prompts.render(
+            "discussion_user",
+            &[("discussion", &disuccsion_texts), ("target", &target_str)],
+        )?;
+
+        let req = stage
+            .request("discussion99")
+            .with_system(sys_prompt_1)
+            .with_user(usr_prompt_1);
+        match generate(llm, &req, "generating discussion summary").await {
+            Ok(choice) => {
+                text_out.push_str(&(format!("{} {}", url, choice)));
+                git_mem_vec.push(GitMemory {
+                    memory_type: MemoryType::Discussion,
+                    name: author_login,
+                    tag_line: title,
+                    source_url: url,
+                    payload: choice,
+                    date: date,
+                });
+            }
+
+            Err(_e) => log::error!("Error generating discussion summary #{}: {}", url, _e),
+        }
     }
+
+    Ok((text_out, git_mem_vec))
 }
 
-pub async fn search_users(github_token: &str, search_query: &str) -> Option<String> {
+pub async fn search_users(
+    client: &GitHubClient,
+    search_query: &str,
+) -> Result<String, AnalyzerError> {
     #[derive(Debug, Deserialize)]
     struct User {
         name: Option<String>,
@@ -1482,36 +1558,14 @@ pub async fn search_users(github_token: &str, search_query: &str) -> Option<Stri
         email: Option<String>,
     }
 
-    #[derive(Debug, Deserialize)]
-    struct UserNode {
-        node: Option<User>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct UserEdge {
-        edges: Option<Vec<Option<UserNode>>>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct UserSearch {
-        search: Option<UserEdge>,
-    }
-
-    #[derive(Debug, Deserialize)]
-    struct UserRoot {
-        data: Option<UserSearch>,
-    }
-
-    let base_url = "https://api.github.com/graphql";
     let mut out = String::from("USERS: \n");
 
-    let query = format!(
-        r#"
-        query {{
-            search(query: "{search_query}", type: USER, first: 100) {{
-                edges {{
-                    node {{
-                        ... on User {{
+    let query = r#"
+        query($query: String!, $first: Int!, $after: String) {
+            search(query: $query, type: USER, first: $first, after: $after) {
+                edges {
+                    node {
+                        ... on User {
                             name
                             login
                             url
@@ -1521,95 +1575,76 @@ pub async fn search_users(github_token: &str, search_query: &str) -> Option<Stri
                             location
                             createdAt
                             email
-                        }}
-                    }}
-                }}
-            }}
-        }}
-        "#,
-        search_query = search_query
-    );
-
-    match github_http_post(&github_token, base_url, &query).await {
-        None => {
-            log::error!("Failed to send the request to get UserRoot: {}", base_url);
-            return None;
-        }
-        Some(res) => match serde_json::from_slice::<UserRoot>(res.as_slice()) {
-            Err(e) => {
-                log::error!("Failed to parse the response for UserRoot: {}", e);
-                return None;
-            }
-            Ok(results) => {
-                if let Some(search) = &results.data {
-                    if let Some(edges) = &search.search {
-                        for edge_option in edges.edges.as_ref().unwrap_or(&vec![]) {
-                            if let Some(edge) = edge_option {
-                                if let Some(user) = &edge.node {
-                                    let login_str = match &user.login {
-                                        Some(login) => format!("Login: {},", login),
-                                        None => {
-                                            continue;
-                                        }
-                                    };
-                                    let name_str = match &user.name {
-                                        Some(name) => format!("Name: {},", name),
-                                        None => String::new(),
-                                    };
-
-                                    let url_str = match &user.url {
-                                        Some(url) => format!("Url: {},", url),
-                                        None => String::new(),
-                                    };
-
-                                    let twitter_str = match &user.twitter_username {
-                                        Some(twitter) => format!("Twitter: {},", twitter),
-                                        None => String::new(),
-                                    };
-
-                                    let bio_str = match &user.bio {
-                                        Some(bio) => format!("Bio: {},", bio),
-                                        None => String::new(),
-                                    };
-
-                                    let company_str = match &user.company {
-                                        Some(company) => format!("Company: {},", company),
-                                        None => String::new(),
-                                    };
-
-                                    let location_str = match &user.location {
-                                        Some(location) => format!("Location: {},", location),
-                                        None => String::new(),
-                                    };
-
-                                    let date_str = match &user.created_at {
-                                        Some(date) => {
-                                            format!(
-                                                "Created At: {},",
-                                                date.date_naive().to_string()
-                                            )
-                                        }
-                                        None => String::new(),
-                                    };
-
-                                    let email_str = match &user.email {
-                                        Some(email) => format!("Email: {}", email),
-                                        None => String::new(),
-                                    };
-
-                                    out.push_str(
-                                            &format!(
-                                                "{name_str} {login_str} {url_str} {twitter_str} {bio_str} {company_str} {location_str} {date_str} {email_str}\n"
-                                            )
-                                        );
-                                }
-                            }
                         }
                     }
                 }
+                pageInfo {
+                    endCursor
+                    hasNextPage
+                }
+            }
+        }
+    "#;
+
+    let users = client
+        .graphql_search::<User>(query, search_query, "user search")
+        .await?;
+
+    for user in &users {
+        let login_str = match &user.login {
+            Some(login) => format!("Login: {},", login),
+            None => {
+                continue;
+            }
+        };
+        let name_str = match &user.name {
+            Some(name) => format!("Name: {},", name),
+            None => String::new(),
+        };
+
+        let url_str = match &user.url {
+            Some(url) => format!("Url: {},", url),
+            None => String::new(),
+        };
+
+        let twitter_str = match &user.twitter_username {
+            Some(twitter) => format!("Twitter: {},", twitter),
+            None => String::new(),
+        };
+
+        let bio_str = match &user.bio {
+            Some(bio) => format!("Bio: {},", bio),
+            None => String::new(),
+        };
+
+        let company_str = match &user.company {
+            Some(company) => format!("Company: {},", company),
+            None => String::new(),
+        };
+
+        let location_str = match &user.location {
+            Some(location) => format!("Location: {},", location),
+            None => String::new(),
+        };
+
+        let date_str = match &user.created_at {
+            Some(date) => {
+                format!("Created At: {},", date.date_naive().to_string())
             }
-        },
+            None => String::new(),
+        };
+
+        let email_str = match &user.email {
+            Some(email) => format!("Email: {}", email),
+            None => String::new(),
+        };
+
+        out.push_str(
+                                &format!(
+                                    "{name_str} {login_str} {url_str} {twitter_str} {bio_str} {company_str} {location_str} {date_str} {email_str}\n"
+                                )
+                            );
     }
 
-    Some(out)
+    Ok(out)
 }
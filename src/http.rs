@@ -0,0 +1,118 @@
+use crate::error::AnalyzerError;
+use futures::future::LocalBoxFuture;
+use http_req::{request, request::Request, uri::Uri};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Method {
+    Get,
+    Post,
+}
+
+/// One outgoing request, as the GitHub and LLM clients build it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpRequest {
+    pub method: Method,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl HttpRequest {
+    pub fn new(method: Method, url: &str) -> Self {
+        HttpRequest {
+            method,
+            url: url.to_string(),
+            headers: Vec::new(),
+            body: None,
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn with_body(mut self, body: &[u8]) -> Self {
+        self.body = Some(body.to_vec());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Value of the first header called `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Sends requests for [`GitHubClient`](crate::github_client::GitHubClient)
+/// and [`OpenAiCompatLlm`](crate::llm::OpenAiCompatLlm). Any status is a
+/// response; only failing to get one is an error.
+pub trait HttpTransport: Send + Sync {
+    fn send<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+    ) -> LocalBoxFuture<'a, Result<HttpResponse, AnalyzerError>>;
+}
+
+/// http_req over WasmEdge's WASI sockets, the transport of every client by default.
+/// http_req reads and writes the socket synchronously, so each request holds
+/// the thread until its response is in; concurrent callers take turns.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WasiHttp;
+
+impl HttpTransport for WasiHttp {
+    fn send<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+    ) -> LocalBoxFuture<'a, Result<HttpResponse, AnalyzerError>> {
+        Box::pin(async move {
+            let uri = parse_uri(&req.url)?;
+            let mut writer = Vec::new();
+            let mut request = Request::new(&uri);
+            request.method(match req.method {
+                Method::Get => request::Method::GET,
+                Method::Post => request::Method::POST,
+            });
+            for (name, value) in &req.headers {
+                request.header(name, value);
+            }
+            if let Some(body) = &req.body {
+                request.header("Content-Length", &body.len()).body(body);
+            }
+            let res = request
+                .send(&mut writer)
+                .map_err(|e| AnalyzerError::Transport(format!("{}: {e}", req.url)))?;
+
+            Ok(HttpResponse {
+                status: u16::from(res.status_code()),
+                headers: res
+                    .headers()
+                    .iter()
+                    .map(|(name, value)| (name.to_string(), value.to_string()))
+                    .collect(),
+                body: writer,
+            })
+        })
+    }
+}
+
+pub(crate) fn parse_uri(url: &str) -> Result<Uri<'_>, AnalyzerError> {
+    Uri::try_from(url).map_err(|e| AnalyzerError::Transport(format!("invalid url {url}: {e}")))
+}
@@ -0,0 +1,209 @@
+use crate::octocrab_compat::{Event, IssueEvent, IssueStateReason, User};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// What happened to an issue in one step of its timeline.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    Labeled {
+        label: String,
+    },
+    Unlabeled {
+        label: String,
+    },
+    Assigned {
+        assignee: String,
+    },
+    Unassigned {
+        assignee: String,
+    },
+    /// A commit message mentioned the issue.
+    ReferencedByCommit {
+        sha: String,
+    },
+    /// A pull request mentioned the issue.
+    ReferencedByPull {
+        number: u64,
+        title: String,
+        url: String,
+    },
+    /// `pull` is the pull request that last referenced the issue when it was
+    /// closed by a commit, which is how GitHub records a merged "Fixes #n".
+    Closed {
+        pull: Option<u64>,
+        sha: Option<String>,
+        not_planned: bool,
+    },
+    Reopened,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LifecycleStep {
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub event: LifecycleEvent,
+}
+
+/// How an issue got where it is: who labeled and assigned it, which commits
+/// and pull requests referenced it, and who closed or reopened it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IssueLifecycle {
+    pub steps: Vec<LifecycleStep>,
+}
+
+impl IssueLifecycle {
+    /// Reads the steps from an issue's timeline, see
+    /// [`get_issue_timeline`](crate::github_data_fetchers::get_issue_timeline).
+    /// Comments and other events are skipped.
+    pub fn from_events(events: &[IssueEvent]) -> Self {
+        let mut events = events.iter().collect::<Vec<&IssueEvent>>();
+        events.sort_by_key(|e| e.created_at);
+
+        let mut steps = Vec::new();
+        let mut last_pull = None;
+        for e in events {
+            let event = match e.event {
+                Some(Event::Labeled) | Some(Event::Unlabeled) => {
+                    let Some(label) = e.label.as_ref().map(|l| l.name.clone()) else {
+                        continue;
+                    };
+                    match e.event {
+                        Some(Event::Labeled) => LifecycleEvent::Labeled { label },
+                        _ => LifecycleEvent::Unlabeled { label },
+                    }
+                }
+                Some(Event::Assigned) => LifecycleEvent::Assigned {
+                    assignee: login(&e.assignee),
+                },
+                Some(Event::Unassigned) => LifecycleEvent::Unassigned {
+                    assignee: login(&e.assignee),
+                },
+                Some(Event::Referenced) => match &e.commit_id {
+                    Some(sha) => LifecycleEvent::ReferencedByCommit { sha: sha.clone() },
+                    None => continue,
+                },
+                Some(Event::CrossReferenced) => {
+                    let Some(pull) = e
+                        .source
+                        .as_ref()
+                        .and_then(|s| s.issue.as_ref())
+                        .filter(|issue| issue.pull_request.is_some())
+                    else {
+                        continue;
+                    };
+                    last_pull = Some(pull.number);
+                    LifecycleEvent::ReferencedByPull {
+                        number: pull.number,
+                        title: pull.title.clone(),
+                        url: pull.html_url.clone(),
+                    }
+                }
+                Some(Event::Closed) => LifecycleEvent::Closed {
+                    pull: e.commit_id.as_ref().and(last_pull),
+                    sha: e.commit_id.clone(),
+                    not_planned: e.state_reason == Some(IssueStateReason::NotPlanned),
+                },
+                Some(Event::Reopened) => LifecycleEvent::Reopened,
+                _ => continue,
+            };
+            steps.push(LifecycleStep {
+                at: e.created_at,
+                actor: e.actor.login.clone(),
+                event,
+            });
+        }
+        IssueLifecycle { steps }
+    }
+
+    /// The step that closed the issue, unless it was reopened since.
+    pub fn resolution(&self) -> Option<&LifecycleStep> {
+        self.steps
+            .iter()
+            .rev()
+            .find(|step| {
+                matches!(
+                    step.event,
+                    LifecycleEvent::Closed { .. } | LifecycleEvent::Reopened
+                )
+            })
+            .filter(|step| matches!(step.event, LifecycleEvent::Closed { .. }))
+    }
+
+    /// The steps as text for an issue prompt, ending with how and by whom
+    /// the issue was resolved. Empty when nothing happened.
+    pub fn describe(&self) -> String {
+        if self.steps.is_empty() {
+            return String::new();
+        }
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| format!("{} ({})", step.describe(), step.at.format("%Y-%m-%d")))
+            .collect::<Vec<String>>()
+            .join("; ");
+        let outcome = match self.resolution() {
+            Some(step) => format!(
+                "Resolution: {} on {}.",
+                step.describe(),
+                step.at.format("%Y-%m-%d")
+            ),
+            None if self
+                .steps
+                .iter()
+                .any(|step| step.event == LifecycleEvent::Reopened) =>
+            {
+                "It was reopened and is not resolved.".to_string()
+            }
+            None => "It is not resolved.".to_string(),
+        };
+        format!("Timeline: {steps}. {outcome}")
+    }
+}
+
+impl LifecycleStep {
+    pub fn describe(&self) -> String {
+        let actor = &self.actor;
+        match &self.event {
+            LifecycleEvent::Labeled { label } => format!("{actor} labeled it '{label}'"),
+            LifecycleEvent::Unlabeled { label } => format!("{actor} removed the label '{label}'"),
+            LifecycleEvent::Assigned { assignee } if assignee == actor => {
+                format!("{actor} self-assigned it")
+            }
+            LifecycleEvent::Assigned { assignee } => format!("{actor} assigned it to {assignee}"),
+            LifecycleEvent::Unassigned { assignee } => format!("{actor} unassigned {assignee}"),
+            LifecycleEvent::ReferencedByCommit { sha } => {
+                format!("{actor} referenced it in commit {}", short_sha(sha))
+            }
+            LifecycleEvent::ReferencedByPull { number, title, .. } => {
+                format!("{actor} referenced it from pull request #{number} '{title}'")
+            }
+            LifecycleEvent::Closed {
+                pull,
+                sha,
+                not_planned,
+            } => {
+                let how = match (pull, sha) {
+                    (Some(number), _) => format!(" via pull request #{number}"),
+                    (None, Some(sha)) => format!(" with commit {}", short_sha(sha)),
+                    (None, None) => String::new(),
+                };
+                let reason = match not_planned {
+                    true => " as not planned",
+                    false => "",
+                };
+                format!("{actor} closed it{how}{reason}")
+            }
+            LifecycleEvent::Reopened => format!("{actor} reopened it"),
+        }
+    }
+}
+
+fn login(user: &Option<User>) -> String {
+    user.as_ref()
+        .map_or("ghost".to_string(), |u| u.login.clone())
+}
+
+pub(crate) fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
@@ -0,0 +1,341 @@
+use crate::github_data_fetchers::{GitMemory, PullRequest};
+use crate::issue_lifecycle::short_sha;
+use crate::octocrab_compat::{Event, IssueEvent};
+use serde::Serialize;
+use std::fmt;
+
+/// Keywords that close the issue they precede, see
+/// https://docs.github.com/en/issues/tracking-your-work-with-issues/linking-a-pull-request-to-an-issue
+const CLOSING_KEYWORDS: [&str; 9] = [
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// An issue or pull request, `owner/repo#number`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct IssueRef {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+}
+
+impl IssueRef {
+    pub fn new(owner: &str, repo: &str, number: u64) -> Self {
+        IssueRef {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number,
+        }
+    }
+
+    /// Reads `github.com/{owner}/{repo}/issues/{n}` and `/pull/{n}` urls, and
+    /// their `api.github.com/repos/...` counterparts.
+    pub fn from_url(url: &str) -> Option<Self> {
+        let path = url.split_once("github.com/")?.1;
+        let path = path.strip_prefix("repos/").unwrap_or(path);
+        let mut parts = path.split('/');
+        let (owner, repo, kind, number) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        if !matches!(kind, "issues" | "pull" | "pulls") || owner.is_empty() || repo.is_empty() {
+            return None;
+        }
+        let digits = number
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or(number, |end| &number[..end]);
+        let number = digits.parse().ok().filter(|n| *n > 0)?;
+        Some(IssueRef::new(owner, repo, number))
+    }
+}
+
+impl fmt::Display for IssueRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}#{}", self.owner, self.repo, self.number)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    Mentions,
+    /// Preceded by a closing keyword, or linked to close the issue on GitHub.
+    Closes,
+}
+
+/// Where a link was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkEvidence {
+    /// The commit message or pull request title and body.
+    Text,
+    /// GitHub's closing issue references of a pull request.
+    ClosingReference,
+    /// A `cross-referenced`, `connected` or `referenced` timeline event.
+    Timeline,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IssueLink {
+    /// `commit a1b2c3d` or `pull request octo-org/demo#13`.
+    pub from: String,
+    pub from_url: String,
+    pub author: String,
+    pub issue: IssueRef,
+    pub kind: LinkKind,
+    pub evidence: LinkEvidence,
+}
+
+impl IssueLink {
+    pub fn describe(&self) -> String {
+        let verb = match self.kind {
+            LinkKind::Mentions => "mentions",
+            LinkKind::Closes => "closes",
+        };
+        let evidence = match self.evidence {
+            LinkEvidence::Text => "its text",
+            LinkEvidence::ClosingReference => "GitHub's closing references",
+            LinkEvidence::Timeline => "the issue timeline",
+        };
+        let author = match self.author.is_empty() {
+            true => String::new(),
+            false => format!(" by {}", self.author),
+        };
+        format!(
+            "{}{author} {verb} {} (from {evidence})",
+            self.from, self.issue
+        )
+    }
+}
+
+/// Which commits and pull requests refer to which issues, read from GitHub
+/// data rather than guessed by the model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LinkGraph {
+    #[serde(skip)]
+    owner: String,
+    #[serde(skip)]
+    repo: String,
+    pub links: Vec<IssueLink>,
+}
+
+impl LinkGraph {
+    /// A graph of `owner/repo`, against which bare `#123` references resolve.
+    pub fn new(owner: &str, repo: &str) -> Self {
+        LinkGraph {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            links: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// Links from the message of a commit, as returned by
+    /// [`get_commits_in_range`](crate::github_data_fetchers::get_commits_in_range).
+    pub fn add_commit(&mut self, commit: &GitMemory) {
+        let sha = commit.source_url.rsplit('/').next().unwrap_or_default();
+        let from = format!("commit {}", short_sha(sha));
+        for (issue, kind) in parse_references(&commit.tag_line, &self.owner, &self.repo) {
+            self.add(IssueLink {
+                from: from.clone(),
+                from_url: commit.source_url.clone(),
+                author: commit.name.clone(),
+                issue,
+                kind,
+                evidence: LinkEvidence::Text,
+            });
+        }
+    }
+
+    pub fn add_pull_request(&mut self, pull: &PullRequest) {
+        let (owner, repo) = pull
+            .repository
+            .split_once('/')
+            .unwrap_or((&self.owner, &self.repo));
+        let this = IssueRef::new(owner, repo, pull.number);
+        let from = format!("pull request {this}");
+        let text = format!("{}\n{}", pull.title, pull.body);
+        let found = parse_references(&text, owner, repo)
+            .into_iter()
+            .map(|(issue, kind)| (issue, kind, LinkEvidence::Text))
+            .chain(pull.linked_issues.iter().filter_map(|url| {
+                let issue = IssueRef::from_url(url)?;
+                Some((issue, LinkKind::Closes, LinkEvidence::ClosingReference))
+            }))
+            .collect::<Vec<_>>();
+        for (issue, kind, evidence) in found {
+            if issue == this {
+                continue;
+            }
+            self.add(IssueLink {
+                from: from.clone(),
+                from_url: pull.html_url.clone(),
+                author: pull.author.clone(),
+                issue,
+                kind,
+                evidence,
+            });
+        }
+    }
+
+    /// Links from the timeline of `issue`, see
+    /// [`get_issue_timeline`](crate::github_data_fetchers::get_issue_timeline).
+    /// A `connected` event marks the pull request that last referenced the
+    /// issue as closing it, since the REST timeline does not name it.
+    pub fn add_timeline(&mut self, issue: &IssueRef, events: &[IssueEvent]) {
+        let mut last_pull = None;
+        for e in events {
+            match e.event {
+                Some(Event::CrossReferenced) => {
+                    let Some(source) = e.source.as_ref().and_then(|s| s.issue.as_ref()) else {
+                        continue;
+                    };
+                    let Some(this) = IssueRef::from_url(&source.url) else {
+                        continue;
+                    };
+                    let is_pull = source.pull_request.is_some();
+                    let closes = source.body.as_deref().is_some_and(|body| {
+                        parse_references(body, &this.owner, &this.repo)
+                            .contains(&(issue.clone(), LinkKind::Closes))
+                    });
+                    let from = match is_pull {
+                        true => format!("pull request {this}"),
+                        false => format!("issue {this}"),
+                    };
+                    if is_pull {
+                        last_pull = Some(from.clone());
+                    }
+                    self.add(IssueLink {
+                        from,
+                        from_url: source.html_url.clone(),
+                        author: source.user.login.clone(),
+                        issue: issue.clone(),
+                        kind: match closes && is_pull {
+                            true => LinkKind::Closes,
+                            false => LinkKind::Mentions,
+                        },
+                        evidence: LinkEvidence::Timeline,
+                    });
+                }
+                Some(Event::Connected) => {
+                    if let Some(link) = self
+                        .links
+                        .iter_mut()
+                        .find(|l| Some(&l.from) == last_pull.as_ref() && l.issue == *issue)
+                    {
+                        link.kind = LinkKind::Closes;
+                        link.evidence = LinkEvidence::Timeline;
+                    }
+                }
+                Some(Event::Referenced) => {
+                    let Some(sha) = &e.commit_id else {
+                        continue;
+                    };
+                    self.add(IssueLink {
+                        from: format!("commit {}", short_sha(sha)),
+                        from_url: e.commit_url.clone().unwrap_or_default(),
+                        author: e.actor.login.clone(),
+                        issue: issue.clone(),
+                        kind: LinkKind::Mentions,
+                        evidence: LinkEvidence::Timeline,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds `link` unless the same source already links the same issue;
+    /// a closing link replaces a mention.
+    fn add(&mut self, link: IssueLink) {
+        match self
+            .links
+            .iter_mut()
+            .find(|l| l.from == link.from && l.issue == link.issue)
+        {
+            Some(known) if known.kind < link.kind => *known = link,
+            Some(_) => {}
+            None => self.links.push(link),
+        }
+    }
+
+    /// One line per link, for the synthesis prompt and text reports.
+    pub fn facts(&self) -> String {
+        self.links
+            .iter()
+            .map(IssueLink::describe)
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// The issues `text` refers to as `#123`, `owner/repo#45` or a full issue
+/// or pull request url, each with whether a closing keyword precedes it.
+/// Bare numbers resolve against `owner/repo`.
+pub fn parse_references(text: &str, owner: &str, repo: &str) -> Vec<(IssueRef, LinkKind)> {
+    let mut found = Vec::<(IssueRef, LinkKind)>::new();
+    let mut push = |issue: IssueRef, start: usize| {
+        let kind = match follows_closing_keyword(&text[..start]) {
+            true => LinkKind::Closes,
+            false => LinkKind::Mentions,
+        };
+        match found.iter_mut().find(|(known, _)| *known == issue) {
+            Some(known) => known.1 = known.1.max(kind),
+            None => found.push((issue, kind)),
+        }
+    };
+
+    for (at, _) in text.match_indices("github.com/") {
+        let start = text[..at]
+            .rfind(|c: char| c.is_whitespace() || c == '(' || c == '<')
+            .map_or(0, |i| i + 1);
+        let end = text[at..]
+            .find(|c: char| c.is_whitespace() || c == ')' || c == '>')
+            .map_or(text.len(), |i| at + i);
+        if let Some(issue) = IssueRef::from_url(&text[start..end]) {
+            push(issue, start);
+        }
+    }
+
+    for (at, _) in text.match_indices('#') {
+        let digits = &text[at + 1..];
+        let len = digits
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(digits.len());
+        let next = digits[len..].chars().next();
+        if len == 0 || next.is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let Some(number) = digits[..len].parse().ok().filter(|n| *n > 0) else {
+            continue;
+        };
+        let start = text[..at]
+            .rfind(|c: char| !(c.is_ascii_alphanumeric() || "-_./".contains(c)))
+            .map_or(0, |i| {
+                i + text[i..].chars().next().map_or(1, char::len_utf8)
+            });
+        let prefix = &text[start..at];
+        let issue = match prefix.split_once('/') {
+            None if prefix.is_empty() && !text[..at].ends_with('&') => {
+                IssueRef::new(owner, repo, number)
+            }
+            Some((o, r)) if !o.is_empty() && !r.is_empty() && !r.contains('/') => {
+                IssueRef::new(o, r, number)
+            }
+            _ => continue,
+        };
+        push(issue, start);
+    }
+    found
+}
+
+/// Whether `before` ends with a closing keyword, optionally followed by a colon.
+fn follows_closing_keyword(before: &str) -> bool {
+    let before = before.trim_end();
+    let before = before.strip_suffix(':').unwrap_or(before).trim_end();
+    let word_start = before
+        .rfind(|c: char| !c.is_ascii_alphabetic())
+        .map_or(0, |i| i + 1);
+    let word = before[word_start..].to_ascii_lowercase();
+    CLOSING_KEYWORDS.contains(&word.as_str())
+}
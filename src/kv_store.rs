@@ -0,0 +1,130 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Key-value persistence used for contributor sets, saved users and the
+/// analysis cache.
+pub trait KvStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<Value>;
+    fn set(&self, key: &str, value: Value);
+}
+
+/// The flows.network store, what the webhook uses when deployed.
+#[cfg(feature = "flows")]
+pub struct FlowsStore;
+
+#[cfg(feature = "flows")]
+impl KvStore for FlowsStore {
+    fn get(&self, key: &str) -> Option<Value> {
+        store_flows::get(key)
+    }
+
+    fn set(&self, key: &str, value: Value) {
+        store_flows::set(key, value, None)
+    }
+}
+
+/// Lives as long as the process; for tests and one-off runs.
+#[derive(Default)]
+pub struct MemoryStore {
+    map: Mutex<HashMap<String, Value>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl KvStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<Value> {
+        self.map.lock().ok()?.get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: Value) {
+        if let Ok(mut map) = self.map.lock() {
+            map.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// A single JSON file holding every key, rewritten on each `set`.
+pub struct FileStore {
+    path: PathBuf,
+    map: Mutex<HashMap<String, Value>>,
+}
+
+impl FileStore {
+    /// Opens `path`, starting empty if it does not exist yet or cannot be read.
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let map = fs::read(&path)
+            .ok()
+            .and_then(|bytes| match serde_json::from_slice(&bytes) {
+                Ok(map) => Some(map),
+                Err(e) => {
+                    log::warn!("ignoring unreadable store {}: {e}", path.display());
+                    None
+                }
+            })
+            .unwrap_or_default();
+        FileStore {
+            path,
+            map: Mutex::new(map),
+        }
+    }
+}
+
+impl KvStore for FileStore {
+    fn get(&self, key: &str) -> Option<Value> {
+        self.map.lock().ok()?.get(key).cloned()
+    }
+
+    fn set(&self, key: &str, value: Value) {
+        let Ok(mut map) = self.map.lock() else {
+            return;
+        };
+        map.insert(key.to_string(), value);
+        let written = serde_json::to_vec(&*map)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| fs::write(&self.path, bytes).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            log::error!("failed to write store {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Picks the backend from the optional `kv_store` environment variable:
+/// `memory`, `file:<path>` or `flows`. Unset means `flows` when built with
+/// the `flows` feature, `memory` otherwise.
+pub fn store_from_env() -> Box<dyn KvStore> {
+    match env::var("kv_store").ok().as_deref().map(str::trim) {
+        Some("memory") => Box::new(MemoryStore::new()),
+        Some(spec) if spec.starts_with("file:") => {
+            Box::new(FileStore::open(spec.trim_start_matches("file:")))
+        }
+        #[cfg(not(feature = "flows"))]
+        Some("flows") => {
+            log::warn!("built without the flows feature, using the memory store");
+            default_store()
+        }
+        Some(spec) if !spec.is_empty() && spec != "flows" => {
+            log::warn!("unknown kv_store {spec}, using the default store");
+            default_store()
+        }
+        _ => default_store(),
+    }
+}
+
+#[cfg(feature = "flows")]
+fn default_store() -> Box<dyn KvStore> {
+    Box::new(FlowsStore)
+}
+
+#[cfg(not(feature = "flows"))]
+fn default_store() -> Box<dyn KvStore> {
+    Box::new(MemoryStore::new())
+}
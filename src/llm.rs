@@ -0,0 +1,476 @@
+use crate::error::AnalyzerError;
+use crate::github_client::backoff;
+use crate::http::{HttpRequest, HttpTransport, Method, WasiHttp};
+use futures::future::LocalBoxFuture;
+#[cfg(feature = "flows")]
+use openai_flows::{
+    chat::{ChatModel, ChatOptions, ResponseFormat, ResponseFormatType},
+    OpenAIFlows,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::{Arc, Mutex};
+
+/// The model most prompts are written for.
+pub const CHAT_MODEL: &str = "gpt-3.5-turbo";
+/// Used where the input does not fit [`CHAT_MODEL`]'s context window.
+pub const LONG_CONTEXT_MODEL: &str = "gpt-3.5-turbo-16k";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: Role,
+    pub content: String,
+}
+
+/// One chat completion; built with [`ChatRequest::new`] and the `with_*` methods.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChatRequest {
+    /// Names the conversation; the flows backend keeps its history under it.
+    pub chat_id: String,
+    /// The pipeline stage sending the request, for usage accounting.
+    pub stage: String,
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u16,
+    pub temperature: f32,
+    /// Asks for the answer as a JSON object (OpenAI's JSON mode). The prompt
+    /// itself must still mention JSON, and not every model supports it.
+    pub json_output: bool,
+}
+
+impl ChatRequest {
+    pub fn new(chat_id: &str, model: &str) -> Self {
+        ChatRequest {
+            chat_id: chat_id.to_string(),
+            stage: String::new(),
+            model: model.to_string(),
+            messages: Vec::new(),
+            max_tokens: 256,
+            temperature: 0.7,
+            json_output: false,
+        }
+    }
+
+    pub fn with_stage(mut self, stage: &str) -> Self {
+        self.stage = stage.to_string();
+        self
+    }
+
+    pub fn with_system(self, content: &str) -> Self {
+        self.with_message(Role::System, content)
+    }
+
+    pub fn with_user(self, content: &str) -> Self {
+        self.with_message(Role::User, content)
+    }
+
+    pub fn with_assistant(self, content: &str) -> Self {
+        self.with_message(Role::Assistant, content)
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u16) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    pub fn with_json_output(mut self, json_output: bool) -> Self {
+        self.json_output = json_output;
+        self
+    }
+
+    fn with_message(mut self, role: Role, content: &str) -> Self {
+        self.messages.push(ChatMessage {
+            role,
+            content: content.to_string(),
+        });
+        self
+    }
+
+    #[cfg(feature = "flows")]
+    fn system_prompt(&self) -> Option<&str> {
+        self.messages
+            .iter()
+            .find(|m| m.role == Role::System)
+            .map(|m| m.content.as_str())
+    }
+
+    fn last_user_prompt(&self) -> &str {
+        self.messages
+            .iter()
+            .rev()
+            .find(|m| m.role == Role::User)
+            .map_or("", |m| m.content.as_str())
+    }
+}
+
+/// A chat completion provider. Every prompt of the analyzers goes through one.
+pub trait LlmClient: Send + Sync {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>>;
+
+    /// Whether the caller's token or cost budget is used up. Analyzers check
+    /// it before each item, the way they check the GitHub rate limit.
+    fn over_budget(&self) -> bool {
+        false
+    }
+
+    /// The model `req` is actually answered by, which clients that pin or
+    /// substitute models report for usage accounting.
+    fn model_for(&self, req: &ChatRequest) -> String {
+        req.model.clone()
+    }
+}
+
+/// openai-flows, using the OpenAI key tied to the flows.network account.
+#[cfg(feature = "flows")]
+pub struct FlowsLlm {
+    retry_times: u8,
+}
+
+#[cfg(feature = "flows")]
+impl Default for FlowsLlm {
+    fn default() -> Self {
+        FlowsLlm { retry_times: 2 }
+    }
+}
+
+#[cfg(feature = "flows")]
+impl FlowsLlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_retry_times(mut self, retry_times: u8) -> Self {
+        self.retry_times = retry_times;
+        self
+    }
+}
+
+#[cfg(feature = "flows")]
+impl LlmClient for FlowsLlm {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        Box::pin(async move {
+            let mut openai = OpenAIFlows::new();
+            openai.set_retry_times(self.retry_times);
+
+            let model = flows_model(&req.model).unwrap_or_else(|| {
+                log::warn!(
+                    "openai-flows has no model {}, using {CHAT_MODEL}",
+                    req.model
+                );
+                ChatModel::GPT35Turbo
+            });
+
+            // Follow-up turns continue the conversation stored under chat_id;
+            // the earlier turns are passed along as the system prompt.
+            let restart = !req.messages.iter().any(|m| m.role == Role::Assistant);
+            let history = match restart {
+                true => None,
+                false => {
+                    let turns = &req.messages[..req.messages.len().saturating_sub(1)];
+                    Some(json!(turns).to_string())
+                }
+            };
+            let co = ChatOptions {
+                model,
+                restart,
+                system_prompt: history.as_deref().or(req.system_prompt()),
+                max_tokens: Some(req.max_tokens),
+                temperature: Some(req.temperature),
+                response_format: req.json_output.then_some(ResponseFormat {
+                    r#type: ResponseFormatType::JsonObject,
+                }),
+                ..Default::default()
+            };
+
+            openai
+                .chat_completion(&req.chat_id, req.last_user_prompt(), &co)
+                .await
+                .map(|r| r.choice)
+                .map_err(|e| AnalyzerError::Llm(e.to_string()))
+        })
+    }
+
+    fn model_for(&self, req: &ChatRequest) -> String {
+        match flows_model(&req.model) {
+            Some(_) => req.model.clone(),
+            None => CHAT_MODEL.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "flows")]
+fn flows_model(model: &str) -> Option<ChatModel> {
+    match model {
+        "gpt-3.5-turbo" => Some(ChatModel::GPT35Turbo),
+        "gpt-3.5-turbo-16k" => Some(ChatModel::GPT35Turbo16K),
+        "gpt-4" => Some(ChatModel::GPT4),
+        "gpt-4-32k" => Some(ChatModel::GPT4_32K),
+        "gpt-4-1106-preview" => Some(ChatModel::GPT4Turbo),
+        _ => None,
+    }
+}
+
+/// Any server speaking the OpenAI `/chat/completions` API, e.g. OpenAI
+/// itself, a llama.cpp server or Ollama.
+pub struct OpenAiCompatLlm {
+    base_url: String,
+    api_key: Option<String>,
+    model: Option<String>,
+    max_retries: u32,
+    transport: Arc<dyn HttpTransport>,
+}
+
+impl OpenAiCompatLlm {
+    /// `base_url` is the part before `/chat/completions`,
+    /// e.g. `http://localhost:11434/v1`.
+    pub fn new(base_url: &str) -> Self {
+        OpenAiCompatLlm {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            model: None,
+            max_retries: 2,
+            transport: Arc::new(WasiHttp),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Sends every request to `model` regardless of the model it names,
+    /// for servers that only host one.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+}
+
+impl LlmClient for OpenAiCompatLlm {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        #[derive(Deserialize)]
+        struct Completion {
+            choices: Vec<Choice>,
+        }
+
+        #[derive(Deserialize)]
+        struct Choice {
+            message: Message,
+        }
+
+        #[derive(Deserialize)]
+        struct Message {
+            content: Option<String>,
+        }
+
+        Box::pin(async move {
+            let url = format!("{}/chat/completions", self.base_url);
+            let mut body = json!({
+                "model": self.model_for(req),
+                "messages": req.messages,
+                "max_tokens": req.max_tokens,
+                "temperature": req.temperature,
+            });
+            if req.json_output {
+                body["response_format"] = json!({ "type": "json_object" });
+            }
+            let mut request = HttpRequest::new(Method::Post, &url)
+                .with_header("Content-Type", "application/json")
+                .with_body(body.to_string().as_bytes());
+            if let Some(api_key) = &self.api_key {
+                request = request.with_header("Authorization", &format!("Bearer {api_key}"));
+            }
+
+            let mut attempt = 0;
+            loop {
+                let res = self
+                    .transport
+                    .send(&request)
+                    .await
+                    .map_err(|e| AnalyzerError::Llm(format!("failed to reach {url}: {e}")))?;
+
+                let status = res.status;
+                if res.is_success() {
+                    let completion = serde_json::from_slice::<Completion>(&res.body)
+                        .map_err(|e| AnalyzerError::decode("chat completion", e))?;
+                    return completion
+                        .choices
+                        .into_iter()
+                        .next()
+                        .and_then(|c| c.message.content)
+                        .ok_or_else(|| AnalyzerError::Llm(format!("{url} returned no choices")));
+                }
+                if (status == 429 || status >= 500) && attempt < self.max_retries {
+                    let wait = backoff(attempt);
+                    attempt += 1;
+                    log::warn!(
+                        "{url} answered {status}, retry {attempt}/{} in {}s",
+                        self.max_retries,
+                        wait.as_secs()
+                    );
+                    tokio::time::sleep(wait).await;
+                    continue;
+                }
+                return Err(AnalyzerError::Llm(format!(
+                    "{url} answered {status}: {}",
+                    String::from_utf8_lossy(&res.body)
+                )));
+            }
+        })
+    }
+
+    fn model_for(&self, req: &ChatRequest) -> String {
+        self.model.clone().unwrap_or_else(|| req.model.clone())
+    }
+}
+
+/// Answers without any network access: the reply set for the request's
+/// stage, else the queued replies in order, else a canned summary derived
+/// from the request. Records every request it sees.
+#[derive(Default)]
+pub struct FakeLlm {
+    stage_replies: HashMap<String, String>,
+    replies: Mutex<VecDeque<String>>,
+    requests: Mutex<Vec<ChatRequest>>,
+}
+
+impl FakeLlm {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_replies<S: Into<String>>(replies: impl IntoIterator<Item = S>) -> Self {
+        FakeLlm {
+            replies: Mutex::new(replies.into_iter().map(Into::into).collect()),
+            ..Default::default()
+        }
+    }
+
+    /// Answers every request of `stage` with `reply`, e.g. JSON for `synthesis`.
+    pub fn with_stage_reply(mut self, stage: &str, reply: &str) -> Self {
+        self.stage_replies
+            .insert(stage.to_string(), reply.to_string());
+        self
+    }
+
+    pub fn requests(&self) -> Vec<ChatRequest> {
+        self.requests.lock().map(|r| r.clone()).unwrap_or_default()
+    }
+}
+
+impl LlmClient for FakeLlm {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        Box::pin(async move {
+            if let Ok(mut requests) = self.requests.lock() {
+                requests.push(req.clone());
+            }
+            if let Some(reply) = self.stage_replies.get(&req.stage) {
+                return Ok(reply.clone());
+            }
+            let queued = self.replies.lock().ok().and_then(|mut r| r.pop_front());
+            Ok(queued.unwrap_or_else(|| {
+                let words = req
+                    .last_user_prompt()
+                    .split_whitespace()
+                    .take(12)
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+                format!("Summary of {}: {words}", req.chat_id)
+            }))
+        })
+    }
+}
+
+/// Runs `req`, prefixing LLM failures with `context`, e.g.
+/// "generating issue summary #12".
+pub async fn generate(
+    llm: &dyn LlmClient,
+    req: &ChatRequest,
+    context: &str,
+) -> Result<String, AnalyzerError> {
+    llm.chat(req).await.map_err(|e| match e {
+        AnalyzerError::Llm(msg) => AnalyzerError::Llm(format!("{context}: {msg}")),
+        e => e,
+    })
+}
+
+/// Picks the provider from the optional `llm_provider` environment variable:
+/// `openai` (any OpenAI-compatible server at `llm_api_url`, with `llm_api_key`
+/// and `llm_model`), `fake`, or `flows` (openai-flows). Unset means `flows`
+/// when built with the `flows` feature, `openai` otherwise.
+pub fn llm_from_env() -> Box<dyn LlmClient> {
+    match env::var("llm_provider").ok().as_deref().map(str::trim) {
+        Some("openai") => Box::new(openai_from_env()),
+        Some("fake") => Box::new(FakeLlm::new()),
+        #[cfg(not(feature = "flows"))]
+        Some("flows") => {
+            log::warn!("built without the flows feature, using llm_provider openai");
+            default_llm()
+        }
+        Some(provider) if !provider.is_empty() && provider != "flows" => {
+            log::warn!("unknown llm_provider {provider}, using the default one");
+            default_llm()
+        }
+        _ => default_llm(),
+    }
+}
+
+fn openai_from_env() -> OpenAiCompatLlm {
+    let base_url =
+        env::var("llm_api_url").unwrap_or_else(|_| "https://api.openai.com/v1".to_string());
+    let mut llm = OpenAiCompatLlm::new(&base_url);
+    if let Ok(api_key) = env::var("llm_api_key") {
+        llm = llm.with_api_key(&api_key);
+    }
+    if let Ok(model) = env::var("llm_model") {
+        llm = llm.with_model(&model);
+    }
+    llm
+}
+
+#[cfg(feature = "flows")]
+fn default_llm() -> Box<dyn LlmClient> {
+    Box::new(FlowsLlm::new())
+}
+
+#[cfg(not(feature = "flows"))]
+fn default_llm() -> Box<dyn LlmClient> {
+    Box::new(openai_from_env())
+}
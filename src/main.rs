@@ -0,0 +1,300 @@
+//! Runs reports from the command line instead of the flows.network webhook.
+//! Reads the same environment variables as the flow (a `.env` file works too).
+
+use github_analyzer::cassette::{Cassette, CassetteMode};
+use github_analyzer::data_analyzers::{get_repo_info, search_bing};
+use github_analyzer::date_range::DateRange;
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_client::GitHubClient;
+use github_analyzer::github_data_fetchers::{
+    get_user_data_by_login, search_discussions_integrated, search_issue, search_repository,
+    search_users,
+};
+use github_analyzer::http::WasiHttp;
+use github_analyzer::kv_store::{store_from_env, KvStore};
+use github_analyzer::llm::{llm_from_env, LlmClient};
+use github_analyzer::notifier::LogNotifier;
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use github_analyzer::report::ReportFormat;
+use github_analyzer::reports::{release_notes_report, weekly_report};
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::Arc;
+
+const USAGE: &str = "usage: github-analyzer <command> [options]
+
+commands:
+  weekly <owner/repo>      activity report, like the webhook's owner=&repo= request
+      --user <login>         only this contributor's activity
+      --days <n>             the last n days (default 7), or
+      --from <date> --to <date>
+      --branch <name>        commits of this branch instead of the default one
+      --format <f>           text (default), markdown, json or html
+  user <login>             profile of a user, plus a web search when bing_key is set
+  about-repo <owner/repo>  summary of a repository's description and README
+  release-notes <owner/repo> <from-tag> <to-tag>
+                           Markdown changelog of the changes between two tags
+  search <kind> <query>    kind is issues, repos, users or discussions
+
+options of every command:
+  --config <path>          pipeline config TOML, instead of the pipeline_config variable
+  --prompts <path>         prompt overrides TOML, instead of the prompts variable
+  --set <key=value>        override one pipeline config key, e.g. synthesis.model=gpt-4
+  --record <path>          save the GitHub and LLM traffic of the run to a cassette file
+  --replay <path>          answer from a recorded cassette instead of GitHub and the LLM
+
+environment: github_token (required unless replaying), llm_provider (default openai here), llm_api_url,
+llm_api_key, llm_model, kv_store (default memory here), bing_key";
+
+/// Positional arguments and `--flag value` options, in the order given.
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some(name) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| format!("--{name} needs a value"))?;
+                    options.push((name.to_string(), value));
+                }
+                None => positional.push(arg),
+            }
+        }
+        Ok(Args {
+            positional,
+            options,
+        })
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .rev()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn positional(&self, index: usize, what: &str) -> Result<&str, String> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing {what}"))
+    }
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    dotenv::dotenv().ok();
+    // openai-flows and the flows.network store only work inside a flow.
+    for (key, default) in [("llm_provider", "openai"), ("kv_store", "memory")] {
+        if env::var(key).is_err() {
+            env::set_var(key, default);
+        }
+    }
+
+    let args = match Args::parse(env::args().skip(1)) {
+        Ok(args) if !args.positional.is_empty() => args,
+        Ok(_) => {
+            eprintln!("{USAGE}");
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+
+    match run(&args).await {
+        Ok(output) => println!("{output}"),
+        Err(e) => {
+            eprintln!("{e}");
+            process::exit(1);
+        }
+    }
+}
+
+async fn run(args: &Args) -> Result<String, String> {
+    let cassette = match (args.option("record"), args.option("replay")) {
+        (Some(_), Some(_)) => return Err("use either --record or --replay".to_string()),
+        (Some(path), None) => Some(Arc::new(Cassette::record(path))),
+        (None, Some(path)) => Some(Arc::new(Cassette::replay(path).map_err(|e| e.to_string())?)),
+        (None, None) => None,
+    };
+    let replaying = cassette
+        .as_ref()
+        .is_some_and(|cassette| cassette.mode() == CassetteMode::Replay);
+    let github_token = match env::var("github_token") {
+        Ok(token) => token,
+        Err(_) if replaying => String::new(),
+        Err(_) => return Err("github_token is not set".to_string()),
+    };
+    let mut client = GitHubClient::from_env(&github_token);
+    let live_llm = llm_from_env();
+    let cassette_llm;
+    let llm: &dyn LlmClient = match &cassette {
+        Some(cassette) => {
+            client = client.with_transport(cassette.transport(Arc::new(WasiHttp)));
+            cassette_llm = cassette.llm(live_llm.as_ref());
+            &cassette_llm
+        }
+        None => live_llm.as_ref(),
+    };
+    let store = store_from_env();
+
+    let mut config = match args.option("config") {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+            PipelineConfig::from_toml(&text).map_err(|e| e.to_string())?
+        }
+        None => PipelineConfig::from_env(),
+    };
+    for (name, set) in &args.options {
+        if name != "set" {
+            continue;
+        }
+        let (key, value) = set
+            .split_once('=')
+            .ok_or_else(|| format!("--set takes key=value, got {set}"))?;
+        config = config
+            .with_override(key, value)
+            .map_err(|e| e.to_string())?;
+    }
+    let prompts = match args.option("prompts") {
+        Some(path) => {
+            let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+            PromptRegistry::builtin()
+                .with_toml(&text)
+                .map_err(|e| e.to_string())?
+        }
+        None => PromptRegistry::from_env(),
+    };
+
+    let output = run_command(args, &client, llm, store.as_ref(), &config, prompts).await;
+    // A failed run is worth keeping too, to replay up to the failure.
+    if let Some(cassette) = &cassette {
+        if let Err(e) = cassette.save() {
+            eprintln!("{e}");
+        }
+    }
+    output
+}
+
+async fn run_command(
+    args: &Args,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    store: &dyn KvStore,
+    config: &PipelineConfig,
+    prompts: PromptRegistry,
+) -> Result<String, String> {
+    let command = args.positional(0, "command")?;
+    let output = match command {
+        "weekly" => {
+            let full_name = args.positional(1, "owner/repo")?;
+            let (owner, repo) = split_full_name(full_name)?;
+            let range =
+                DateRange::from_params(args.option("days"), args.option("from"), args.option("to"))
+                    .map_err(|e| e.to_string())?;
+            let format = match args.option("format") {
+                Some(format) => ReportFormat::from_param(format)
+                    .ok_or_else(|| format!("unsupported format {format}"))?,
+                None => ReportFormat::Text,
+            };
+            let prompts = prompts.with_store(store, full_name);
+            let report = weekly_report(
+                client,
+                llm,
+                store,
+                &LogNotifier,
+                config,
+                &prompts,
+                owner,
+                repo,
+                args.option("user").map(String::from),
+                &range,
+                args.option("branch").map(String::from),
+                None,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            report.render(format)
+        }
+        "user" => {
+            let login = args.positional(1, "login")?;
+            let profile = get_user_data_by_login(client, login)
+                .await
+                .map_err(|e| e.to_string())?;
+            match env::var("bing_key") {
+                Ok(bing_key) => {
+                    let query = format!("github user {login}");
+                    let search_data = search_bing(&bing_key, &query).await.unwrap_or_default();
+                    format!("Found on profile: {profile}\nFound with search: {search_data}")
+                }
+                Err(_) => profile,
+            }
+        }
+        "about-repo" => {
+            let full_name = args.positional(1, "owner/repo")?;
+            split_full_name(full_name)?;
+            let prompts = prompts.with_store(store, full_name);
+            let summary = get_repo_info(client, llm, config, &prompts, full_name)
+                .await
+                .map_err(|e| e.to_string())?;
+            format!("About {full_name}: {summary}")
+        }
+        "release-notes" => {
+            let full_name = args.positional(1, "owner/repo")?;
+            let (owner, repo) = split_full_name(full_name)?;
+            let from_tag = args.positional(2, "from-tag")?;
+            let to_tag = args.positional(3, "to-tag")?;
+            let prompts = prompts.with_store(store, full_name);
+            let notes = release_notes_report(
+                client, llm, store, config, &prompts, owner, repo, from_tag, to_tag,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            notes.render_markdown()
+        }
+        "search" => {
+            let kind = args.positional(1, "search kind")?;
+            let query = args.positional(2, "search query")?;
+            let res = match kind {
+                "issues" => search_issue(client, query).await,
+                "repos" => search_repository(client, query).await,
+                "users" => search_users(client, query).await,
+                "discussions" => search_discussions_integrated(
+                    client,
+                    llm,
+                    config,
+                    &prompts,
+                    query,
+                    &args.option("user").map(String::from),
+                )
+                .await
+                .map(|(text, _)| text),
+                _ => Err(AnalyzerError::InvalidInput(format!(
+                    "unknown search kind {kind}, use issues, repos, users or discussions"
+                ))),
+            };
+            res.map_err(|e| e.to_string())?
+        }
+        _ => return Err(format!("unknown command {command}\n\n{USAGE}")),
+    };
+    Ok(output)
+}
+
+fn split_full_name(full_name: &str) -> Result<(&str, &str), String> {
+    full_name
+        .split_once('/')
+        .filter(|(owner, repo)| !owner.is_empty() && !repo.is_empty())
+        .ok_or_else(|| format!("expected owner/repo, got {full_name}"))
+}
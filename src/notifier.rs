@@ -0,0 +1,70 @@
+use futures::future::LocalBoxFuture;
+use std::env;
+
+/// Where the analyzers post intermediate data and finished reports, e.g. a
+/// Slack workspace while debugging a flow. Posting never fails the caller.
+pub trait Notifier: Send + Sync {
+    fn notify<'a>(&'a self, channel: &'a str, text: &'a str) -> LocalBoxFuture<'a, ()>;
+}
+
+/// Writes every message to the debug log.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn notify<'a>(&'a self, channel: &'a str, text: &'a str) -> LocalBoxFuture<'a, ()> {
+        Box::pin(async move { log::debug!("[{channel}] {text}") })
+    }
+}
+
+/// Posts to the channels of a Slack workspace connected to the flow.
+#[cfg(feature = "flows")]
+pub struct SlackNotifier {
+    workspace: String,
+}
+
+#[cfg(feature = "flows")]
+impl SlackNotifier {
+    pub fn new(workspace: &str) -> Self {
+        SlackNotifier {
+            workspace: workspace.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "flows")]
+impl Notifier for SlackNotifier {
+    fn notify<'a>(&'a self, channel: &'a str, text: &'a str) -> LocalBoxFuture<'a, ()> {
+        Box::pin(async move {
+            slack_flows::send_message_to_channel(&self.workspace, channel, text.to_string()).await
+        })
+    }
+}
+
+/// Picks the notifier from the optional `notifier` environment variable:
+/// `log` or `slack:<workspace>`. Unset means the `ik8` Slack workspace when
+/// built with the `flows` feature, `log` otherwise.
+pub fn notifier_from_env() -> Box<dyn Notifier> {
+    match env::var("notifier").ok().as_deref().map(str::trim) {
+        Some("log") => Box::new(LogNotifier),
+        #[cfg(feature = "flows")]
+        Some(spec) if spec.starts_with("slack:") => {
+            Box::new(SlackNotifier::new(spec.trim_start_matches("slack:")))
+        }
+        Some(spec) if !spec.is_empty() => {
+            log::warn!("unsupported notifier {spec}, using the default one");
+            default_notifier()
+        }
+        _ => default_notifier(),
+    }
+}
+
+#[cfg(feature = "flows")]
+fn default_notifier() -> Box<dyn Notifier> {
+    Box::new(SlackNotifier::new("ik8"))
+}
+
+#[cfg(not(feature = "flows"))]
+fn default_notifier() -> Box<dyn Notifier> {
+    Box::new(LogNotifier)
+}
@@ -79,6 +79,55 @@ pub struct PullRequestLink {
     pub patch_url: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Review {
+    pub id: u64,
+    pub node_id: String,
+    pub html_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<ReviewState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[non_exhaustive]
+pub enum ReviewState {
+    Approved,
+    Pending,
+    ChangesRequested,
+    Commented,
+    Dismissed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ReviewComment {
+    pub id: u64,
+    pub node_id: String,
+    pub url: String,
+    pub html_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pull_request_review_id: Option<u64>,
+    pub path: String,
+    pub diff_hunk: String,
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<User>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_reply_to_id: Option<u64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Contents {
@@ -103,8 +152,13 @@ pub enum Event {
     AddedToProject,
     Assigned,
     Closed,
+    Commented,
+    Connected,
     ConvertedNoteToIssue,
+    #[serde(rename = "cross-referenced")]
+    CrossReferenced,
     Demilestoned,
+    Disconnected,
     HeadRefDeleted,
     HeadRefForcePushed,
     HeadRefRestored,
@@ -119,6 +173,7 @@ pub enum Event {
     RemovedFromProject,
     Renamed,
     Reopened,
+    Reviewed,
     ReviewDismissed,
     ReviewRequested,
     ReviewRequestRemoved,
@@ -139,7 +194,7 @@ pub enum IssueState {
     Closed,
 }
 
-#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct IssueEvent {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -155,6 +210,9 @@ pub struct IssueEvent {
     pub assigner: Option<User>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub labels: Option<Vec<Label>>,
+    /// Set on `labeled` and `unlabeled` events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<IssueEventLabel>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub milestone: Option<Milestone>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -165,9 +223,29 @@ pub struct IssueEvent {
     pub commit_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub commit_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_reason: Option<IssueStateReason>,
+    /// Set on `cross-referenced` timeline events.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<IssueEventSource>,
     pub created_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IssueEventLabel {
+    pub name: String,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IssueEventSource {
+    pub r#type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue: Option<Issue>,
+}
+
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct ProjectCard {
@@ -180,6 +258,29 @@ pub struct ProjectCard {
     pub column_url: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Release {
+    pub url: String,
+    pub html_url: String,
+    pub id: u64,
+    pub node_id: String,
+    pub tag_name: String,
+    pub target_commitish: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    pub draft: bool,
+    pub prerelease: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<User>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub struct Project {
@@ -197,8 +298,8 @@ pub struct Project {
     pub creator: User,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -218,8 +319,8 @@ pub struct ProjectColumn {
     pub name: String,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Hash, Eq, PartialEq, Serialize, Deserialize)]
@@ -297,8 +398,8 @@ pub struct Milestone {
     pub closed_issues: Option<i64>,
     pub created_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub closed_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -447,8 +548,8 @@ pub struct Repository {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub permissions: Option<Permissions>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -489,8 +590,8 @@ pub struct RepositoryMetrics {
     pub description: Option<String>,
     pub documentation: Option<String>,
     pub files: HashMap<String, Option<RepositoryFile>>,
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
     pub content_reports_enabled: Option<bool>,
 }
 
@@ -608,8 +709,8 @@ pub struct Status {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-       #[serde(rename = "updatedAt")]
-   pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
     pub state: StatusState,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub creator: Option<User>,
@@ -0,0 +1,255 @@
+use crate::error::AnalyzerError;
+use crate::llm::{ChatRequest, CHAT_MODEL, LONG_CONTEXT_MODEL};
+use crate::utils::{count_tokens, squeeze_fit_post_texts};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+
+/// Tokens of every context window held back for the instructions around the input.
+const PROMPT_RESERVE: usize = 512;
+
+/// How an input that is over its stage's budget is cut down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Truncation {
+    /// Keep the beginning.
+    Head,
+    /// Drop the middle, keeping `head_share` of the budget from the beginning.
+    Middle,
+}
+
+/// Model and token budget of one LLM step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageConfig {
+    /// The stage's key in [`PipelineConfig`], for usage accounting.
+    #[serde(skip)]
+    pub name: String,
+    pub model: String,
+    /// Tokens the model accepts, prompt and answer together.
+    pub context_window: usize,
+    pub max_output_tokens: u16,
+    /// Caps the input below what the context window would allow.
+    pub max_input_tokens: Option<usize>,
+    pub truncation: Truncation,
+    pub head_share: f32,
+    /// Requests JSON output from the model for steps that expect JSON; only
+    /// for models with a JSON mode, e.g. `gpt-3.5-turbo` or `gpt-4-1106-preview`.
+    pub json_mode: bool,
+}
+
+impl StageConfig {
+    fn new(model: &str, context_window: usize, max_output_tokens: u16) -> Self {
+        StageConfig {
+            name: String::new(),
+            model: model.to_string(),
+            context_window,
+            max_output_tokens,
+            max_input_tokens: None,
+            truncation: Truncation::Head,
+            head_share: 1.0,
+            json_mode: false,
+        }
+    }
+
+    fn middle(mut self, head_share: f32) -> Self {
+        self.truncation = Truncation::Middle;
+        self.head_share = head_share;
+        self
+    }
+
+    fn max_input(mut self, max_input_tokens: usize) -> Self {
+        self.max_input_tokens = Some(max_input_tokens);
+        self
+    }
+
+    /// Tokens of input the stage's prompt may carry.
+    pub fn input_budget(&self) -> usize {
+        let room = self
+            .context_window
+            .saturating_sub(self.max_output_tokens as usize + PROMPT_RESERVE);
+        self.max_input_tokens.map_or(room, |max| max.min(room))
+    }
+
+    pub fn fits(&self, text: &str) -> bool {
+        count_tokens(text) <= self.input_budget()
+    }
+
+    /// Cuts `text` down to the stage's input budget.
+    pub fn fit(&self, text: &str) -> String {
+        self.fit_to(text, self.input_budget())
+    }
+
+    /// Cuts `text` down to `budget` tokens, for stages whose input has several parts.
+    pub fn fit_to(&self, text: &str, budget: usize) -> String {
+        let head_share = match self.truncation {
+            Truncation::Head => 1.0,
+            Truncation::Middle => self.head_share,
+        };
+        squeeze_fit_post_texts(text, budget, head_share)
+    }
+
+    /// A request to the stage's model, limited to its output budget.
+    pub fn request(&self, chat_id: &str) -> ChatRequest {
+        ChatRequest::new(chat_id, &self.model)
+            .with_stage(&self.name)
+            .with_max_tokens(self.max_output_tokens)
+    }
+}
+
+/// Per-stage models and budgets of a report. The `*_long` stages take over
+/// when there is little to report, so each item can be read in more detail.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineConfig {
+    /// Commits, issues or pull requests up to this count use the `*_long` stages.
+    pub long_context_max_items: usize,
+    pub readme: StageConfig,
+    pub repo_overview: StageConfig,
+    pub commit: StageConfig,
+    pub commit_long: StageConfig,
+    pub issue: StageConfig,
+    pub issue_long: StageConfig,
+    pub pull_request: StageConfig,
+    pub pull_request_long: StageConfig,
+    pub reviews: StageConfig,
+    pub discussion: StageConfig,
+    pub discussion_long: StageConfig,
+    pub synthesis: StageConfig,
+    /// Merges web search results into a user profile.
+    pub search_merge: StageConfig,
+    /// Analysis of further items stops once a report has used this many tokens.
+    pub max_tokens: Option<u64>,
+    /// Analysis of further items stops once a report's estimated cost reaches this many USD.
+    pub max_cost: Option<f64>,
+    /// Prices by model name, for the cost estimate.
+    pub prices: BTreeMap<String, ModelPrice>,
+}
+
+/// USD per 1000 tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    pub prompt: f64,
+    pub completion: f64,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        PipelineConfig {
+            long_context_max_items: 2,
+            readme: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 256).max_input(5_000),
+            repo_overview: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 700)
+                .max_input(12_000)
+                .middle(0.7),
+            commit: StageConfig::new(CHAT_MODEL, 4_096, 128).middle(0.6),
+            commit_long: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 128),
+            issue: StageConfig::new(CHAT_MODEL, 4_096, 128),
+            issue_long: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 128).max_input(12_000),
+            pull_request: StageConfig::new(CHAT_MODEL, 4_096, 128).middle(0.6),
+            pull_request_long: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 128),
+            reviews: StageConfig::new(CHAT_MODEL, 4_096, 192).middle(0.6),
+            discussion: StageConfig::new(CHAT_MODEL, 4_096, 192).middle(0.4),
+            discussion_long: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 256)
+                .max_input(12_000)
+                .middle(0.4),
+            synthesis: StageConfig::new(LONG_CONTEXT_MODEL, 16_384, 1_024),
+            search_merge: StageConfig::new(CHAT_MODEL, 4_096, 700),
+            max_tokens: None,
+            max_cost: None,
+            prices: [
+                ("gpt-3.5-turbo", 0.0005, 0.0015),
+                ("gpt-3.5-turbo-16k", 0.003, 0.004),
+                ("gpt-4", 0.03, 0.06),
+                ("gpt-4-32k", 0.06, 0.12),
+                ("gpt-4-1106-preview", 0.01, 0.03),
+            ]
+            .into_iter()
+            .map(|(model, prompt, completion)| {
+                (model.to_string(), ModelPrice { prompt, completion })
+            })
+            .collect(),
+        }
+        .named()
+    }
+}
+
+impl PipelineConfig {
+    /// The defaults, overridden by whatever tables and keys `text` sets, e.g.
+    /// `[synthesis]` / `model = "gpt-4"` / `context_window = 8192`.
+    pub fn from_toml(text: &str) -> Result<Self, AnalyzerError> {
+        let patch = toml::from_str::<Value>(text)
+            .map_err(|e| AnalyzerError::InvalidInput(format!("invalid pipeline config: {e}")))?;
+        PipelineConfig::default().merged(patch)
+    }
+
+    /// Reads the optional `pipeline_config` environment variable, holding
+    /// either a path to a TOML file or the TOML itself.
+    pub fn from_env() -> Self {
+        let Ok(spec) = env::var("pipeline_config") else {
+            return PipelineConfig::default();
+        };
+        let text = fs::read_to_string(spec.trim()).unwrap_or(spec);
+        PipelineConfig::from_toml(&text).unwrap_or_else(|e| {
+            log::error!("{e}, using the default pipeline config");
+            PipelineConfig::default()
+        })
+    }
+
+    /// Sets one dotted key such as `synthesis.model` or `long_context_max_items`.
+    /// `value` is read as JSON when it parses, as a plain string otherwise.
+    pub fn with_override(self, key: &str, value: &str) -> Result<Self, AnalyzerError> {
+        let value =
+            serde_json::from_str::<Value>(value).unwrap_or_else(|_| Value::String(value.into()));
+        let patch = key.rsplit('.').fold(value, |inner, part| {
+            let mut map = serde_json::Map::new();
+            map.insert(part.to_string(), inner);
+            Value::Object(map)
+        });
+        self.merged(patch)
+    }
+
+    fn merged(&self, patch: Value) -> Result<Self, AnalyzerError> {
+        let mut merged =
+            serde_json::to_value(self).map_err(|e| AnalyzerError::decode("pipeline config", e))?;
+        merge(&mut merged, patch);
+        serde_json::from_value::<PipelineConfig>(merged)
+            .map(PipelineConfig::named)
+            .map_err(|e| AnalyzerError::InvalidInput(format!("invalid pipeline config: {e}")))
+    }
+
+    /// Fills in the stage names, which are not part of the serialized config.
+    fn named(mut self) -> Self {
+        for (name, stage) in [
+            ("readme", &mut self.readme),
+            ("repo_overview", &mut self.repo_overview),
+            ("commit", &mut self.commit),
+            ("commit_long", &mut self.commit_long),
+            ("issue", &mut self.issue),
+            ("issue_long", &mut self.issue_long),
+            ("pull_request", &mut self.pull_request),
+            ("pull_request_long", &mut self.pull_request_long),
+            ("reviews", &mut self.reviews),
+            ("discussion", &mut self.discussion),
+            ("discussion_long", &mut self.discussion_long),
+            ("synthesis", &mut self.synthesis),
+            ("search_merge", &mut self.search_merge),
+        ] {
+            stage.name = name.to_string();
+        }
+        self
+    }
+}
+
+fn merge(base: &mut Value, patch: Value) {
+    match (base, patch) {
+        (Value::Object(base), Value::Object(patch)) => {
+            for (key, value) in patch {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
@@ -0,0 +1,330 @@
+use crate::error::AnalyzerError;
+use crate::kv_store::KvStore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::hash::Hasher;
+use twox_hash::XxHash;
+
+/// The prompts shipped with the analyzer as `(name, version, text)`.
+/// `{{variable}}` placeholders are filled in by [`PromptRegistry::render`].
+const BUILTIN: &[(&str, u32, &str)] = &[
+    (
+        "search_merge_system",
+        1,
+        "Determine if the primary block of text data from definitive sources and the secondary block from search results correspond to the same user or project. If they do, compile the information into a comprehensive summary. If they don’t match, only summarize the primary block. Proceed based on your judgment, without explaining the matching process. Please respond in the following JSON format, leaving a field empty if no information is available, and make a concise summary of available information.",
+    ),
+    (
+        "search_merge_user",
+        1,
+        r#"We have two blocks of text data for you to examine. The first block: `{{current_data}}` comes from our verified databases, and the second block: `{{search_data}}` is from various search results. Your task is to identify whether they are associated with the same user or project. If they are, provide a summary that combines the information, giving more emphasis to the data from the definitive sources and using the search data to supplement it. If not, simply summarize the first block. There's no need to explain your matching process, just proceed based on your analysis. Provide your response in the following JSON format, leaving a field blank if no information is present, and make a concise summary of the available data:
+    ```json{
+        "MatchStatus": "",
+        "Summary": ""
+      }
+      ```"#,
+    ),
+    (
+        "repo_overview_system",
+        1,
+        "Your task is to examine the textual content from a GitHub repo page, emphasizing the Header, About, Release, Contributors, Languages, and README sections. This process should be carried out objectively, focusing on factual information extraction from each segment. Avoid making subjective judgments or inferences. The data should be presented systematically, corresponding to each section. Please note, the provided text will be in a flattened format.",
+    ),
+    (
+        "repo_overview_user",
+        1,
+        "I’ve obtained a flattened text from a GitHub repo page and require analysis of the following sections: 1) Header, with data on Fork, Star, Issues, Pull Request, etc.; 2) About, containing project description, keywords, number of stars, watchers, and forks; 3) Release, with details on the latest release and total releases; 4) Contributors, showing the number of contributors; 5) Languages, displaying the language composition in the project, and 6) README, which is usually a body of text describing the project, please summarize README when presenting result. Please extract and present data from these sections individually. Here is the text: {{page_text}}",
+    ),
+    (
+        "readme_system",
+        1,
+        "Your task is to objectively analyze a GitHub profile and the README of their project. Focus on extracting factual information about the features of the project, and its stated objectives. Avoid making judgments or inferring subjective value.",
+    ),
+    (
+        "readme_user",
+        1,
+        "Based on the profile and README provided: {{readme}}, extract a concise summary detailing this project's factual significance in its domain, their areas of expertise, and the main features and goals of the project. Ensure the insights are objective and under 110 tokens.",
+    ),
+    (
+        "commit_system",
+        1,
+        "Given a commit patch from user {{user_name}}, analyze its content. Focus on changes that substantively alter code or functionality. A good analysis prioritizes the commit message for clues on intent and refrains from overstating the impact of minor changes. Aim to provide a balanced, fact-based representation that distinguishes between major and minor contributions to the project. Keep your analysis concise.",
+    ),
+    (
+        "commit_user",
+        1,
+        "Analyze the commit patch: {{patch}}, and its description: {{tag_line}}. Summarize the main changes, but only emphasize modifications that directly affect core functionality. A good summary is fact-based, derived primarily from the commit message, and avoids over-interpretation. It recognizes the difference between minor textual changes and substantial code adjustments. Conclude by evaluating the realistic impact of {{user_name}}'s contributions in this commit on the project. Limit the response to 110 tokens.",
+    ),
+    (
+        "issue_system",
+        1,
+        "Given the information that user '{{author}}' opened an issue titled '{{title}}', your task is to deeply analyze the content of the issue posts. Distill the crux of the issue, the potential solutions suggested, and evaluate the significant contributions of the participants in resolving or progressing the discussion.",
+    ),
+    (
+        "issue_user",
+        2,
+        "Analyze the GitHub issue content: {{issue}}. Provide a concise analysis touching upon: The central problem discussed in the issue. The main solutions proposed or agreed upon. How and by whom the issue was resolved, if its timeline says so. Emphasize the role and significance of '{{target}}' in contributing towards the resolution or progression of the discussion. Aim for a succinct, analytical summary that stays under 110 tokens.",
+    ),
+    (
+        "pull_request_system",
+        1,
+        "Given the information that user '{{author}}' opened a pull request titled '{{title}}', your task is to analyze its diff and review thread. Identify what the change does to the code base, how the review went, and the contributions of the participants. Refrain from overstating the impact of minor changes.",
+    ),
+    (
+        "pull_request_user",
+        1,
+        "Analyze the GitHub pull request: {{pull_request}}. Provide a concise analysis touching upon: the main change it makes, notable points raised in review and whether it was merged. Emphasize the role and significance of '{{target}}' in authoring or reviewing it. Aim for a succinct, analytical summary that stays under 110 tokens.",
+    ),
+    (
+        "reviews_system",
+        1,
+        "Given code review feedback left on GitHub pull requests, pick out the notable points: design concerns, bugs caught, requested changes and how they shaped the code. Ignore pleasantries and trivial nitpicks.",
+    ),
+    (
+        "reviews_user",
+        1,
+        "Review feedback: {{feedback}}. Summarize the notable feedback given by {{target}} and what it pointed out about the code under review. Aim for a succinct summary that stays under 160 tokens.",
+    ),
+    (
+        "discussion_system",
+        1,
+        "Analyze the provided GitHub discussion. Identify the main topic, actions by participants, crucial viewpoints, solutions or consensus reached, and particularly highlight the contributions of specific individuals, especially '{{target}}'. Summarize without being verbose.",
+    ),
+    (
+        "discussion_user",
+        1,
+        "Analyze the content: {{discussion}}. Briefly summarize the central topic, participants' actions, primary viewpoints, and outcomes. Emphasize the role of '{{target}}' in driving the discussion or reaching a resolution. Aim for a succinct summary that is rich in analysis and under 192 tokens.",
+    ),
+    (
+        "synthesis_system",
+        1,
+        "Analyze the GitHub activity data and profile data over {{period}} to detect both key impactful contributions and connections between commits, pull requests, issues, and discussions. Highlight specific code changes, resolutions, and improvements. Furthermore, trace evidence of commits or pull requests addressing specific issues, discussions leading to commits, or issues spurred by discussions. The aim is to map out both the impactful technical advancements and the developmental narrative of the project.",
+    ),
+    (
+        "synthesis_user",
+        2,
+        "From {{profile}}, {{commits}}, {{pull_requests}}, {{reviews}}, {{issues}}, and {{discussions}}, detail {{target}} significant technical contributions. Enumerate individual tasks, code enhancements, code reviews, and bug resolutions, emphasizing impactful contributions. Concurrently, identify connections: commits or pull requests that resolve specific issues, discussions that may have catalyzed certain commits, or issues influenced by preceding discussions. {{links}} Extract tangible instances showcasing both impact and interconnections within {{period}}.",
+    ),
+    (
+        "synthesis_followup",
+        2,
+        r#"Analyze the key technical contributions made by {{target}} over {{period}}. Consider the following points and answer with a single JSON object matching the JSON schema below, one string per point. If no information is available for a point, leave it as an empty string.
+        - impactful: Highlight impactful contributions and their interconnections.
+        - alignment: Explain alignment with the project's goals.
+        - patterns: Identify recurring patterns or trends.
+        - synergy: Discuss synergy between individual and collective advancement.
+        - significance: Comment objectively on the significance of each contribution.
+        Schema: {{schema}}"#,
+    ),
+    (
+        "synthesis_repair",
+        1,
+        "The following answer was meant to be a single JSON object matching the JSON schema {{schema}}, but it cannot be parsed. Rewrite it as that JSON object, keeping its content and leaving a point as an empty string if the answer says nothing about it. Reply with the JSON object only. Answer: {{answer}}",
+    ),
+    (
+        "home_project_system",
+        1,
+        "First, let's analyze and understand the provided Github data in a step-by-step manner. Begin by evaluating the user's activity based on their most active repositories, languages used, issues they're involved in, and discussions they've participated in. Concurrently, grasp the characteristics and requirements of the home project. Your aim is to identify overlaps or connections between the user's skills or activities and the home project's needs.",
+    ),
+    (
+        "home_project_user",
+        1,
+        "Using a structured approach, analyze the given data: User Profile: {{user_profile}} Active Repositories: {{repos}} Issues Involved: {{issues}} Discussions Participated: {{discussions}} Home project's characteristics: {{home_repo}} Identify patterns in the user's activity and spot potential synergies with the home project. Pay special attention to the programming languages they use, especially if they align with the home project's requirements. Derive insights from their interactions and the data provided.",
+    ),
+    (
+        "home_project_followup",
+        1,
+        "Now, using the insights from your step-by-step analysis, craft a concise bullet-point summary that underscores: - The user's main areas of expertise and interest. - The relevance of their preferred languages or technologies to the home project. - Their potential contributions to the home project, based on their skills and interactions. Ensure the summary is clear, insightful, and remains under 256 tokens. Emphasize any evident alignments between the user's skills and the project's needs.",
+    ),
+];
+
+/// One named prompt. Bump `version` whenever `text` changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptTemplate {
+    pub version: u32,
+    pub text: String,
+}
+
+/// The prompts of every analyzer, looked up by name. Starts from the
+/// built-in texts; overrides may replace any of them but can only use the
+/// placeholders the built-in text of the same name uses, and must carry a
+/// higher version than the text they replace.
+#[derive(Debug, Clone)]
+pub struct PromptRegistry {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl Default for PromptRegistry {
+    fn default() -> Self {
+        PromptRegistry::builtin()
+    }
+}
+
+impl PromptRegistry {
+    pub fn builtin() -> Self {
+        let templates = BUILTIN
+            .iter()
+            .map(|(name, version, text)| {
+                let template = PromptTemplate {
+                    version: *version,
+                    text: text.to_string(),
+                };
+                (name.to_string(), template)
+            })
+            .collect();
+        PromptRegistry { templates }
+    }
+
+    /// Overrides from TOML with one table per prompt, e.g.
+    /// `[commit_user]` / `version = 2` / `text = "..."`.
+    pub fn with_toml(self, text: &str) -> Result<Self, AnalyzerError> {
+        let overrides = toml::from_str::<HashMap<String, PromptTemplate>>(text)
+            .map_err(|e| AnalyzerError::InvalidInput(format!("invalid prompts: {e}")))?;
+        self.with_overrides(overrides)
+    }
+
+    pub fn with_overrides(
+        mut self,
+        overrides: HashMap<String, PromptTemplate>,
+    ) -> Result<Self, AnalyzerError> {
+        for (name, template) in overrides {
+            let Some((_, _, builtin)) = BUILTIN.iter().find(|(n, _, _)| *n == name) else {
+                return Err(AnalyzerError::InvalidInput(format!(
+                    "unknown prompt {name}"
+                )));
+            };
+            let known = placeholders(builtin);
+            if let Some(var) = placeholders(&template.text)
+                .into_iter()
+                .find(|var| !known.contains(var))
+            {
+                return Err(AnalyzerError::InvalidInput(format!(
+                    "prompt {name} has no variable {{{{{var}}}}}"
+                )));
+            }
+            if let Some(current) = self.templates.get(&name) {
+                if template.text != current.text && template.version <= current.version {
+                    return Err(AnalyzerError::InvalidInput(format!(
+                        "prompt {name} changes the text of version {} without raising its version",
+                        current.version
+                    )));
+                }
+            }
+            self.templates.insert(name, template);
+        }
+        Ok(self)
+    }
+
+    /// The built-in prompts with the overrides of the optional `prompts`
+    /// environment variable, holding either a path to a TOML file or the TOML itself.
+    pub fn from_env() -> Self {
+        let Ok(spec) = env::var("prompts") else {
+            return PromptRegistry::builtin();
+        };
+        let text = fs::read_to_string(spec.trim()).unwrap_or(spec);
+        PromptRegistry::builtin()
+            .with_toml(&text)
+            .unwrap_or_else(|e| {
+                log::error!("{e}, using the built-in prompts");
+                PromptRegistry::builtin()
+            })
+    }
+
+    /// Applies the overrides kept in `store`: those under `prompts` for every
+    /// repository, then those under `prompts:<owner>/<repo>` for `full_name`.
+    /// Each key holds a JSON object shaped like the TOML of [`Self::with_toml`].
+    pub fn with_store(self, store: &dyn KvStore, full_name: &str) -> Self {
+        ["prompts".to_string(), format!("prompts:{full_name}")]
+            .iter()
+            .fold(self, |registry, key| {
+                let Some(value) = store.get(key) else {
+                    return registry;
+                };
+                let overrides = serde_json::from_value::<HashMap<String, PromptTemplate>>(value)
+                    .map_err(|e| AnalyzerError::InvalidInput(format!("invalid prompts: {e}")));
+                match overrides.and_then(|o| registry.clone().with_overrides(o)) {
+                    Ok(registry) => registry,
+                    Err(e) => {
+                        log::error!("ignoring prompt overrides under {key}: {e}");
+                        registry
+                    }
+                }
+            })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    /// The versions of `names` joined by dots.
+    pub fn versions(&self, names: &[&str]) -> String {
+        names
+            .iter()
+            .map(|name| self.get(name).map_or(0, |t| t.version).to_string())
+            .collect::<Vec<String>>()
+            .join(".")
+    }
+
+    /// A hash of the texts of `names`, for keys of cached summaries: any
+    /// change to the wording gives a new key, whatever the versions say.
+    pub fn fingerprint(&self, names: &[&str]) -> String {
+        let mut hasher = XxHash::with_seed(0);
+        for name in names {
+            hasher.write(name.as_bytes());
+            hasher.write(&[0]);
+            if let Some(template) = self.get(name) {
+                hasher.write(template.text.as_bytes());
+            }
+            hasher.write(&[0]);
+        }
+        format!("{:x}", hasher.finish())
+    }
+
+    /// The text of prompt `name` with its placeholders replaced by `vars`.
+    /// Values are inserted as they are; placeholders inside them stay untouched.
+    pub fn render(&self, name: &str, vars: &[(&str, &str)]) -> Result<String, AnalyzerError> {
+        let template = self
+            .get(name)
+            .ok_or_else(|| AnalyzerError::InvalidInput(format!("unknown prompt {name}")))?;
+
+        let mut out = String::with_capacity(template.text.len());
+        let mut rest = template.text.as_str();
+        while let Some(start) = rest.find("{{") {
+            let after = &rest[start + 2..];
+            match placeholder_at(after) {
+                Some(var) => {
+                    let value = vars.iter().find(|(v, _)| *v == var).ok_or_else(|| {
+                        AnalyzerError::InvalidInput(format!(
+                            "prompt {name} needs variable {{{{{var}}}}}"
+                        ))
+                    })?;
+                    out.push_str(&rest[..start]);
+                    out.push_str(value.1);
+                    rest = &after[var.len() + 2..];
+                }
+                None => {
+                    out.push_str(&rest[..start + 2]);
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// The variable name of a `{{name}}` placeholder whose opening braces
+/// directly precede `text`.
+fn placeholder_at(text: &str) -> Option<&str> {
+    let end = text.find("}}")?;
+    let var = &text[..end];
+    let is_name = !var.is_empty() && var.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    is_name.then_some(var)
+}
+
+fn placeholders(text: &str) -> Vec<&str> {
+    text.match_indices("{{")
+        .filter_map(|(i, _)| placeholder_at(&text[i + 2..]))
+        .collect()
+}
@@ -0,0 +1,303 @@
+use crate::github_client::DEFAULT_WEB_URL;
+use crate::github_data_fetchers::{GitMemory, PullRequest};
+use crate::issue_lifecycle::short_sha;
+use crate::issue_links::{parse_references, IssueRef};
+use crate::octocrab_compat::Release;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Conventional commit types and their changelog headings, in the order the
+/// groups are rendered. Anything else goes under [`OTHER_CHANGES`].
+const CHANGE_TYPES: [(&str, &str); 10] = [
+    ("feat", "Features"),
+    ("fix", "Bug fixes"),
+    ("perf", "Performance"),
+    ("refactor", "Refactoring"),
+    ("docs", "Documentation"),
+    ("test", "Tests"),
+    ("build", "Build and CI"),
+    ("ci", "Build and CI"),
+    ("chore", "Chores"),
+    ("revert", "Reverts"),
+];
+
+const OTHER_CHANGES: &str = "Other changes";
+
+/// Pull request labels that stand for a conventional commit type.
+const LABEL_TYPES: [(&str, &str); 12] = [
+    ("feature", "feat"),
+    ("enhancement", "feat"),
+    ("bug", "fix"),
+    ("bugfix", "fix"),
+    ("performance", "perf"),
+    ("refactor", "refactor"),
+    ("documentation", "docs"),
+    ("docs", "docs"),
+    ("test", "test"),
+    ("tests", "test"),
+    ("ci", "ci"),
+    ("dependencies", "build"),
+];
+
+/// The type of a conventional commit subject such as `fix(parser)!: ...`.
+pub fn change_type(message: &str) -> Option<&'static str> {
+    let subject = message.lines().next()?;
+    let (prefix, _) = subject.split_once(':')?;
+    let prefix = prefix.trim_end_matches('!');
+    let kind = prefix.split_once('(').map_or(prefix, |(kind, _)| kind);
+    let kind = kind.trim().to_ascii_lowercase();
+    CHANGE_TYPES
+        .iter()
+        .find(|(known, _)| *known == kind)
+        .map(|(known, _)| *known)
+}
+
+/// The change type of the first label that names one.
+pub fn label_type(labels: &[String]) -> Option<&'static str> {
+    labels.iter().find_map(|label| {
+        let label = label.trim().to_ascii_lowercase();
+        let label = label.rsplit(['/', ':']).next().unwrap_or_default().trim();
+        LABEL_TYPES
+            .iter()
+            .find(|(known, _)| *known == label)
+            .map(|(_, kind)| *kind)
+    })
+}
+
+/// One changelog line: a merged pull request with the commits it brought
+/// in, or a commit pushed without one.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogEntry {
+    pub title: String,
+    pub url: String,
+    /// `#13` for pull requests, the short SHA for commits.
+    pub reference: String,
+    pub authors: Vec<String>,
+    pub commits: Vec<GitMemory>,
+}
+
+impl ChangelogEntry {
+    /// The commit analyzer's summaries of the entry's commits.
+    pub fn summary(&self) -> Option<String> {
+        let summary = self
+            .commits
+            .iter()
+            .map(|commit| commit.payload.trim())
+            .filter(|payload| !payload.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ");
+        Some(summary).filter(|s| !s.is_empty())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogGroup {
+    pub heading: String,
+    pub entries: Vec<ChangelogEntry>,
+}
+
+/// The changes between two tags, grouped for a changelog.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseNotes {
+    pub owner: String,
+    pub repo: String,
+    /// Where the repository is browsed, `https://github.com` unless set.
+    pub web_url: String,
+    pub from_tag: String,
+    pub to_tag: String,
+    /// The release published for `to_tag`, if any.
+    pub release: Option<Release>,
+    pub groups: Vec<ChangelogGroup>,
+}
+
+impl ReleaseNotes {
+    /// Groups `commits` by change type. Commits are listed under the pull
+    /// request `pulls` maps their SHA to, see
+    /// [`get_commit_pull_requests`](crate::github_data_fetchers::get_commit_pull_requests),
+    /// or else the one they mention, like the `(#13)` of a squash merge.
+    /// Pull requests are typed by their labels before their title.
+    pub fn new(
+        owner: &str,
+        repo: &str,
+        from_tag: &str,
+        to_tag: &str,
+        commits: Vec<GitMemory>,
+        pulls: &HashMap<String, PullRequest>,
+    ) -> Self {
+        let mut typed = Vec::<(&'static str, ChangelogEntry)>::new();
+        for commit in commits {
+            let sha = commit.source_url.rsplit('/').next().unwrap_or_default();
+            let pull = pulls.get(sha).or_else(|| {
+                parse_references(&commit.tag_line, owner, repo)
+                    .into_iter()
+                    .find_map(|(issue, _)| {
+                        pulls
+                            .values()
+                            .find(|pull| IssueRef::new(owner, repo, pull.number) == issue)
+                    })
+            });
+            let Some(pull) = pull else {
+                let kind = change_type(&commit.tag_line).unwrap_or("");
+                typed.push((
+                    kind,
+                    ChangelogEntry {
+                        title: commit.tag_line.lines().next().unwrap_or("").to_string(),
+                        url: commit.source_url.clone(),
+                        reference: short_sha(sha).to_string(),
+                        authors: Some(commit.name.clone())
+                            .filter(|author| !author.is_empty())
+                            .into_iter()
+                            .collect(),
+                        commits: vec![commit],
+                    },
+                ));
+                continue;
+            };
+            match typed
+                .iter_mut()
+                .find(|(_, entry)| entry.url == pull.html_url)
+            {
+                Some((_, entry)) => {
+                    if !commit.name.is_empty() && !entry.authors.contains(&commit.name) {
+                        entry.authors.push(commit.name.clone());
+                    }
+                    entry.commits.push(commit);
+                }
+                None => {
+                    let kind = label_type(&pull.labels)
+                        .or_else(|| change_type(&pull.title))
+                        .or_else(|| change_type(&commit.tag_line))
+                        .unwrap_or("");
+                    let mut authors = Vec::new();
+                    for author in [&pull.author, &commit.name] {
+                        if !author.is_empty() && !authors.contains(author) {
+                            authors.push(author.clone());
+                        }
+                    }
+                    typed.push((
+                        kind,
+                        ChangelogEntry {
+                            title: pull.title.clone(),
+                            url: pull.html_url.clone(),
+                            reference: format!("#{}", pull.number),
+                            authors,
+                            commits: vec![commit],
+                        },
+                    ));
+                }
+            }
+        }
+
+        let mut groups = Vec::<ChangelogGroup>::new();
+        let headings = CHANGE_TYPES
+            .iter()
+            .map(|(_, heading)| *heading)
+            .chain([OTHER_CHANGES]);
+        for heading in headings {
+            if groups.iter().any(|group| group.heading == heading) {
+                continue;
+            }
+            let entries = typed
+                .iter()
+                .filter(|(kind, _)| heading_of(kind) == heading)
+                .map(|(_, entry)| entry.clone())
+                .collect::<Vec<ChangelogEntry>>();
+            if !entries.is_empty() {
+                groups.push(ChangelogGroup {
+                    heading: heading.to_string(),
+                    entries,
+                });
+            }
+        }
+
+        ReleaseNotes {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            web_url: DEFAULT_WEB_URL.to_string(),
+            from_tag: from_tag.to_string(),
+            to_tag: to_tag.to_string(),
+            release: None,
+            groups,
+        }
+    }
+
+    pub fn with_release(mut self, release: Option<Release>) -> Self {
+        self.release = release;
+        self
+    }
+
+    pub fn with_web_url(mut self, web_url: &str) -> Self {
+        self.web_url = web_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn compare_url(&self) -> String {
+        format!(
+            "{}/{}/{}/compare/{}...{}",
+            self.web_url, self.owner, self.repo, self.from_tag, self.to_tag
+        )
+    }
+
+    /// When the release of `to_tag` was published.
+    pub fn date(&self) -> Option<NaiveDate> {
+        let release = self.release.as_ref()?;
+        release
+            .published_at
+            .or(release.created_at)
+            .map(|at| at.date_naive())
+    }
+
+    /// A changelog section: a heading per release and per change type.
+    pub fn render_markdown(&self) -> String {
+        let name = self
+            .release
+            .as_ref()
+            .and_then(|release| release.name.clone())
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| self.to_tag.clone());
+        let mut out = format!(
+            "## [{}]({})",
+            name.replace(['[', ']'], ""),
+            self.compare_url()
+        );
+        if let Some(date) = self.date() {
+            out.push_str(&format!(" - {date}"));
+        }
+        out.push_str("\n\n");
+        if self.groups.is_empty() {
+            out.push_str(&format!("No changes since {}.\n", self.from_tag));
+        }
+        for group in &self.groups {
+            out.push_str(&format!("### {}\n\n", group.heading));
+            for entry in &group.entries {
+                let authors = entry
+                    .authors
+                    .iter()
+                    .map(|author| format!("@{author}"))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                out.push_str(&format!(
+                    "- {} ([{}]({}))",
+                    entry.title, entry.reference, entry.url
+                ));
+                if !authors.is_empty() {
+                    out.push_str(&format!(" by {authors}"));
+                }
+                if let Some(summary) = entry.summary() {
+                    out.push_str(&format!("\n  {}", summary.replace('\n', " ")));
+                }
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn heading_of(kind: &str) -> &'static str {
+    CHANGE_TYPES
+        .iter()
+        .find(|(known, _)| *known == kind)
+        .map_or(OTHER_CHANGES, |(_, heading)| *heading)
+}
@@ -0,0 +1,341 @@
+use crate::date_range::DateRange;
+use crate::github_data_fetchers::GitMemory;
+use crate::issue_links::IssueLink;
+use crate::synthesis::Synthesis;
+use crate::usage::Usage;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+
+/// A finished report, independent of how it is delivered; see [`ReportFormat`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub owner: String,
+    pub repo: String,
+    pub user_name: Option<String>,
+    pub period: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub about: Option<String>,
+    pub sections: Vec<ReportSection>,
+    /// Commits and pull requests referring to issues, see
+    /// [`LinkGraph`](crate::issue_links::LinkGraph).
+    pub links: Vec<IssueLink>,
+    /// Final synthesis across all sections.
+    pub synthesis: Option<Synthesis>,
+    /// The model's final answer when it could not be read as a [`Synthesis`].
+    pub synthesis_text: Option<String>,
+    pub notes: Vec<String>,
+    pub stats: ReportStats,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSection {
+    pub name: String,
+    pub found: usize,
+    pub items: Vec<ReportItem>,
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportItem {
+    pub title: String,
+    pub url: String,
+    pub author: String,
+    pub date: NaiveDate,
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReportStats {
+    pub commits: usize,
+    pub issues: usize,
+    pub pull_requests: usize,
+    pub reviews: usize,
+    pub discussions: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ReportFormat {
+    /// Parses the webhook's `format=` value.
+    pub fn from_param(format: &str) -> Option<Self> {
+        match format.trim().to_lowercase().as_str() {
+            "text" | "txt" | "plain" => Some(ReportFormat::Text),
+            "markdown" | "md" => Some(ReportFormat::Markdown),
+            "json" => Some(ReportFormat::Json),
+            "html" => Some(ReportFormat::Html),
+            _ => None,
+        }
+    }
+
+    /// Picks the first supported media type of an `Accept` header.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept
+            .split(',')
+            .find_map(|media| match media.split(';').next().unwrap_or("").trim() {
+                "text/plain" => Some(ReportFormat::Text),
+                "text/markdown" => Some(ReportFormat::Markdown),
+                "application/json" => Some(ReportFormat::Json),
+                "text/html" => Some(ReportFormat::Html),
+                _ => None,
+            })
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Text => "text/plain",
+            ReportFormat::Markdown => "text/markdown; charset=utf-8",
+            ReportFormat::Json => "application/json",
+            ReportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+impl ReportItem {
+    pub fn from_memory(gm: &GitMemory) -> Self {
+        ReportItem {
+            title: gm.tag_line.lines().next().unwrap_or("").to_string(),
+            url: gm.source_url.clone(),
+            author: gm.name.clone(),
+            date: gm.date,
+            summary: Some(gm.payload.clone()).filter(|p| !p.is_empty()),
+        }
+    }
+}
+
+impl Report {
+    pub fn new(owner: &str, repo: &str, user_name: Option<String>, range: &DateRange) -> Self {
+        Report {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            user_name,
+            period: range.period(),
+            from: range.from,
+            to: range.to,
+            generated_at: Utc::now(),
+            about: None,
+            sections: Vec::new(),
+            links: Vec::new(),
+            synthesis: None,
+            synthesis_text: None,
+            notes: Vec::new(),
+            stats: ReportStats::default(),
+            usage: Usage::default(),
+        }
+    }
+
+    pub fn title(&self) -> String {
+        let subject = match &self.user_name {
+            Some(user_name) => format!("{user_name} in {}/{}", self.owner, self.repo),
+            None => format!("{}/{}", self.owner, self.repo),
+        };
+        format!("Activity of {subject} over {}", self.period)
+    }
+
+    pub fn render(&self, format: ReportFormat) -> String {
+        match format {
+            ReportFormat::Text => self.render_text(),
+            ReportFormat::Markdown => self.render_markdown(),
+            ReportFormat::Json => self.render_json(),
+            ReportFormat::Html => self.render_html(),
+        }
+    }
+
+    /// The plain text layout the webhook has always answered with.
+    pub fn render_text(&self) -> String {
+        let mut out = Vec::<String>::new();
+        for section in &self.sections {
+            let urls = section
+                .items
+                .iter()
+                .map(|item| item.url.to_owned())
+                .collect::<Vec<String>>()
+                .join("\n");
+            out.push(format!(
+                "found {} {}:\n{urls}",
+                section.found,
+                section.name.to_lowercase()
+            ));
+        }
+        if !self.links.is_empty() {
+            let links = self
+                .links
+                .iter()
+                .map(IssueLink::describe)
+                .collect::<Vec<String>>()
+                .join("\n");
+            out.push(format!("found {} links:\n{links}", self.links.len()));
+        }
+        out.extend(self.notes.iter().cloned());
+        if let Some(synthesis) = self.synthesis_bullets() {
+            out.push(synthesis);
+        }
+        out.join("\n")
+    }
+
+    pub fn render_markdown(&self) -> String {
+        let mut out = format!("# {}\n\n", self.title());
+        if let Some(about) = &self.about {
+            out.push_str(&format!("> {}\n\n", about.replace('\n', " ")));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("_{note}_\n\n"));
+        }
+        if let Some(synthesis) = self.synthesis_bullets() {
+            out.push_str(&format!("## Summary\n\n{synthesis}\n\n"));
+        }
+        for section in &self.sections {
+            out.push_str(&format!("## {} ({})\n\n", section.name, section.found));
+            if let Some(summary) = &section.summary {
+                out.push_str(&format!("{summary}\n\n"));
+            }
+            for item in &section.items {
+                out.push_str(&format!(
+                    "- [{}]({}) by @{} on {}",
+                    item.title.replace(['[', ']'], ""),
+                    item.url,
+                    item.author,
+                    item.date
+                ));
+                if let Some(summary) = &item.summary {
+                    out.push_str(&format!("\n  {}", summary.replace('\n', " ")));
+                }
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        if !self.links.is_empty() {
+            out.push_str(&format!("## Links ({})\n\n", self.links.len()));
+            for link in &self.links {
+                out.push_str(&format!("- {}\n", link.describe()));
+            }
+            out.push('\n');
+        }
+        out.push_str(&self.stats_line());
+        out.push('\n');
+        out
+    }
+
+    pub fn render_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_else(|e| {
+            log::error!("failed to serialize report: {e}");
+            "{}".to_string()
+        })
+    }
+
+    pub fn render_html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut body = format!("<h1>{title}</h1>\n");
+        if let Some(about) = &self.about {
+            body.push_str(&format!(
+                "<blockquote>{}</blockquote>\n",
+                escape_html(about)
+            ));
+        }
+        for note in &self.notes {
+            body.push_str(&format!("<p><em>{}</em></p>\n", escape_html(note)));
+        }
+        if let Some(synthesis) = self.synthesis_bullets() {
+            body.push_str("<h2>Summary</h2>\n<ul>\n");
+            for line in synthesis.lines().filter(|l| !l.trim().is_empty()) {
+                let line = line.trim().trim_start_matches("- ");
+                body.push_str(&format!("<li>{}</li>\n", escape_html(line)));
+            }
+            body.push_str("</ul>\n");
+        }
+        for section in &self.sections {
+            body.push_str(&format!(
+                "<h2>{} ({})</h2>\n",
+                escape_html(&section.name),
+                section.found
+            ));
+            if let Some(summary) = &section.summary {
+                body.push_str(&format!("<p>{}</p>\n", escape_html(summary)));
+            }
+            if section.items.is_empty() {
+                continue;
+            }
+            body.push_str("<ul>\n");
+            for item in &section.items {
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a> by {} on {}",
+                    escape_html(&item.url),
+                    escape_html(&item.title),
+                    escape_html(&item.author),
+                    item.date
+                ));
+                if let Some(summary) = &item.summary {
+                    body.push_str(&format!("<p>{}</p>", escape_html(summary)));
+                }
+                body.push_str("</li>\n");
+            }
+            body.push_str("</ul>\n");
+        }
+        if !self.links.is_empty() {
+            body.push_str(&format!("<h2>Links ({})</h2>\n<ul>\n", self.links.len()));
+            for link in &self.links {
+                body.push_str(&format!(
+                    "<li><a href=\"{}\">{}</a></li>\n",
+                    escape_html(&link.from_url),
+                    escape_html(&link.describe())
+                ));
+            }
+            body.push_str("</ul>\n");
+        }
+        body.push_str(&format!(
+            "<footer>{}</footer>\n",
+            escape_html(&self.stats_line())
+        ));
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n{body}</body>\n</html>\n"
+        )
+    }
+
+    /// The synthesis as bullet points, or the raw answer it could not be read from.
+    fn synthesis_bullets(&self) -> Option<String> {
+        self.synthesis
+            .as_ref()
+            .map(Synthesis::to_bullets)
+            .or_else(|| self.synthesis_text.clone())
+            .filter(|text| !text.trim().is_empty())
+    }
+
+    fn stats_line(&self) -> String {
+        let stats = &self.stats;
+        let mut line = format!(
+            "{} commits, {} issues, {} pull requests, {} reviews, {} discussions from {} to {}.",
+            stats.commits,
+            stats.issues,
+            stats.pull_requests,
+            stats.reviews,
+            stats.discussions,
+            self.from.format("%Y-%m-%d"),
+            self.to.format("%Y-%m-%d")
+        );
+        if self.usage.total_tokens() > 0 {
+            line.push_str(&format!(
+                " {} LLM tokens, about ${:.4}.",
+                self.usage.total_tokens(),
+                self.usage.cost
+            ));
+        }
+        line
+    }
+}
+
+fn escape_html(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
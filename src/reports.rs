@@ -1,205 +1,506 @@
 use crate::data_analyzers::*;
+use crate::date_range::DateRange;
+use crate::error::AnalyzerError;
+use crate::github_client::GitHubClient;
 use crate::github_data_fetchers::*;
-use crate::utils::parse_summary_from_raw_json;
-use chrono::{Duration, Utc};
+use crate::issue_links::{IssueRef, LinkGraph};
+use crate::kv_store::KvStore;
+use crate::llm::LlmClient;
+use crate::notifier::Notifier;
+use crate::pipeline_config::PipelineConfig;
+use crate::prompts::PromptRegistry;
+use crate::release_notes::ReleaseNotes;
+use crate::report::{Report, ReportItem, ReportSection};
+use crate::synthesis::read_synthesis;
+use crate::usage::MeteredLlm;
 use log;
-use webhook_flows::send_response;
 
-pub async fn search_user(github_token: &str, user_login: &str) -> Option<String> {
-    let _profile_data = get_user_data_by_login(github_token, user_login).await;
+pub async fn search_user(client: &GitHubClient, user_login: &str) -> Result<String, AnalyzerError> {
+    let _profile_data = get_user_data_by_login(client, user_login).await;
 
     // let repos = get_user_repos_gql(github_token, user).await;
 
     _profile_data
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn weekly_report(
-    github_token: String,
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    store: &dyn KvStore,
+    notifier: &dyn Notifier,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
     owner: &str,
     repo: &str,
     user_name: Option<String>,
+    range: &DateRange,
+    branch: Option<String>,
     token: Option<String>,
-) -> String {
-    let n_days = 7u16;
-    let mut report = Vec::<String>::new();
-
-    let mut _profile_data = String::new();
-
-    match is_valid_owner_repo_integrated(&github_token, owner, repo).await {
-        None => {
-            send_response(
-                400,
-                vec![(String::from("content-type"), String::from("text/plain"))],
-                "You've entered invalid owner/repo, or the target is private. Please try again."
-                    .as_bytes()
-                    .to_vec(),
-            );
-            std::process::exit(1);
-        }
-        Some(gm) => {
-            _profile_data = format!("About {}/{}: {}", owner, repo, gm.payload);
-        }
-    }
+) -> Result<Report, AnalyzerError> {
+    let mut report = Report::new(owner, repo, user_name.clone(), range);
+    let metered = MeteredLlm::new(llm, config);
+    let llm: &dyn LlmClient = &metered;
+
+    let gm = is_valid_owner_repo_integrated(client, llm, config, prompts, owner, repo).await?;
+    let _profile_data = format!("About {}/{}: {}", owner, repo, gm.payload);
+    report.about = Some(gm.payload.clone()).filter(|about| !about.is_empty());
 
     let mut commits_count = 0;
     let mut issues_count = 0;
+    let mut pulls_count = 0;
+    let mut reviews_count = 0;
+    let mut links = LinkGraph::new(owner, repo);
+    // Each source that cannot be fetched is left out of the report, which
+    // fails only when none of them can be.
+    let mut failures = Vec::<AnalyzerError>::new();
+    let mut fetched_sources = 0;
 
     let mut commits_summaries = String::new();
     'commits_block: {
-        match get_commits_in_range(&github_token, owner, repo, user_name.clone(), n_days, token.clone()).await {
-            Some((count, mut commits_vec, weekly_commits_vec)) => {
-                let commits_str = commits_vec
-                    .iter()
-                    .map(|com| com.source_url.to_owned())
-                    .collect::<Vec<String>>()
-                    .join("\n");
-
-                report.push(format!("found {count} commits:\n{commits_str}"));
-                // send_message_to_channel("ik8", "ch_rep", commits_str.to_string()).await;
-                let mut is_sparce = false;
-                let mut _turbo = false;
-                match count {
-                    0 => break 'commits_block,
-                    1..=2 => is_sparce = true,
-                    6.. => _turbo = true,
-                    _ => {}
-                };
-                commits_count = count;
-                match process_commits(&github_token, &mut commits_vec, _turbo, is_sparce, token.clone()).await {
-                    Some(summary) => {
-                        commits_summaries = summary;
-                    }
-                    None => log::error!("processing commits failed"),
-                }
-
-                if is_sparce {
-                    let weekly_commits_log = weekly_commits_vec
-                        .iter()
-                        .map(|com| format!("{}: {}", com.name, com.tag_line))
-                        .collect::<Vec<String>>()
-                        .join("\n");
+        let (count, mut commits_vec, weekly_commits_vec) = match get_commits_in_range(
+            client,
+            owner,
+            repo,
+            user_name.clone(),
+            range,
+            branch.clone(),
+            token.clone(),
+        )
+        .await
+        {
+            Ok(commits) => commits,
+            Err(e) => {
+                log::error!("failed to get commits for {owner}/{repo}: {e}");
+                report
+                    .notes
+                    .push(format!("Commits could not be fetched: {e}"));
+                failures.push(e);
+                break 'commits_block;
+            }
+        };
+        fetched_sources += 1;
+        report.stats.commits = count;
+        let mut section = ReportSection {
+            name: "Commits".to_string(),
+            found: count,
+            items: Vec::new(),
+            summary: None,
+        };
 
-                    commits_summaries = format!("Here is the contributor's commits details: {commits_summaries}, here is the log of weekly commits for the entire repository: {weekly_commits_log}");
-                }
-                // send_message_to_channel("ik8", "ch_rep", commits_summaries.clone()).await;
+        // send_message_to_channel("ik8", "ch_rep", commits_str.to_string()).await;
+        let mut is_sparce = false;
+        let mut _turbo = false;
+        match count {
+            0 => {
+                report.sections.push(section);
+                break 'commits_block;
+            }
+            n if n <= config.long_context_max_items => is_sparce = true,
+            6.. => _turbo = true,
+            _ => {}
+        };
+        commits_count = count;
+        match process_commits(
+            client,
+            llm,
+            store,
+            config,
+            prompts,
+            &mut commits_vec,
+            _turbo,
+            is_sparce,
+            token.clone(),
+        )
+        .await
+        {
+            Ok(summary) => {
+                commits_summaries = summary;
             }
-            None => log::error!("failed to get commits"),
+            Err(e) => log::error!("processing commits failed: {}", e),
+        }
+        for commit in &commits_vec {
+            links.add_commit(commit);
         }
+        section.items = commits_vec.iter().map(ReportItem::from_memory).collect();
+        report.sections.push(section);
+
+        if is_sparce {
+            let weekly_commits_log = weekly_commits_vec
+                .iter()
+                .map(|com| format!("{}: {}", com.name, com.tag_line))
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            commits_summaries = format!("Here is the contributor's commits details: {commits_summaries}, here is the log of weekly commits for the entire repository: {weekly_commits_log}");
+        }
+        // send_message_to_channel("ik8", "ch_rep", commits_summaries.clone()).await;
     }
     let mut issues_summaries = String::new();
 
     'issues_block: {
-        match get_issues_in_range(&github_token, owner, repo, user_name.clone(), n_days, token.clone()).await {
-            Some((count, issue_vec)) => {
-                let issues_str = issue_vec
-                    .iter()
-                    .map(|issue| issue.html_url.to_owned())
-                    .collect::<Vec<String>>()
-                    .join("\n");
-
-                report.push(format!("found {count} issues:\n{issues_str}"));
-                // send_message_to_channel("ik8", "ch_iss", issues_str.to_string()).await;
-
-                let mut is_sparce = false;
-                let mut _turbo = false;
-
-                match count {
-                    0 => break 'issues_block,
-                    1..=2 => is_sparce = true,
-                    4.. => _turbo = true,
-                    _ => {}
-                };
-                issues_count = count;
-                match process_issues(
-                    &github_token,
-                    issue_vec,
-                    user_name.clone(),
-                    _turbo,
-                    is_sparce,
-                    token.clone(),
-                )
+        let (count, issue_vec) =
+            match get_issues_in_range(client, owner, repo, user_name.clone(), range, token.clone())
                 .await
-                {
-                    Some((summary, _, _issues_vec)) => {
-                        // send_message_to_channel("ik8", "ch_err", summary.clone()).await;
-                        issues_summaries = summary;
-                    }
-                    None => log::error!("processing issues failed"),
+            {
+                Ok(issues) => issues,
+                Err(e) => {
+                    log::error!("failed to get issues for {owner}/{repo}: {e}");
+                    report
+                        .notes
+                        .push(format!("Issues could not be fetched: {e}"));
+                    failures.push(e);
+                    break 'issues_block;
                 }
+            };
+        fetched_sources += 1;
+        report.stats.issues = count;
+        let mut section = ReportSection {
+            name: "Issues".to_string(),
+            found: count,
+            items: issue_vec
+                .iter()
+                .map(|issue| ReportItem {
+                    title: issue.title.clone(),
+                    url: issue.html_url.clone(),
+                    author: issue.user.login.clone(),
+                    date: issue.created_at.date_naive(),
+                    summary: None,
+                })
+                .collect(),
+            summary: None,
+        };
+        // send_message_to_channel("ik8", "ch_iss", issues_str.to_string()).await;
+
+        let mut is_sparce = false;
+        let mut _turbo = false;
+
+        match count {
+            0 => {
+                report.sections.push(section);
+                break 'issues_block;
+            }
+            n if n <= config.long_context_max_items => is_sparce = true,
+            4.. => _turbo = true,
+            _ => {}
+        };
+        issues_count = count;
+        let timelines = get_issue_timelines(client, owner, repo, &issue_vec).await;
+        for issue in &issue_vec {
+            if let Some(events) = timelines.get(&issue.number) {
+                links.add_timeline(&IssueRef::new(owner, repo, issue.number), events);
+            }
+        }
+        match process_issues(
+            client,
+            llm,
+            store,
+            config,
+            prompts,
+            issue_vec,
+            &timelines,
+            user_name.clone(),
+            _turbo,
+            is_sparce,
+            token.clone(),
+        )
+        .await
+        {
+            Ok((summary, _, issues_vec)) => {
+                // send_message_to_channel("ik8", "ch_err", summary.clone()).await;
+                issues_summaries = summary;
+                attach_summaries(&mut section.items, &issues_vec);
             }
-            None => log::error!("failed to get issues"),
+            Err(e) => log::error!("processing issues failed: {}", e),
         }
+        report.sections.push(section);
     }
 
-    let now = Utc::now();
-    let a_week_ago = now - Duration::days(n_days as i64 + 30);
-    let n_days_ago_str = a_week_ago.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let mut pulls_summaries = String::new();
 
+    'pulls_block: {
+        let (count, pull_vec) =
+            match get_pull_requests_in_range(client, owner, repo, user_name.clone(), range).await {
+                Ok(pulls) => pulls,
+                Err(e) => {
+                    log::error!("failed to get pull requests for {owner}/{repo}: {e}");
+                    report
+                        .notes
+                        .push(format!("Pull requests could not be fetched: {e}"));
+                    failures.push(e);
+                    break 'pulls_block;
+                }
+            };
+        fetched_sources += 1;
+        report.stats.pull_requests = count;
+        let mut section = ReportSection {
+            name: "Pull requests".to_string(),
+            found: count,
+            items: pull_vec
+                .iter()
+                .map(|pull| ReportItem {
+                    title: pull.title.clone(),
+                    url: pull.html_url.clone(),
+                    author: pull.author.clone(),
+                    date: pull.created_at.date_naive(),
+                    summary: None,
+                })
+                .collect(),
+            summary: None,
+        };
+
+        let is_sparce = match count {
+            0 => {
+                report.sections.push(section);
+                break 'pulls_block;
+            }
+            n => n <= config.long_context_max_items,
+        };
+        pulls_count = count;
+        for pull in &pull_vec {
+            links.add_pull_request(pull);
+        }
+        match process_pull_requests(
+            client,
+            llm,
+            config,
+            prompts,
+            pull_vec,
+            user_name.clone(),
+            is_sparce,
+        )
+        .await
+        {
+            Ok((summary, _, pulls_vec)) => {
+                pulls_summaries = summary;
+                attach_summaries(&mut section.items, &pulls_vec);
+            }
+            Err(e) => log::error!("processing pull requests failed: {}", e),
+        }
+        report.sections.push(section);
+    }
+
+    let mut reviews_summaries = String::new();
+
+    match get_reviews_in_range(client, owner, repo, user_name.clone(), range).await {
+        Ok((0, _)) => {
+            fetched_sources += 1;
+            log::info!("no reviews found for {owner}/{repo}");
+        }
+        Ok((count, reviews_vec)) => {
+            fetched_sources += 1;
+            report.stats.reviews = count;
+            reviews_count = count;
+            match analyze_reviews(
+                llm,
+                &config.reviews,
+                prompts,
+                &reviews_vec,
+                user_name.clone(),
+            )
+            .await
+            {
+                Ok(summary) => reviews_summaries = summary,
+                Err(e) => log::error!("processing reviews failed: {}", e),
+            }
+            report.sections.push(ReportSection {
+                name: "Reviews".to_string(),
+                found: count,
+                items: reviews_vec
+                    .iter()
+                    .map(|pull_reviews| ReportItem {
+                        title: pull_reviews.pull.title.clone(),
+                        url: pull_reviews.pull.html_url.clone(),
+                        author: pull_reviews.pull.author.clone(),
+                        date: pull_reviews.pull.created_at.date_naive(),
+                        summary: None,
+                    })
+                    .collect(),
+                summary: Some(reviews_summaries.clone()).filter(|s| !s.is_empty()),
+            });
+        }
+        Err(e) => {
+            log::error!("failed to get reviews for {owner}/{repo}: {e}");
+            report
+                .notes
+                .push(format!("Reviews could not be fetched: {e}"));
+            failures.push(e);
+        }
+    }
+
+    let updated_str = range.updated_qualifier();
     let discussion_query = match &user_name {
         Some(user_name) => {
-            format!("repo:{owner}/{repo} involves: {user_name} updated:>{n_days_ago_str}")
+            format!("repo:{owner}/{repo} involves:{user_name} {updated_str}")
         }
-        None => format!("repo:{owner}/{repo} updated:>{n_days_ago_str}"),
+        None => format!("repo:{owner}/{repo} {updated_str}"),
     };
 
     let mut discussion_data = String::new();
-    match search_discussions_integrated(&github_token, &discussion_query, &user_name).await {
-        Some((summary, discussion_vec)) => {
-            let count = discussion_vec.len();
-            let discussions_str = discussion_vec
-                .iter()
-                .map(|discussion| discussion.source_url.to_owned())
-                .collect::<Vec<String>>()
-                .join("\n");
-
-            report.push(format!(
-                "{count} discussions were referenced in analysis:\n {discussions_str}"
-            ));
+    match search_discussions_integrated(client, llm, config, prompts, &discussion_query, &user_name)
+        .await
+    {
+        Ok((summary, discussion_vec)) if !discussion_vec.is_empty() => {
+            fetched_sources += 1;
+            report.stats.discussions = discussion_vec.len();
+            report.sections.push(ReportSection {
+                name: "Discussions".to_string(),
+                found: discussion_vec.len(),
+                items: discussion_vec.iter().map(ReportItem::from_memory).collect(),
+                summary: None,
+            });
             // send_message_to_channel("ik8", "ch_dis", summary.clone()).await;
             discussion_data = summary;
         }
-        None => log::error!("failed to get discussions for {owner}/{repo}"),
+        Ok(_) => {
+            fetched_sources += 1;
+            log::info!("no discussions found for {owner}/{repo}");
+        }
+        Err(e) => {
+            log::error!("failed to get discussions for {owner}/{repo}: {e}");
+            report
+                .notes
+                .push(format!("Discussions could not be fetched: {e}"));
+            failures.push(e);
+        }
+    }
+    if fetched_sources == 0 && !failures.is_empty() {
+        return Err(failures.remove(0));
     }
 
-    let total_input_entry_count = (commits_count + issues_count) as u16;
+    report.links = links.links.clone();
+    let link_facts = links.facts();
+    let total_input_entry_count =
+        (commits_count + issues_count + pulls_count + reviews_count) as u16;
 
-    if commits_summaries.is_empty() && issues_summaries.is_empty() && discussion_data.is_empty() {
+    if commits_summaries.is_empty()
+        && issues_summaries.is_empty()
+        && pulls_summaries.is_empty()
+        && reviews_summaries.is_empty()
+        && discussion_data.is_empty()
+    {
+        report.sections.clear();
         match &user_name {
             Some(target_person) => {
-                report = vec![format!(
+                report.notes.push(format!(
                     "No useful data found for {}, you may try alternative means to find out more about {}",
                     target_person, target_person
-                )];
+                ));
             }
 
             None => {
-                report = vec!["No useful data found, nothing to report".to_string()];
+                report
+                    .notes
+                    .push("No useful data found, nothing to report".to_string());
             }
         }
     } else {
-        match correlate_commits_issues_discussions(
+        let final_summary = correlate_commits_issues_discussions(
+            llm,
+            &config.synthesis,
+            prompts,
             Some(&_profile_data),
             Some(&commits_summaries),
             Some(&issues_summaries),
+            Some(&pulls_summaries),
+            Some(&reviews_summaries),
             Some(&discussion_data),
+            Some(link_facts.as_str()).filter(|facts| !facts.is_empty()),
             user_name.as_deref(),
+            &range.period(),
             total_input_entry_count,
         )
-        .await
-        {
-            None => {
-                report = vec!["no report generated".to_string()];
+        .await?;
+        notifier.notify("ch_err", &final_summary).await;
+
+        match read_synthesis(llm, &config.synthesis, prompts, &final_summary).await {
+            Ok(synthesis) => report.synthesis = Some(synthesis).filter(|s| !s.is_empty()),
+            Err(e) => {
+                log::error!("falling back to the raw synthesis: {e}");
+                report.synthesis_text = Some(final_summary);
             }
-            Some(final_summary) => {
-               slack_flows::send_message_to_channel("ik8", "ch_err", format!("{:?}", final_summary)).await;
+        }
+    }
+
+    report.usage = metered.usage();
+    if report.usage.budget_exhausted {
+        report
+            .notes
+            .push("The token budget ran out, some items were not analyzed".to_string());
+    }
+    Ok(report)
+}
+
+/// Release notes for the changes between `from_tag` and `to_tag`: the
+/// commits in between and the merged pull requests they came from, grouped
+/// by change type, each group summarized by the commit analyzer.
+#[allow(clippy::too_many_arguments)]
+pub async fn release_notes_report(
+    client: &GitHubClient,
+    llm: &dyn LlmClient,
+    store: &dyn KvStore,
+    config: &PipelineConfig,
+    prompts: &PromptRegistry,
+    owner: &str,
+    repo: &str,
+    from_tag: &str,
+    to_tag: &str,
+) -> Result<ReleaseNotes, AnalyzerError> {
+    let metered = MeteredLlm::new(llm, config);
+    let llm: &dyn LlmClient = &metered;
 
-                let clean_summary = parse_summary_from_raw_json(&final_summary);
-                report.push(clean_summary);
+    let commits = compare_refs(client, owner, repo, from_tag, to_tag).await?;
+    let pulls = get_commit_pull_requests(client, owner, repo, &commits).await;
+    let release = match get_release_by_tag(client, owner, repo, to_tag).await {
+        Ok(release) => release,
+        Err(e) => {
+            log::warn!("failed to get releases of {owner}/{repo}: {e}");
+            None
+        }
+    };
+
+    let mut notes = ReleaseNotes::new(owner, repo, from_tag, to_tag, commits, &pulls)
+        .with_web_url(client.web_url())
+        .with_release(release);
+    for group in notes.groups.iter_mut() {
+        let mut commits = group
+            .entries
+            .iter()
+            .flat_map(|entry| entry.commits.clone())
+            .collect::<Vec<GitMemory>>();
+        let is_sparce = commits.len() <= config.long_context_max_items;
+        if let Err(e) = process_commits(
+            client,
+            llm,
+            store,
+            config,
+            prompts,
+            &mut commits,
+            false,
+            is_sparce,
+            None,
+        )
+        .await
+        {
+            log::error!("summarizing {} failed: {}", group.heading, e);
+            continue;
+        }
+        for commit in group.entries.iter_mut().flat_map(|e| e.commits.iter_mut()) {
+            if let Some(done) = commits.iter().find(|c| c.source_url == commit.source_url) {
+                commit.payload = done.payload.clone();
             }
         }
     }
+    Ok(notes)
+}
 
-    report.join("\n")
+/// Copies the per-item LLM summaries onto the report items with the same url.
+fn attach_summaries(items: &mut [ReportItem], memories: &[GitMemory]) {
+    for item in items.iter_mut() {
+        if let Some(gm) = memories.iter().find(|gm| gm.source_url == item.url) {
+            item.summary = Some(gm.payload.clone()).filter(|p| !p.is_empty());
+        }
+    }
 }
 
 /*
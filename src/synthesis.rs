@@ -0,0 +1,111 @@
+use crate::error::AnalyzerError;
+use crate::llm::{generate, LlmClient};
+use crate::pipeline_config::StageConfig;
+use crate::prompts::PromptRegistry;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// JSON schema of [`Synthesis`], handed to the model by the synthesis prompts.
+pub const SYNTHESIS_SCHEMA: &str = r#"{"type": "object", "properties": {"impactful": {"type": "string", "description": "impactful contributions and their interconnections"}, "alignment": {"type": "string", "description": "alignment with the project's goals"}, "patterns": {"type": "string", "description": "recurring patterns or trends"}, "synergy": {"type": "string", "description": "synergy between individual and collective advancement"}, "significance": {"type": "string", "description": "significance of each contribution"}}, "required": ["impactful", "alignment", "patterns", "synergy", "significance"]}"#;
+
+/// The final synthesis of a report. Points the model had nothing to say
+/// about are empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Synthesis {
+    #[serde(default, deserialize_with = "text_or_list")]
+    pub impactful: String,
+    #[serde(default, deserialize_with = "text_or_list")]
+    pub alignment: String,
+    #[serde(default, deserialize_with = "text_or_list")]
+    pub patterns: String,
+    #[serde(default, deserialize_with = "text_or_list")]
+    pub synergy: String,
+    #[serde(default, deserialize_with = "text_or_list")]
+    pub significance: String,
+}
+
+impl Synthesis {
+    /// Reads the JSON object in a model answer, ignoring code fences or
+    /// prose around it.
+    pub fn parse(answer: &str) -> Result<Self, AnalyzerError> {
+        let json = match (answer.find('{'), answer.rfind('}')) {
+            (Some(start), Some(end)) if start < end => &answer[start..=end],
+            _ => {
+                return Err(AnalyzerError::Llm(
+                    "synthesis holds no JSON object".to_string(),
+                ))
+            }
+        };
+        serde_json::from_str(json)
+            .map_err(|e| AnalyzerError::Llm(format!("synthesis is not valid JSON: {e}")))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points().iter().all(|point| point.trim().is_empty())
+    }
+
+    /// One bullet point per point the model filled in.
+    pub fn to_bullets(&self) -> String {
+        self.points()
+            .iter()
+            .map(|point| point.split_whitespace().collect::<Vec<&str>>().join(" "))
+            .filter(|point| !point.is_empty())
+            .map(|point| format!("- {point}"))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn points(&self) -> [&str; 5] {
+        [
+            &self.impactful,
+            &self.alignment,
+            &self.patterns,
+            &self.synergy,
+            &self.significance,
+        ]
+    }
+}
+
+/// Models often answer a point with a list of sentences instead of one string.
+fn text_or_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Null => String::new(),
+        Value::String(text) => text,
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(text) => text,
+                other => other.to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+        other => other.to_string(),
+    })
+}
+
+/// Parses the synthesis `answer`. When it is malformed, e.g. cut off at the
+/// token limit, the model is asked once to rewrite it as JSON.
+pub async fn read_synthesis(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
+    prompts: &PromptRegistry,
+    answer: &str,
+) -> Result<Synthesis, AnalyzerError> {
+    let e = match Synthesis::parse(answer) {
+        Ok(synthesis) => return Ok(synthesis),
+        Err(e) => e,
+    };
+    log::warn!("{e}, asking the model to repair it");
+
+    let usr_prompt = prompts.render(
+        "synthesis_repair",
+        &[("schema", SYNTHESIS_SCHEMA), ("answer", &stage.fit(answer))],
+    )?;
+    let req = stage
+        .request("synthesis-repair")
+        .with_user(&usr_prompt)
+        .with_temperature(0.0)
+        .with_json_output(stage.json_mode);
+    let repaired = generate(llm, &req, "repairing synthesis").await?;
+    Synthesis::parse(&repaired)
+}
@@ -0,0 +1,148 @@
+use crate::error::AnalyzerError;
+use crate::llm::{ChatRequest, LlmClient};
+use crate::pipeline_config::{ModelPrice, PipelineConfig};
+use crate::utils::count_tokens;
+use futures::future::LocalBoxFuture;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// Tokens OpenAI adds around every message, and once to prime the reply.
+const TOKENS_PER_MESSAGE: u64 = 4;
+const TOKENS_PER_REPLY: u64 = 3;
+
+/// Tokens and estimated cost of the LLM calls of one stage with one model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct StageUsage {
+    pub stage: String,
+    pub model: String,
+    pub calls: u32,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+}
+
+/// What a report spent on the LLM. Tokens are counted with cl100k_base on
+/// our side, so they are close to, but not exactly, what the provider bills.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Usage {
+    pub stages: Vec<StageUsage>,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost: f64,
+    /// Models without a price, left out of `cost`.
+    pub unpriced_models: Vec<String>,
+    /// Whether the `max_tokens` or `max_cost` budget was used up.
+    pub budget_exhausted: bool,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+}
+
+/// Wraps an [`LlmClient`], recording the tokens of every successful call
+/// and reporting [`LlmClient::over_budget`] against the config's limits.
+pub struct MeteredLlm<'a> {
+    inner: &'a dyn LlmClient,
+    prices: BTreeMap<String, ModelPrice>,
+    max_tokens: Option<u64>,
+    max_cost: Option<f64>,
+    stages: Mutex<BTreeMap<(String, String), StageUsage>>,
+}
+
+impl<'a> MeteredLlm<'a> {
+    pub fn new(inner: &'a dyn LlmClient, config: &PipelineConfig) -> Self {
+        MeteredLlm {
+            inner,
+            prices: config.prices.clone(),
+            max_tokens: config.max_tokens,
+            max_cost: config.max_cost,
+            stages: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn usage(&self) -> Usage {
+        let stages = self
+            .stages
+            .lock()
+            .map(|stages| stages.values().cloned().collect::<Vec<StageUsage>>())
+            .unwrap_or_default();
+        let mut unpriced_models = stages
+            .iter()
+            .filter(|s| !self.prices.contains_key(&s.model))
+            .map(|s| s.model.clone())
+            .collect::<Vec<String>>();
+        unpriced_models.sort();
+        unpriced_models.dedup();
+        Usage {
+            prompt_tokens: stages.iter().map(|s| s.prompt_tokens).sum(),
+            completion_tokens: stages.iter().map(|s| s.completion_tokens).sum(),
+            cost: stages.iter().map(|s| s.cost).sum(),
+            unpriced_models,
+            budget_exhausted: self.over_budget(),
+            stages,
+        }
+    }
+
+    fn record(&self, req: &ChatRequest, answer: &str) {
+        let prompt_tokens = req
+            .messages
+            .iter()
+            .map(|m| count_tokens(&m.content) as u64 + TOKENS_PER_MESSAGE)
+            .sum::<u64>()
+            + TOKENS_PER_REPLY;
+        let completion_tokens = count_tokens(answer) as u64;
+        let model = self.inner.model_for(req);
+        let cost = self.prices.get(&model).map_or(0.0, |price| {
+            (prompt_tokens as f64 * price.prompt + completion_tokens as f64 * price.completion)
+                / 1000.0
+        });
+
+        let Ok(mut stages) = self.stages.lock() else {
+            return;
+        };
+        let usage = stages
+            .entry((req.stage.clone(), model.clone()))
+            .or_insert_with(|| StageUsage {
+                stage: req.stage.clone(),
+                model,
+                ..Default::default()
+            });
+        usage.calls += 1;
+        usage.prompt_tokens += prompt_tokens;
+        usage.completion_tokens += completion_tokens;
+        usage.cost += cost;
+    }
+}
+
+impl LlmClient for MeteredLlm<'_> {
+    fn chat<'a>(
+        &'a self,
+        req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        Box::pin(async move {
+            let answer = self.inner.chat(req).await?;
+            self.record(req, &answer);
+            Ok(answer)
+        })
+    }
+
+    fn model_for(&self, req: &ChatRequest) -> String {
+        self.inner.model_for(req)
+    }
+
+    fn over_budget(&self) -> bool {
+        let Ok(stages) = self.stages.lock() else {
+            return false;
+        };
+        let tokens = stages
+            .values()
+            .map(|s| s.prompt_tokens + s.completion_tokens)
+            .sum::<u64>();
+        let cost = stages.values().map(|s| s.cost).sum::<f64>();
+        self.max_tokens.is_some_and(|max| tokens >= max)
+            || self.max_cost.is_some_and(|max| cost >= max)
+    }
+}
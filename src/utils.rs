@@ -1,13 +1,14 @@
-use http_req::{request::Method, request::Request, response, uri::Uri};
-use log;
-use openai_flows::{
-    chat::{ChatModel, ChatOptions},
-    OpenAIFlows,
-};
-use serde::Deserialize;
+use crate::error::AnalyzerError;
+use crate::kv_store::KvStore;
+use crate::llm::{generate, LlmClient};
+use crate::pipeline_config::StageConfig;
+use futures::{stream, StreamExt};
 use serde_json::Value;
 use std::collections::HashSet;
-use store_flows::{get, set};
+use std::env;
+use std::future::Future;
+use std::sync::OnceLock;
+use tiktoken_rs::CoreBPE;
 /*
 use crypto::{symmetriccipher, buffer, aes, blockmodes};
 use crypto::buffer::{ReadBuffer, WriteBuffer, BufferResult};
@@ -37,6 +38,33 @@ fn get_vals(hex_key: &str) -> (String, String) {
 
  */
 
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// How many fetch/LLM steps may be in flight at once, from the optional
+/// `analysis_concurrency` environment variable.
+pub fn analysis_concurrency() -> usize {
+    env::var("analysis_concurrency")
+        .ok()
+        .and_then(|n| n.parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_CONCURRENCY)
+}
+
+/// Runs `f` over `items` with at most `limit` futures in flight on the
+/// current task, returning the outputs in input order.
+pub async fn map_bounded<I, T, F, Fut>(items: I, limit: usize, f: F) -> Vec<T>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future<Output = T>,
+{
+    stream::iter(items)
+        .map(f)
+        .buffered(limit.max(1))
+        .collect()
+        .await
+}
+
 pub fn squeeze_fit_remove_quoted(inp_str: &str, max_len: u16, split: f32) -> String {
     let mut body = String::new();
     let mut inside_quote = false;
@@ -87,12 +115,24 @@ pub fn squeeze_fit_remove_quoted(inp_str: &str, max_len: u16, split: f32) -> Str
     final_text
 }
 
-pub fn squeeze_fit_post_texts(inp_str: &str, max_len: u16, split: f32) -> String {
-    let bpe = tiktoken_rs::cl100k_base().unwrap();
+/// The cl100k_base tokenizer, built once per process.
+pub fn bpe() -> &'static CoreBPE {
+    static BPE: OnceLock<CoreBPE> = OnceLock::new();
+    BPE.get_or_init(|| tiktoken_rs::cl100k_base().expect("cl100k_base ships with tiktoken-rs"))
+}
+
+pub fn count_tokens(text: &str) -> usize {
+    bpe().encode_ordinary(text).len()
+}
+
+/// Cuts `inp_str` to `max_len` tokens by dropping its middle, keeping `split`
+/// of them from the beginning (1.0 keeps only the beginning).
+pub fn squeeze_fit_post_texts(inp_str: &str, max_len: usize, split: f32) -> String {
+    let bpe = bpe();
 
     let input_token_vec = bpe.encode_ordinary(inp_str);
     let input_len = input_token_vec.len();
-    if input_len < max_len as usize {
+    if input_len < max_len {
         return inp_str.to_string();
     }
     // // Filter out the tokens corresponding to lines with undesired patterns
@@ -103,10 +143,10 @@ pub fn squeeze_fit_post_texts(inp_str: &str, max_len: u16, split: f32) -> String
     //         filtered_tokens.extend(tokens_for_line.drain(..));
     //     }
     // }
-    let n_take_from_beginning = (input_len as f32 * split).ceil() as usize;
-    let n_take_from_end = max_len as usize - n_take_from_beginning;
+    let n_take_from_beginning = ((max_len as f32 * split).ceil() as usize).min(max_len);
+    let n_take_from_end = max_len - n_take_from_beginning;
 
-    let mut concatenated_tokens = Vec::with_capacity(max_len as usize);
+    let mut concatenated_tokens = Vec::with_capacity(max_len);
     concatenated_tokens.extend_from_slice(&input_token_vec[..n_take_from_beginning]);
     concatenated_tokens.extend_from_slice(&input_token_vec[input_len - n_take_from_end..]);
 
@@ -115,7 +155,10 @@ pub fn squeeze_fit_post_texts(inp_str: &str, max_len: u16, split: f32) -> String
         .map_or("failed to decode tokens".to_string(), |s| s.to_string())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn chain_of_chat(
+    llm: &dyn LlmClient,
+    stage: &StageConfig,
     sys_prompt_1: &str,
     usr_prompt_1: &str,
     chat_id: &str,
@@ -123,141 +166,30 @@ pub async fn chain_of_chat(
     usr_prompt_2: &str,
     gen_len_2: u16,
     error_tag: &str,
-) -> Option<String> {
-    let openai = OpenAIFlows::new();
-
-    let co_1 = ChatOptions {
-        model: ChatModel::GPT35Turbo16K,
-        restart: true,
-        system_prompt: Some(sys_prompt_1),
-        max_tokens: Some(gen_len_1),
-        temperature: Some(0.7),
-        ..Default::default()
-    };
-
-    match openai.chat_completion(chat_id, usr_prompt_1, &co_1).await {
-        Ok(res_1) => {
-            let sys_prompt_2 = serde_json::json!([{"role": "system", "content": sys_prompt_1},
-    {"role": "user", "content": usr_prompt_1},
-    {"role": "assistant", "content": &res_1.choice}])
-            .to_string();
-
-            let co_2 = ChatOptions {
-                model: ChatModel::GPT35Turbo16K,
-                restart: false,
-                system_prompt: Some(&sys_prompt_2),
-                max_tokens: Some(gen_len_2),
-                temperature: Some(0.7),
-                ..Default::default()
-            };
-            match openai.chat_completion(chat_id, usr_prompt_2, &co_2).await {
-                Ok(res_2) => {
-                    if res_2.choice.len() < 10 {
-                        log::error!(
-                            "{}, GPT generation went sideway: {:?}",
-                            error_tag,
-                            res_2.choice
-                        );
-                        return None;
-                    }
-                    return Some(res_2.choice);
-                }
-                Err(_e) => log::error!("{}, Step 2 GPT generation error {:?}", error_tag, _e),
-            };
-        }
-        Err(_e) => log::error!("{}, Step 1 GPT generation error {:?}", error_tag, _e),
-    }
-
-    None
-}
-
-pub async fn github_http_fetch(token: &str, url: &str) -> Option<Vec<u8>> {
-    let url = Uri::try_from(url).unwrap();
-    let mut writer = Vec::new();
-
-    match Request::new(&url)
-        .method(Method::GET)
-        .header("User-Agent", "flows-network connector")
-        .header("Content-Type", "application/vnd.github.v3+json")
-        .header("Authorization", &format!("Bearer {token}"))
-        .send(&mut writer)
-    {
-        Ok(res) => {
-            if !res.status_code().is_success() {
-                log::error!("Github http error {:?}", res.status_code());
-                return None;
-            };
-
-            Some(writer)
-        }
-        Err(_e) => {
-            log::error!("Error getting response from Github: {:?}", _e);
-            None
-        }
-    }
-}
-
-pub fn github_fetch_with_header(
-    token: &str,
-    url: &str,
-) -> Result<(response::Response, Vec<u8>), Box<dyn std::error::Error>> {
-    let uri = Uri::try_from(url)?;
-    let mut writer = std::io::Cursor::new(Vec::new());
-
-    let response = match Request::new(&uri)
-        .method(Method::GET)
-        .header("User-Agent", "flows-network connector")
-        .header("Content-Type", "application/vnd.github.v3+json")
-        .header("Authorization", &format!("Bearer {}", token))
-        .send(&mut writer)
-    {
-        Ok(res) => {
-            if !res.status_code().is_success() {
-                return Err(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "github_fetch_with_header encountered Github http error",
-                )));
-            };
-            res
-        }
-        Err(e) => {
-            log::error!("Error getting response from Github: {:?}", e);
-            return Err(Box::new(e));
-        }
-    };
-
-    Ok((response, writer.into_inner()))
-}
-
-pub async fn github_http_post(token: &str, base_url: &str, query: &str) -> Option<Vec<u8>> {
-    let base_url = Uri::try_from(base_url).unwrap();
-    let mut writer = Vec::new();
-
-    let query = serde_json::json!({"query": query});
-    match Request::new(&base_url)
-        .method(Method::POST)
-        .header("User-Agent", "flows-network connector")
-        .header("Content-Type", "application/json")
-        .header("Authorization", &format!("Bearer {}", token))
-        .header("Content-Length", &query.to_string().len())
-        .body(&query.to_string().into_bytes())
-        .send(&mut writer)
-    {
-        Ok(res) => {
-            if !res.status_code().is_success() {
-                log::error!("Github http error {:?}", res.status_code());
-                return None;
-            };
-            Some(writer)
-        }
-        Err(_e) => {
-            log::error!("Error getting response from Github: {:?}", _e);
-            None
-        }
+) -> Result<String, AnalyzerError> {
+    let req_1 = stage
+        .request(chat_id)
+        .with_system(sys_prompt_1)
+        .with_user(usr_prompt_1)
+        .with_max_tokens(gen_len_1.min(stage.max_output_tokens));
+    let res_1 = generate(llm, &req_1, &format!("{error_tag}, step 1")).await?;
+
+    let req_2 = req_1
+        .with_assistant(&res_1)
+        .with_user(usr_prompt_2)
+        .with_max_tokens(gen_len_2.min(stage.max_output_tokens));
+    let res_2 = generate(llm, &req_2, &format!("{error_tag}, step 2")).await?;
+
+    if res_2.len() < 10 {
+        return Err(AnalyzerError::Llm(format!(
+            "{error_tag}, generation went sideway: {:?}",
+            res_2
+        )));
     }
+    Ok(res_2)
 }
 
-pub async fn save_user(owner: &str, repo: &str, user_name: &str) -> bool {
+pub async fn save_user(store: &dyn KvStore, owner: &str, repo: &str, user_name: &str) -> bool {
     use std::hash::Hasher;
     use twox_hash::XxHash;
     let repo_string = format!("{owner}/{repo}");
@@ -266,7 +198,8 @@ pub async fn save_user(owner: &str, repo: &str, user_name: &str) -> bool {
     let hash = hasher.finish();
     let key = &format!("{:x}", hash);
 
-    let mut existing_users: HashSet<String> = get(key)
+    let mut existing_users: HashSet<String> = store
+        .get(key)
         .and_then(|val| serde_json::from_value(val).ok())
         .unwrap_or_else(HashSet::new);
 
@@ -279,115 +212,11 @@ pub async fn save_user(owner: &str, repo: &str, user_name: &str) -> bool {
     }
 
     // Save updated records
-    set(
+    store.set(
         key,
         Value::String(serde_json::to_string(&existing_users).unwrap()),
-        None,
     );
 
     // If the user_name was added, return true; otherwise, return false
     !already_exists
 }
-
-pub fn custom_json_parser(input: &str) -> Option<String> {
-    #[derive(Debug, Deserialize)]
-    struct GitHubIssueSummary {
-        principal_arguments: Option<Vec<String>>,
-        suggested_solutions: Option<Vec<String>>,
-        areas_of_consensus: Option<Vec<String>>,
-        areas_of_disagreement: Option<Vec<String>>,
-        concise_summary: Option<String>,
-    }
-
-    let mut parsed_data: std::collections::HashMap<String, serde_json::Value> =
-        std::collections::HashMap::new();
-
-    let lines: Vec<&str> = input.lines().collect();
-    for line in lines {
-        if line.trim().starts_with("\"") {
-            let parts: Vec<&str> = line.split(':').collect();
-            if parts.len() >= 2 {
-                let key = parts[0].trim_matches(|c| c == '"' || c == ' ');
-                let value: String = parts[1..].join(":");
-
-                if value.len() >= 15 {
-                    // Ignore if data is less than 15 characters
-                    if let Ok(json_value) = serde_json::from_str(&value) {
-                        parsed_data.insert(key.to_string(), json_value);
-                    }
-                }
-            }
-        }
-    }
-
-    let mut summary = GitHubIssueSummary {
-        principal_arguments: None,
-        suggested_solutions: None,
-        areas_of_consensus: None,
-        areas_of_disagreement: None,
-        concise_summary: None,
-    };
-
-    if let Some(val) = parsed_data.get("principal_arguments") {
-        if let Ok(converted) = serde_json::from_value(val.clone()) {
-            summary.principal_arguments = Some(converted);
-        }
-    }
-
-    if let Some(val) = parsed_data.get("suggested_solutions") {
-        if let Ok(converted) = serde_json::from_value(val.clone()) {
-            summary.suggested_solutions = Some(converted);
-        }
-    }
-
-    if let Some(val) = parsed_data.get("areas_of_consensus") {
-        if let Ok(converted) = serde_json::from_value(val.clone()) {
-            summary.areas_of_consensus = Some(converted);
-        }
-    }
-
-    if let Some(val) = parsed_data.get("areas_of_disagreement") {
-        if let Ok(converted) = serde_json::from_value(val.clone()) {
-            summary.areas_of_disagreement = Some(converted);
-        }
-    }
-
-    if let Some(val) = parsed_data.get("concise_summary") {
-        if let Ok(converted) = serde_json::from_value(val.clone()) {
-            summary.concise_summary = Some(converted);
-        }
-    }
-
-    Some(summary.concise_summary.unwrap_or("".to_string()))
-}
-
-
-pub fn parse_summary_from_raw_json(input: &str) -> String {
-    let keys = ["impactful", "alignment", "patterns", "synergy", "significance"];
-    let mut output = String::new();
-
-    for i in 0..keys.len() {
-        let key_string = format!("\"{}\":", keys[i]);
-        if let Some(start) = input.find(&key_string) {
-            let value_start = start + key_string.len();
-            let value_end = if i < keys.len() - 1 {
-                let next_key = format!("\"{}\":", keys[i+1]);
-                match input[value_start..].find(&next_key) {
-                    Some(end) => end,
-                    None => input.len() - value_start,
-                }
-            } else {
-                input.len() - value_start
-            };
-            let value = &input[value_start..value_start + value_end].trim().trim_matches('"');
-            let value = value.replace("\n", " ").replace('{', "").trim().replace('}', "");
-            if value.len() >= 15 {
-                output += "- ";
-                output += value.trim();
-                output += "\n";
-            }
-        }
-    }
-
-    output.trim_end().to_string()
-}
\ No newline at end of file
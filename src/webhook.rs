@@ -0,0 +1,250 @@
+//! The flows.network webhook: `on_deploy` registers it, `handler` serves it.
+
+use crate::data_analyzers::{get_repo_overview_by_scraper, search_bing};
+use crate::date_range::DateRange;
+use crate::github_client::GitHubClient;
+use crate::github_data_fetchers::get_user_data_by_login;
+use crate::kv_store::store_from_env;
+use crate::llm::llm_from_env;
+use crate::notifier::notifier_from_env;
+use crate::pipeline_config::PipelineConfig;
+use crate::prompts::PromptRegistry;
+use crate::report::ReportFormat;
+use crate::reports::weekly_report;
+use flowsnet_platform_sdk::logger;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::env;
+use webhook_flows::{create_endpoint, request_handler, send_response};
+
+#[no_mangle]
+#[tokio::main(flavor = "current_thread")]
+pub async fn on_deploy() {
+    create_endpoint().await;
+}
+
+#[request_handler]
+async fn handler(
+    _headers: Vec<(String, String)>,
+    _subpath: String,
+    _qry: HashMap<String, Value>,
+    _body: Vec<u8>,
+) {
+    logger::init();
+    let github_token = env::var("github_token").expect("github_token was not present in env");
+    let github_client = GitHubClient::from_env(&github_token);
+    let llm = llm_from_env();
+    let store = store_from_env();
+    let notifier = notifier_from_env();
+    let prompts = PromptRegistry::from_env();
+
+    // `cfg.<stage>.<field>=value` params override the pipeline config for this
+    // request, as do the `max_tokens` and `max_cost` budgets.
+    let mut config = PipelineConfig::from_env();
+    for (key, value) in _qry.iter() {
+        let key = match key.as_str() {
+            "max_tokens" | "max_cost" => key.as_str(),
+            _ => match key.strip_prefix("cfg.") {
+                Some(key) => key,
+                None => continue,
+            },
+        };
+        let value = value.as_str().map_or(value.to_string(), |v| v.to_string());
+        config = match config.with_override(key, &value) {
+            Ok(config) => config,
+            Err(e) => {
+                send_response(
+                    e.status_code(),
+                    vec![(String::from("content-type"), String::from("text/plain"))],
+                    e.to_string().as_bytes().to_vec(),
+                );
+                return;
+            }
+        };
+    }
+    let Ocp_Apim_Subscription_Key = env::var("bing_key").expect("bing key was not present in env");
+
+    let user_login = _qry
+        .get("login")
+        .unwrap_or(&Value::Null)
+        .as_str()
+        .map(|n| n.to_string());
+
+    if user_login.is_some() {
+        match get_user_data_by_login(&github_client, &user_login.clone().unwrap()).await {
+            Ok(pro) => {
+                let query = &format!("github user {}", user_login.unwrap());
+
+                let search_data = search_bing(&Ocp_Apim_Subscription_Key, query)
+                    .await
+                    .unwrap_or("".to_string());
+
+                // let res = maybe_include_search_data(&pro, &search_data)
+                //     .await
+                //     .unwrap_or("failed to merge data".to_string());
+
+                send_response(
+                    200,
+                    vec![(String::from("content-type"), String::from("text/plain"))],
+                    format!(
+                        "Found on profile: {}\nFound with search: {}",
+                        pro, search_data
+                    )
+                    .as_bytes()
+                    .to_vec(),
+                )
+            }
+            Err(e) => send_response(
+                e.status_code(),
+                vec![(String::from("content-type"), String::from("text/plain"))],
+                format!("failed to find user with such login: {e}")
+                    .as_bytes()
+                    .to_vec(),
+            ),
+        }
+        return;
+    }
+    let about_repo = _qry
+        .get("about_repo")
+        .unwrap_or(&Value::Null)
+        .as_str()
+        .map(|n| n.to_string());
+
+    if let Some(about_repo) = about_repo {
+        let prompts = prompts.with_store(store.as_ref(), &about_repo);
+        match get_repo_overview_by_scraper(
+            &github_client,
+            llm.as_ref(),
+            &config,
+            &prompts,
+            &about_repo,
+        )
+        .await
+        {
+            Err(e) => {
+                send_response(
+                    e.status_code(),
+                    vec![(String::from("content-type"), String::from("text/plain"))],
+                    e.to_string().as_bytes().to_vec(),
+                );
+            }
+            Ok(summary) => {
+                let _profile_data = format!("About {}: {}", about_repo, summary);
+                send_response(
+                    200,
+                    vec![(String::from("content-type"), String::from("text/plain"))],
+                    _profile_data.as_bytes().to_vec(),
+                )
+            }
+        }
+        return;
+    }
+
+    let (owner, repo) = match (
+        _qry.get("owner").unwrap_or(&Value::Null).as_str(),
+        _qry.get("repo").unwrap_or(&Value::Null).as_str(),
+    ) {
+        (Some(o), Some(r)) => (o.to_string(), r.to_string()),
+        (_, _) => {
+            send_response(
+                400,
+                vec![(String::from("content-type"), String::from("text/plain"))],
+                "You must provide an owner and repo name."
+                    .as_bytes()
+                    .to_vec(),
+            );
+            return;
+        }
+    };
+
+    let user_name = _qry
+        .get("username")
+        .unwrap_or(&Value::Null)
+        .as_str()
+        .map(|n| n.to_string());
+    let branch = _qry
+        .get("branch")
+        .unwrap_or(&Value::Null)
+        .as_str()
+        .map(|n| n.to_string());
+    let token = _qry
+        .get("token")
+        .unwrap_or(&Value::Null)
+        .as_str()
+        .map(|n| n.to_string());
+
+    let range = match DateRange::from_params(
+        _qry.get("days").unwrap_or(&Value::Null).as_str(),
+        _qry.get("from").unwrap_or(&Value::Null).as_str(),
+        _qry.get("to").unwrap_or(&Value::Null).as_str(),
+    ) {
+        Ok(range) => range,
+        Err(e) => {
+            send_response(
+                e.status_code(),
+                vec![(String::from("content-type"), String::from("text/plain"))],
+                e.to_string().as_bytes().to_vec(),
+            );
+            return;
+        }
+    };
+
+    let format = match _qry.get("format").unwrap_or(&Value::Null).as_str() {
+        Some(format) => match ReportFormat::from_param(format) {
+            Some(format) => format,
+            None => {
+                send_response(
+                    400,
+                    vec![(String::from("content-type"), String::from("text/plain"))],
+                    format!("Unsupported format {format}, use text, markdown, json or html.")
+                        .as_bytes()
+                        .to_vec(),
+                );
+                return;
+            }
+        },
+        None => _headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("accept"))
+            .and_then(|(_, accept)| ReportFormat::from_accept(accept))
+            .unwrap_or(ReportFormat::Text),
+    };
+
+    let prompts = prompts.with_store(store.as_ref(), &format!("{owner}/{repo}"));
+    match weekly_report(
+        &github_client,
+        llm.as_ref(),
+        store.as_ref(),
+        notifier.as_ref(),
+        &config,
+        &prompts,
+        &owner,
+        &repo,
+        user_name,
+        &range,
+        branch,
+        token.clone(),
+    )
+    .await
+    {
+        Ok(report) => {
+            send_response(
+                200,
+                vec![(
+                    String::from("content-type"),
+                    String::from(format.content_type()),
+                )],
+                report.render(format).as_bytes().to_vec(),
+            );
+            notifier.notify("ch_err", &report.render_text()).await;
+        }
+        Err(e) => {
+            log::error!("weekly report for {owner}/{repo} failed: {e}");
+            send_response(
+                e.status_code(),
+                vec![(String::from("content-type"), String::from("text/plain"))],
+                e.to_string().as_bytes().to_vec(),
+            );
+        }
+    }
+}
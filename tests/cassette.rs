@@ -0,0 +1,134 @@
+mod support;
+
+use github_analyzer::cassette::Cassette;
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_client::GitHubClient;
+use github_analyzer::http::{HttpRequest, Method};
+use github_analyzer::kv_store::MemoryStore;
+use github_analyzer::llm::{ChatRequest, FakeLlm, LlmClient};
+use github_analyzer::notifier::LogNotifier;
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use github_analyzer::report::Report;
+use github_analyzer::reports::weekly_report;
+use std::path::PathBuf;
+use std::sync::Arc;
+use support::{demo_range, demo_repo, MockGitHub, OWNER, REPO, SYNTHESIS_JSON};
+
+fn cassette_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "github-analyzer-{}-{name}.json",
+        std::process::id()
+    ))
+}
+
+async fn run(
+    cassette: &Arc<Cassette>,
+    mock: &Arc<MockGitHub>,
+    llm: &FakeLlm,
+) -> Result<Report, AnalyzerError> {
+    let client = GitHubClient::new("test-token")
+        .with_transport(cassette.transport(mock.clone()))
+        .with_max_retries(0);
+    weekly_report(
+        &client,
+        &cassette.llm(llm),
+        &MemoryStore::new(),
+        &LogNotifier,
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        OWNER,
+        REPO,
+        None,
+        &demo_range(),
+        None,
+        None,
+    )
+    .await
+}
+
+#[tokio::test]
+async fn replays_a_recorded_report_offline() {
+    let path = cassette_path("weekly");
+    let recorder = Arc::new(Cassette::record(&path));
+    let llm = FakeLlm::new().with_stage_reply("synthesis", SYNTHESIS_JSON);
+    let recorded = run(&recorder, &Arc::new(demo_repo()), &llm).await.unwrap();
+    recorder.save().unwrap();
+
+    let player = Arc::new(Cassette::replay(&path).unwrap());
+    let offline = Arc::new(MockGitHub::new());
+    let live_llm = FakeLlm::new();
+    let mut replayed = run(&player, &offline, &live_llm).await.unwrap();
+    std::fs::remove_file(&path).ok();
+
+    replayed.generated_at = recorded.generated_at;
+    assert_eq!(replayed.render_markdown(), recorded.render_markdown());
+    assert_eq!(replayed.synthesis, recorded.synthesis);
+    assert!(offline.paths().is_empty());
+    assert!(live_llm.requests().is_empty());
+}
+
+#[tokio::test]
+async fn replay_asks_the_model_only_for_changed_prompts() {
+    let path = cassette_path("changed-prompt");
+    let recorder = Cassette::record(&path);
+    let chat = |prompt: &str| {
+        ChatRequest::new("issue-12", "gpt-3.5-turbo")
+            .with_stage("issue")
+            .with_user(prompt)
+    };
+    recorder
+        .llm(&FakeLlm::with_replies(["recorded"]))
+        .chat(&chat("summarize #12"))
+        .await
+        .unwrap();
+    recorder.save().unwrap();
+
+    let player = Cassette::replay(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    let live_llm = FakeLlm::with_replies(["live"]);
+    let llm = player.llm(&live_llm);
+
+    assert_eq!(llm.chat(&chat("summarize #12")).await.unwrap(), "recorded");
+    assert_eq!(
+        llm.chat(&chat("summarize #12 briefly")).await.unwrap(),
+        "live"
+    );
+    assert_eq!(live_llm.requests().len(), 1);
+}
+
+#[tokio::test]
+async fn recorded_urls_hide_the_token() {
+    let path = cassette_path("token");
+    let recorder = Arc::new(Cassette::record(&path));
+    let mock = Arc::new(demo_repo());
+    let url = "https://api.github.com/repos/octo-org/demo/pulls/13&token=ghp_secret";
+    recorder
+        .transport(mock)
+        .send(&HttpRequest::new(Method::Get, url))
+        .await
+        .unwrap();
+    recorder.save().unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+
+    assert!(text.contains("pulls/13&token=REDACTED"));
+    assert!(!text.contains("ghp_secret"));
+
+    let player = Arc::new(Cassette::replay(&path).unwrap());
+    std::fs::remove_file(&path).ok();
+    let offline = player.transport(Arc::new(MockGitHub::new()));
+    assert!(offline
+        .send(&HttpRequest::new(Method::Get, url))
+        .await
+        .unwrap()
+        .is_success());
+    assert!(matches!(
+        offline
+            .send(&HttpRequest::new(
+                Method::Get,
+                "https://api.github.com/repos/octo-org/demo/pulls/14"
+            ))
+            .await,
+        Err(AnalyzerError::Transport(_))
+    ));
+}
@@ -0,0 +1,139 @@
+mod support;
+
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_client::GitHubClient;
+use github_analyzer::llm::{ChatRequest, LlmClient, OpenAiCompatLlm};
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::usage::MeteredLlm;
+use std::sync::Arc;
+use std::time::Duration;
+use support::{ok, status, Scripted};
+use tokio::time::Instant;
+
+fn client(transport: &Arc<Scripted>) -> GitHubClient {
+    GitHubClient::new("test-token").with_transport(transport.clone())
+}
+
+#[tokio::test(start_paused = true)]
+async fn waits_as_long_as_retry_after_asks() {
+    let transport = Arc::new(Scripted::new([
+        status(429, &[("Retry-After", "30")]),
+        ok("{}"),
+    ]));
+    let started = Instant::now();
+
+    let body = client(&transport).get("repos/octo-org/demo").await.unwrap();
+
+    assert_eq!(body, b"{}");
+    assert_eq!(transport.sent(), 2);
+    assert!(started.elapsed() >= Duration::from_secs(30));
+}
+
+#[tokio::test(start_paused = true)]
+async fn retries_server_errors_with_backoff() {
+    let transport = Arc::new(Scripted::new([
+        status(502, &[]),
+        status(503, &[]),
+        ok("{}"),
+    ]));
+    let started = Instant::now();
+
+    client(&transport).get("repos/octo-org/demo").await.unwrap();
+
+    assert_eq!(transport.sent(), 3);
+    assert!(started.elapsed() >= Duration::from_secs(1 + 2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn gives_up_once_the_retries_are_spent() {
+    let transport = Arc::new(Scripted::new([status(429, &[("Retry-After", "1")])]));
+
+    let err = client(&transport)
+        .with_max_retries(2)
+        .get("repos/octo-org/demo")
+        .await
+        .unwrap_err();
+
+    assert_eq!(err, AnalyzerError::RateLimited { reset_at: None });
+    assert_eq!(transport.sent(), 3);
+}
+
+#[tokio::test(start_paused = true)]
+async fn fails_at_once_when_the_budget_resets_too_late() {
+    let reset_at = (chrono::Utc::now().timestamp() + 3600).to_string();
+    let transport = Arc::new(Scripted::new([status(
+        403,
+        &[
+            ("X-RateLimit-Limit", "5000"),
+            ("X-RateLimit-Remaining", "0"),
+            ("X-RateLimit-Reset", &reset_at),
+        ],
+    )]));
+    let client = client(&transport);
+
+    let err = client.get("repos/octo-org/demo").await.unwrap_err();
+
+    assert!(matches!(
+        err,
+        AnalyzerError::RateLimited { reset_at: Some(_) }
+    ));
+    assert_eq!(transport.sent(), 1);
+    assert!(!client.has_budget(0));
+    let budget = client.rate_budget("core").unwrap();
+    assert_eq!((budget.limit, budget.remaining), (Some(5000), 0));
+}
+
+#[tokio::test]
+async fn keeps_a_budget_per_resource() {
+    let transport = Arc::new(Scripted::new([status(
+        200,
+        &[
+            ("X-RateLimit-Remaining", "4"),
+            ("X-RateLimit-Resource", "graphql"),
+        ],
+    )]));
+    let client = client(&transport);
+
+    client.post_graphql("{ viewer { login } }").await.unwrap();
+
+    assert_eq!(client.rate_budget("graphql").unwrap().remaining, 4);
+    assert_eq!(client.rate_budget("core"), None);
+    assert!(client.has_budget(10));
+}
+
+#[tokio::test(start_paused = true)]
+async fn chat_retries_an_overloaded_server() {
+    let transport = Arc::new(Scripted::new([
+        status(503, &[]),
+        ok(r#"{"choices": [{"message": {"content": "hello"}}]}"#),
+    ]));
+    let llm = OpenAiCompatLlm::new("http://localhost:11434/v1").with_transport(transport.clone());
+
+    let reply = llm
+        .chat(&ChatRequest::new("chat", "model").with_user("hi"))
+        .await;
+
+    assert_eq!(reply.unwrap(), "hello");
+    assert_eq!(transport.sent(), 2);
+}
+
+#[tokio::test]
+async fn usage_is_counted_for_the_model_actually_used() {
+    let transport = Arc::new(Scripted::new([ok(
+        r#"{"choices": [{"message": {"content": "hello"}}]}"#,
+    )]));
+    let llm = OpenAiCompatLlm::new("http://localhost:11434/v1")
+        .with_model("llama3")
+        .with_transport(transport);
+    let metered = MeteredLlm::new(&llm, &PipelineConfig::default());
+
+    metered
+        .chat(&ChatRequest::new("chat", "gpt-4").with_user("hi"))
+        .await
+        .unwrap();
+
+    let usage = metered.usage();
+    assert_eq!(usage.stages[0].model, "llama3");
+    assert_eq!(usage.unpriced_models, ["llama3"]);
+    assert_eq!(usage.cost, 0.0);
+}
@@ -0,0 +1,269 @@
+mod support;
+
+use github_analyzer::cache::commit_key;
+use github_analyzer::date_range::DateRange;
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_client::{next_page_url, GitHubClient};
+use github_analyzer::github_data_fetchers::get_user_data_by_login;
+use github_analyzer::http::{HttpResponse, Method};
+use github_analyzer::issue_links::{parse_references, IssueRef, LinkKind};
+use github_analyzer::pipeline_config::{PipelineConfig, Truncation};
+use github_analyzer::prompts::PromptRegistry;
+use github_analyzer::release_notes::{change_type, label_type};
+use github_analyzer::report::ReportFormat;
+use github_analyzer::synthesis::Synthesis;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use support::{client, ok, MockGitHub, SYNTHESIS_JSON};
+
+#[test]
+fn synthesis_is_read_from_fenced_or_wordy_answers() {
+    let answer = format!("Here is the summary:\n```json\n{SYNTHESIS_JSON}\n```\nHope it helps!");
+    let synthesis = Synthesis::parse(&answer).unwrap();
+
+    assert_eq!(
+        synthesis.synergy,
+        "Bob documented the retry settings Alice added."
+    );
+    assert_eq!(synthesis.to_bullets().lines().count(), 4);
+    assert!(Synthesis::parse("{}").unwrap().is_empty());
+}
+
+#[test]
+fn synthesis_without_json_is_an_error() {
+    for answer in ["No activity this week.", r#"{"impactful": "cut off"#] {
+        assert!(matches!(
+            Synthesis::parse(answer),
+            Err(AnalyzerError::Llm(_))
+        ));
+    }
+}
+
+#[test]
+fn next_page_is_read_from_the_link_header() {
+    let res = HttpResponse {
+        status: 200,
+        headers: vec![(
+            "link".to_string(),
+            r#"<https://api.github.com/repositories/1/commits?page=2>; rel="next", <https://api.github.com/repositories/1/commits?page=5>; rel="last""#.to_string(),
+        )],
+        body: Vec::new(),
+    };
+
+    assert_eq!(
+        next_page_url(&res).as_deref(),
+        Some("https://api.github.com/repositories/1/commits?page=2")
+    );
+    assert_eq!(next_page_url(&HttpResponse::default()), None);
+}
+
+#[test]
+fn enterprise_urls_follow_the_api_url() {
+    let client = GitHubClient::new("test-token").with_base_url("https://ghe.example.com/api/v3/");
+
+    assert_eq!(client.graphql_url(), "https://ghe.example.com/api/graphql");
+    assert_eq!(client.web_url(), "https://ghe.example.com");
+    let github = GitHubClient::new("test-token").with_base_url("https://api.github.com");
+    assert_eq!(github.web_url(), "https://github.com");
+}
+
+#[tokio::test]
+async fn paginate_follows_next_links() {
+    let mut first = ok("[1, 2]");
+    first.headers.push((
+        "Link".to_string(),
+        r#"<https://api.github.com/numbers/more>; rel="next""#.to_string(),
+    ));
+    let mock = Arc::new(
+        MockGitHub::new()
+            .respond(Method::Get, "/numbers", None, first)
+            .get("/numbers/more", "[3]"),
+    );
+
+    let numbers = client(&mock)
+        .paginate::<u32>("numbers", "numbers")
+        .collect()
+        .await
+        .unwrap();
+
+    assert_eq!(numbers, [1, 2, 3]);
+}
+
+#[tokio::test]
+async fn graphql_errors_fail_only_without_data() {
+    #[derive(Deserialize)]
+    struct Viewer {
+        login: String,
+    }
+
+    let partial = json!({"data": {"login": "alice"}, "errors": [{"message": "rate limited"}]});
+    let failed = json!({"data": null, "errors": [{"message": "Bad credentials"}]});
+    let mock = Arc::new(
+        MockGitHub::new()
+            .graphql("partial", &partial.to_string())
+            .graphql("failed", &failed.to_string()),
+    );
+    let client = client(&mock);
+
+    let viewer = client
+        .graphql::<Viewer>("query partial", json!({}), "viewer")
+        .await
+        .unwrap();
+    assert_eq!(viewer.login, "alice");
+    let res = client
+        .graphql::<Viewer>("query failed", json!({}), "viewer")
+        .await;
+    assert!(matches!(res, Err(AnalyzerError::GraphQl(msg)) if msg == "Bad credentials"));
+}
+
+#[tokio::test]
+async fn user_lookups_send_the_login_as_a_variable() {
+    let login = r#"alice") { id } #"#;
+    let user = json!({"data": {"repositoryOwner": {"login": "alice", "name": "Alice"}}});
+    let mock = Arc::new(MockGitHub::new().graphql("repositoryOwner", &user.to_string()));
+
+    let profile = get_user_data_by_login(&client(&mock), login).await.unwrap();
+
+    assert!(profile.contains("Login: alice,"));
+    let sent = mock.requests();
+    let body: serde_json::Value = serde_json::from_slice(sent[0].body.as_ref().unwrap()).unwrap();
+    assert_eq!(body["variables"]["login"], login);
+    assert!(!body["query"].as_str().unwrap().contains("alice"));
+}
+
+#[test]
+fn issue_references_in_commit_messages() {
+    let message = "Retry jobs (#7), see https://github.com/octo-org/queue/issues/3#issuecomment-1\n\nFixes: #12, resolves other/lib#45\nCloses https://github.com/octo-org/demo/pull/9 &#38; #1a";
+
+    assert_eq!(
+        parse_references(message, "octo-org", "demo"),
+        [
+            (IssueRef::new("octo-org", "queue", 3), LinkKind::Mentions),
+            (IssueRef::new("octo-org", "demo", 9), LinkKind::Closes),
+            (IssueRef::new("octo-org", "demo", 7), LinkKind::Mentions),
+            (IssueRef::new("octo-org", "demo", 12), LinkKind::Closes),
+            (IssueRef::new("other", "lib", 45), LinkKind::Closes),
+        ]
+    );
+    assert!(parse_references("Prefix fixed in a1b2#3c and v1.2#4/5", "o", "r").is_empty());
+}
+
+#[test]
+fn change_types_from_subjects_and_labels() {
+    assert_eq!(change_type("feat(cli)!: add release notes"), Some("feat"));
+    assert_eq!(
+        change_type("Fix: retry on 502\n\nfeat: not this line"),
+        Some("fix")
+    );
+    assert_eq!(change_type("Retry failed jobs: with backoff"), None);
+    let labels = ["good first issue", "type: bug", "enhancement"].map(String::from);
+    assert_eq!(label_type(&labels), Some("fix"));
+    assert_eq!(label_type(&["question".to_string()]), None);
+}
+
+#[test]
+fn date_range_params() {
+    let range = DateRange::from_params(None, Some("2024-05-01"), Some("2024-05-07")).unwrap();
+    assert_eq!(range.since_str(), "2024-05-01T00:00:00Z");
+    assert_eq!(range.until_str(), "2024-05-07T23:59:59Z");
+    assert_eq!(range.period(), "the period from 2024-05-01 to 2024-05-07");
+    assert!(DateRange::from_params(Some("0"), None, None).is_err());
+    assert!(DateRange::from_params(None, Some("2024-05-07"), Some("2024-05-01")).is_err());
+    assert!(DateRange::from_params(None, Some("last week"), None).is_err());
+    assert!(DateRange::from_params(Some("14"), Some("2024-05-01"), None).is_err());
+}
+
+#[test]
+fn pipeline_config_overrides_only_the_keys_given() {
+    let config = PipelineConfig::from_toml(
+        r#"
+        [synthesis]
+        model = "gpt-4"
+        truncation = "middle"
+        "#,
+    )
+    .unwrap();
+    let default = PipelineConfig::default();
+
+    assert_eq!(config.synthesis.model, "gpt-4");
+    assert_eq!(config.synthesis.truncation, Truncation::Middle);
+    assert_eq!(
+        config.synthesis.max_output_tokens,
+        default.synthesis.max_output_tokens
+    );
+    assert_eq!(config.issue.model, default.issue.model);
+    assert!(PipelineConfig::from_toml("[synthesis]\nmodle = \"gpt-4\"").is_err());
+}
+
+#[test]
+fn cached_summaries_are_keyed_by_stage_and_model() {
+    let config = PipelineConfig::default();
+    let sha = "a1b2c3d4e5f60718293a4b5c6d7e8f9012345678";
+    let mut gpt4 = config.commit.clone();
+    gpt4.model = "gpt-4".to_string();
+
+    let builtin = PromptRegistry::builtin();
+    let reworded = builtin
+        .clone()
+        .with_toml("[commit_user]\nversion = 2\ntext = \"Summarize {{patch}}.\"")
+        .unwrap();
+    let names = ["commit_system", "commit_user"];
+    let hash = builtin.fingerprint(&names);
+
+    let key = commit_key(&config.commit, &hash, sha);
+    assert_eq!(
+        key,
+        commit_key(&config.commit, &builtin.fingerprint(&names), sha)
+    );
+    assert_ne!(key, commit_key(&config.commit_long, &hash, sha));
+    assert_ne!(key, commit_key(&gpt4, &hash, sha));
+    assert_ne!(
+        key,
+        commit_key(&config.commit, &reworded.fingerprint(&names), sha)
+    );
+}
+
+#[test]
+fn prompts_need_every_placeholder() {
+    let prompts = PromptRegistry::builtin();
+
+    let rendered = prompts
+        .render("readme_user", &[("readme", "Readme: # demo")])
+        .unwrap();
+    assert!(rendered.contains("Readme: # demo"));
+    assert!(prompts.render("readme_user", &[]).is_err());
+    assert!(prompts.render("no_such_prompt", &[]).is_err());
+    assert!(prompts
+        .clone()
+        .with_toml("[no_such_prompt]\nversion = 2\ntext = \"hi\"")
+        .is_err());
+}
+
+#[test]
+fn prompt_overrides_raise_the_version() {
+    let builtin = PromptRegistry::builtin();
+    let version = builtin.get("readme_user").unwrap().version;
+    let reworded = |version: u32| {
+        format!("[readme_user]\nversion = {version}\ntext = \"Describe {{{{readme}}}}.\"")
+    };
+
+    assert!(builtin.clone().with_toml(&reworded(version)).is_err());
+    let registry = builtin.clone().with_toml(&reworded(version + 1)).unwrap();
+    assert_eq!(registry.get("readme_user").unwrap().version, version + 1);
+    // The same text again is not a change.
+    assert!(registry.with_toml(&reworded(version + 1)).is_ok());
+}
+
+#[test]
+fn report_format_from_accept_header() {
+    assert_eq!(
+        ReportFormat::from_accept("text/html;q=0.9, application/json"),
+        Some(ReportFormat::Html)
+    );
+    assert_eq!(
+        ReportFormat::from_accept("image/png, text/markdown"),
+        Some(ReportFormat::Markdown)
+    );
+    assert_eq!(ReportFormat::from_accept("*/*"), None);
+}
@@ -0,0 +1,80 @@
+mod support;
+
+use futures::future::LocalBoxFuture;
+use github_analyzer::data_analyzers::process_commits;
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_data_fetchers::get_commits_in_range;
+use github_analyzer::kv_store::MemoryStore;
+use github_analyzer::llm::{ChatRequest, LlmClient};
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use support::{client, demo_range, demo_repo, OWNER, REPO};
+
+/// Takes longer the earlier a chat starts, so overlapping chats finish in
+/// reverse order, and remembers how many were ever in flight together.
+#[derive(Default)]
+struct SlowLlm {
+    started: Mutex<usize>,
+    in_flight: Mutex<usize>,
+    most_in_flight: Mutex<usize>,
+}
+
+impl LlmClient for SlowLlm {
+    fn chat<'a>(
+        &'a self,
+        _req: &'a ChatRequest,
+    ) -> LocalBoxFuture<'a, Result<String, AnalyzerError>> {
+        Box::pin(async move {
+            let n = {
+                let mut started = self.started.lock().unwrap();
+                *started += 1;
+                *started - 1
+            };
+            {
+                let mut in_flight = self.in_flight.lock().unwrap();
+                *in_flight += 1;
+                let mut most = self.most_in_flight.lock().unwrap();
+                *most = (*most).max(*in_flight);
+            }
+            tokio::time::sleep(Duration::from_millis(100 - 10 * n as u64)).await;
+            *self.in_flight.lock().unwrap() -= 1;
+            Ok(format!("chat {n}"))
+        })
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn summarizes_commits_concurrently_in_order() {
+    let mock = Arc::new(demo_repo());
+    let client = client(&mock);
+    let llm = SlowLlm::default();
+    let (_, mut commits, _) =
+        get_commits_in_range(&client, OWNER, REPO, None, &demo_range(), None, None)
+            .await
+            .unwrap();
+
+    let summaries = process_commits(
+        &client,
+        &llm,
+        &MemoryStore::new(),
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        &mut commits,
+        false,
+        false,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(*llm.most_in_flight.lock().unwrap(), 2);
+    let lines = summaries.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 2);
+    for (n, (line, commit)) in lines.iter().zip(&commits).enumerate() {
+        assert!(line.contains(&commit.source_url));
+        assert!(line.ends_with(&format!("chat {n}")));
+    }
+    assert_eq!(commits[0].payload, "chat 0");
+}
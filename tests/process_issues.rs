@@ -0,0 +1,143 @@
+mod support;
+
+use github_analyzer::data_analyzers::process_issues;
+use github_analyzer::github_client::GitHubClient;
+use github_analyzer::github_data_fetchers::{
+    get_issue_timeline, get_issue_timelines, get_issues_in_range,
+};
+use github_analyzer::issue_lifecycle::{IssueLifecycle, LifecycleEvent};
+use github_analyzer::kv_store::{KvStore, MemoryStore};
+use github_analyzer::llm::FakeLlm;
+use github_analyzer::octocrab_compat::{Event, Issue, IssueEvent};
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use std::collections::HashMap;
+use std::sync::Arc;
+use support::{client, demo_range, demo_repo, MockGitHub, OWNER, REPO};
+
+async fn issues(client: &GitHubClient) -> (Vec<Issue>, HashMap<u64, Vec<IssueEvent>>) {
+    let (_, issues) = get_issues_in_range(client, OWNER, REPO, None, &demo_range(), None)
+        .await
+        .unwrap();
+    let timelines = get_issue_timelines(client, OWNER, REPO, &issues).await;
+    (issues, timelines)
+}
+
+async fn process(
+    client: &GitHubClient,
+    llm: &FakeLlm,
+    store: &dyn KvStore,
+    (issues, timelines): (Vec<Issue>, HashMap<u64, Vec<IssueEvent>>),
+) -> (String, usize) {
+    let (summaries, count, _) = process_issues(
+        client,
+        llm,
+        store,
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        issues,
+        &timelines,
+        Some("alice".to_string()),
+        false,
+        true,
+        None,
+    )
+    .await
+    .unwrap();
+    (summaries, count)
+}
+
+#[tokio::test]
+async fn summarizes_an_issue_with_its_comments() {
+    let mock = Arc::new(demo_repo());
+    let client = client(&mock);
+    let llm = FakeLlm::with_replies(["Alice added retries, bob confirmed the fix."]);
+
+    let issues = issues(&client).await;
+    let (summaries, count) = process(&client, &llm, &MemoryStore::new(), issues).await;
+
+    assert_eq!(count, 1);
+    assert!(summaries.contains("https://api.github.com/repos/octo-org/demo/issues/12"));
+    assert!(summaries.contains("Alice added retries"));
+    let requests = llm.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].stage, "issue_long");
+    let prompt = &requests[0].messages.last().unwrap().content;
+    assert!(prompt.contains("labeled 'bug'"));
+    assert!(prompt.contains("bob commented: Fixed by #13"));
+    assert!(prompt.contains("Resolution: bob closed it via pull request #13 on 2024-05-03."));
+    assert!(prompt.contains("alice"));
+}
+
+#[tokio::test]
+async fn reuses_cached_summaries() {
+    let mock = Arc::new(demo_repo());
+    let client = client(&mock);
+    let store = MemoryStore::new();
+    let issues = issues(&client).await;
+
+    let first = process(&client, &FakeLlm::new(), &store, issues.clone()).await;
+    let fetched = mock.paths().len();
+    let llm = FakeLlm::new();
+    let second = process(&client, &llm, &store, issues).await;
+
+    assert_eq!(first, second);
+    assert!(llm.requests().is_empty());
+    assert_eq!(mock.paths().len(), fetched);
+}
+
+#[tokio::test]
+async fn summarizes_without_comments_when_they_cannot_be_fetched() {
+    let mock = Arc::new(MockGitHub::new().get(
+        "/search/issues",
+        include_str!("fixtures/search_issues.json"),
+    ));
+    let client = client(&mock);
+    let llm = FakeLlm::new();
+
+    let issues = issues(&client).await;
+    let (_, count) = process(&client, &llm, &MemoryStore::new(), issues).await;
+
+    assert_eq!(count, 1);
+    let requests = llm.requests();
+    let prompt = &requests[0].messages.last().unwrap().content;
+    assert!(!prompt.contains("commented"));
+}
+
+#[tokio::test]
+async fn reads_the_lifecycle_from_the_timeline() {
+    let mock = Arc::new(demo_repo());
+    let events = get_issue_timeline(&client(&mock), OWNER, REPO, 12)
+        .await
+        .unwrap();
+
+    let lifecycle = IssueLifecycle::from_events(&events);
+    let steps = lifecycle
+        .steps
+        .iter()
+        .map(|step| step.describe())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        steps,
+        [
+            "bob labeled it 'bug'",
+            "alice self-assigned it",
+            "alice referenced it from pull request #13 'Retry failed jobs with exponential backoff'",
+            "alice referenced it in commit a1b2c3d",
+            "bob closed it via pull request #13",
+        ]
+    );
+    assert!(matches!(
+        lifecycle.resolution().map(|step| &step.event),
+        Some(LifecycleEvent::Closed { pull: Some(13), .. })
+    ));
+
+    let mut reopened = events.last().unwrap().clone();
+    reopened.event = Some(Event::Reopened);
+    reopened.created_at += chrono::Duration::days(1);
+    let lifecycle = IssueLifecycle::from_events(&[events, vec![reopened]].concat());
+    assert!(lifecycle.resolution().is_none());
+    assert!(lifecycle
+        .describe()
+        .ends_with("It was reopened and is not resolved."));
+}
@@ -0,0 +1,109 @@
+mod support;
+
+use github_analyzer::github_data_fetchers::get_release_by_tag;
+use github_analyzer::kv_store::MemoryStore;
+use github_analyzer::llm::FakeLlm;
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use github_analyzer::release_notes::ReleaseNotes;
+use github_analyzer::reports::release_notes_report;
+use std::collections::HashMap;
+use std::sync::Arc;
+use support::{client, demo_repo, OWNER, REPO};
+
+#[tokio::test]
+async fn groups_the_changes_between_two_tags() {
+    let mock = Arc::new(demo_repo());
+    let llm = FakeLlm::new();
+
+    let notes = release_notes_report(
+        &client(&mock),
+        &llm,
+        &MemoryStore::new(),
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        OWNER,
+        REPO,
+        "v1.0.0",
+        "v1.1.0",
+    )
+    .await
+    .unwrap();
+
+    let headings = notes
+        .groups
+        .iter()
+        .map(|group| group.heading.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(headings, ["Features", "Documentation"]);
+    let feature = &notes.groups[0].entries[0];
+    assert_eq!(feature.reference, "#13");
+    assert_eq!(feature.url, "https://github.com/octo-org/demo/pull/13");
+    assert_eq!(feature.authors, ["alice"]);
+    assert!(feature.summary().is_some());
+    assert_eq!(notes.groups[1].entries[0].reference, "b2c3d4e");
+
+    let markdown = notes.render_markdown();
+    assert!(markdown.starts_with(
+        "## [v1.1.0](https://github.com/octo-org/demo/compare/v1.0.0...v1.1.0) - 2024-05-07\n"
+    ));
+    assert!(markdown.contains(
+        "- Retry failed jobs with exponential backoff ([#13](https://github.com/octo-org/demo/pull/13)) by @alice\n"
+    ));
+    assert_eq!(llm.requests().len(), 2);
+    assert!(!mock.paths().contains(&"/graphql".to_string()));
+}
+
+#[tokio::test]
+async fn links_to_the_enterprise_host_and_skips_deleted_authors() {
+    let mut pulls: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/commit_a1b2c3d_pulls.json")).unwrap();
+    pulls[0]["user"] = serde_json::Value::Null;
+    let mock = Arc::new(demo_repo().get(
+        "/repos/octo-org/demo/commits/a1b2c3d4e5f60718293a4b5c6d7e8f9012345678/pulls",
+        &pulls.to_string(),
+    ));
+    let client = client(&mock).with_web_url("https://ghe.example.com");
+
+    let notes = release_notes_report(
+        &client,
+        &FakeLlm::new(),
+        &MemoryStore::new(),
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        OWNER,
+        REPO,
+        "v1.0.0",
+        "v1.1.0",
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        notes.compare_url(),
+        "https://ghe.example.com/octo-org/demo/compare/v1.0.0...v1.1.0"
+    );
+    // The pull request's author is gone; the commit's author remains.
+    assert_eq!(notes.groups[0].entries[0].authors, ["alice"]);
+}
+
+#[tokio::test]
+async fn tags_without_a_release_have_none() {
+    let mock = Arc::new(demo_repo());
+
+    let release = get_release_by_tag(&client(&mock), OWNER, REPO, "v1.0.0")
+        .await
+        .unwrap();
+
+    assert!(release.is_none());
+}
+
+#[test]
+fn no_changes_without_commits() {
+    let notes = ReleaseNotes::new(OWNER, REPO, "v1.1.0", "v1.1.1", Vec::new(), &HashMap::new());
+
+    assert!(notes.groups.is_empty());
+    assert!(notes
+        .render_markdown()
+        .ends_with("No changes since v1.1.0.\n"));
+}
@@ -0,0 +1,257 @@
+//! Offline stand-ins shared by the tests. [`MockGitHub`] answers in place of
+//! api.github.com and github.com with the responses recorded under
+//! `tests/fixtures`, a week of activity in `octo-org/demo`.
+
+#![allow(dead_code)]
+
+use futures::future::LocalBoxFuture;
+use github_analyzer::date_range::DateRange;
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::github_client::GitHubClient;
+use github_analyzer::http::{HttpRequest, HttpResponse, HttpTransport, Method};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+pub const OWNER: &str = "octo-org";
+pub const REPO: &str = "demo";
+
+pub const SYNTHESIS_JSON: &str = r#"{"impactful": "Alice made failed jobs retry with backoff, fixing #12.", "alignment": "Reliability is the point of a job queue.", "patterns": ["Bugs are fixed within two days.", "Reviews ask for better logging."], "synergy": "Bob documented the retry settings Alice added.", "significance": null}"#;
+
+struct Route {
+    method: Method,
+    path: String,
+    body_contains: Option<String>,
+    response: HttpResponse,
+}
+
+/// Serves canned responses by method and path, ignoring host and query,
+/// and records every request. Later routes take precedence over earlier
+/// ones for the same request. Unknown paths get GitHub's 404.
+#[derive(Default)]
+pub struct MockGitHub {
+    routes: Vec<Route>,
+    requests: Mutex<Vec<HttpRequest>>,
+}
+
+impl MockGitHub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(self, path: &str, body: &str) -> Self {
+        self.respond(Method::Get, path, None, ok(body))
+    }
+
+    /// Answers GraphQL requests whose query contains `marker`.
+    pub fn graphql(self, marker: &str, body: &str) -> Self {
+        self.respond(Method::Post, "/graphql", Some(marker), ok(body))
+    }
+
+    pub fn respond(
+        mut self,
+        method: Method,
+        path: &str,
+        body_contains: Option<&str>,
+        response: HttpResponse,
+    ) -> Self {
+        self.routes.push(Route {
+            method,
+            path: path.to_string(),
+            body_contains: body_contains.map(str::to_string),
+            response,
+        });
+        self
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<HttpRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Paths requested so far, in order.
+    pub fn paths(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|req| path_of(&req.url).to_string())
+            .collect()
+    }
+}
+
+impl HttpTransport for MockGitHub {
+    fn send<'a>(
+        &'a self,
+        req: &'a HttpRequest,
+    ) -> LocalBoxFuture<'a, Result<HttpResponse, AnalyzerError>> {
+        self.requests.lock().unwrap().push(req.clone());
+        let body = String::from_utf8_lossy(req.body.as_deref().unwrap_or_default());
+        let route = self.routes.iter().rev().find(|route| {
+            route.method == req.method
+                && route.path == path_of(&req.url)
+                && route
+                    .body_contains
+                    .as_ref()
+                    .is_none_or(|marker| body.contains(marker.as_str()))
+        });
+        let res = match route {
+            Some(route) => route.response.clone(),
+            None => HttpResponse {
+                status: 404,
+                headers: Vec::new(),
+                body: br#"{"message": "Not Found"}"#.to_vec(),
+            },
+        };
+        Box::pin(async move { Ok(res) })
+    }
+}
+
+/// Answers every request with the next of its responses, repeating the
+/// last one once they run out, and counts the requests.
+pub struct Scripted {
+    responses: Mutex<VecDeque<HttpResponse>>,
+    sent: Mutex<usize>,
+}
+
+impl Scripted {
+    pub fn new(responses: impl IntoIterator<Item = HttpResponse>) -> Self {
+        Scripted {
+            responses: Mutex::new(responses.into_iter().collect()),
+            sent: Mutex::new(0),
+        }
+    }
+
+    pub fn sent(&self) -> usize {
+        *self.sent.lock().unwrap()
+    }
+}
+
+impl HttpTransport for Scripted {
+    fn send<'a>(
+        &'a self,
+        _req: &'a HttpRequest,
+    ) -> LocalBoxFuture<'a, Result<HttpResponse, AnalyzerError>> {
+        *self.sent.lock().unwrap() += 1;
+        let mut responses = self.responses.lock().unwrap();
+        let res = match responses.len() {
+            0 | 1 => responses.front().cloned().unwrap_or_default(),
+            _ => responses.pop_front().unwrap_or_default(),
+        };
+        Box::pin(async move { Ok(res) })
+    }
+}
+
+pub fn status(status: u16, headers: &[(&str, &str)]) -> HttpResponse {
+    HttpResponse {
+        status,
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: Vec::new(),
+    }
+}
+
+/// `/repos/o/r/readme` for `https://api.github.com/repos/o/r/readme?ref=main`.
+fn path_of(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.find('/').map_or("/", |slash| &rest[slash..]);
+    path.split(['?', '&']).next().unwrap_or(path)
+}
+
+pub fn ok(body: &str) -> HttpResponse {
+    HttpResponse {
+        status: 200,
+        headers: vec![("X-RateLimit-Remaining".to_string(), "4999".to_string())],
+        body: body.as_bytes().to_vec(),
+    }
+}
+
+/// A client talking to `mock`, failing at once instead of retrying.
+pub fn client(mock: &Arc<MockGitHub>) -> GitHubClient {
+    GitHubClient::new("test-token")
+        .with_transport(mock.clone())
+        .with_max_retries(0)
+}
+
+/// Every endpoint a weekly report of `octo-org/demo` reads.
+pub fn demo_repo() -> MockGitHub {
+    MockGitHub::new()
+        .get(
+            "/repos/octo-org/demo/community/profile",
+            include_str!("../fixtures/community_profile.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/readme",
+            include_str!("../fixtures/readme.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/commits",
+            include_str!("../fixtures/commits.json"),
+        )
+        .get(
+            "/octo-org/demo/commit/a1b2c3d4e5f60718293a4b5c6d7e8f9012345678.patch",
+            include_str!("../fixtures/commit_a1b2c3d.patch"),
+        )
+        .get(
+            "/octo-org/demo/commit/b2c3d4e5f60718293a4b5c6d7e8f901234567890.patch",
+            include_str!("../fixtures/commit_b2c3d4e.patch"),
+        )
+        .get(
+            "/search/issues",
+            include_str!("../fixtures/search_issues.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/issues/12/comments",
+            include_str!("../fixtures/issue_12_comments.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/issues/12/timeline",
+            include_str!("../fixtures/issue_12_timeline.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/commits/a1b2c3d4e5f60718293a4b5c6d7e8f9012345678/pulls",
+            include_str!("../fixtures/commit_a1b2c3d_pulls.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/commits/b2c3d4e5f60718293a4b5c6d7e8f901234567890/pulls",
+            "[]",
+        )
+        .get(
+            "/repos/octo-org/demo/releases/tags/v1.1.0",
+            include_str!("../fixtures/release_v1.1.0.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/compare/v1.0.0...v1.1.0",
+            include_str!("../fixtures/compare_v1.0.0...v1.1.0.json"),
+        )
+        .graphql(
+            "... on PullRequest",
+            include_str!("../fixtures/graphql_pull_requests.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/pulls/13",
+            include_str!("../fixtures/pull_13.diff"),
+        )
+        .get(
+            "/repos/octo-org/demo/issues/13/comments",
+            include_str!("../fixtures/pull_13_comments.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/pulls/13/reviews",
+            include_str!("../fixtures/pull_13_reviews.json"),
+        )
+        .get(
+            "/repos/octo-org/demo/pulls/13/comments",
+            include_str!("../fixtures/pull_13_review_comments.json"),
+        )
+        .graphql(
+            "... on Discussion",
+            include_str!("../fixtures/graphql_discussions.json"),
+        )
+}
+
+/// The week the fixtures were recorded in.
+pub fn demo_range() -> DateRange {
+    DateRange::from_params(None, Some("2024-05-01"), Some("2024-05-07")).unwrap()
+}
@@ -0,0 +1,264 @@
+mod support;
+
+use github_analyzer::error::AnalyzerError;
+use github_analyzer::http::{HttpResponse, Method};
+use github_analyzer::kv_store::MemoryStore;
+use github_analyzer::llm::FakeLlm;
+use github_analyzer::notifier::LogNotifier;
+use github_analyzer::pipeline_config::PipelineConfig;
+use github_analyzer::prompts::PromptRegistry;
+use github_analyzer::report::Report;
+use github_analyzer::reports::weekly_report;
+use std::sync::Arc;
+use support::{client, demo_range, demo_repo, ok, MockGitHub, OWNER, REPO, SYNTHESIS_JSON};
+
+async fn run(
+    mock: &Arc<MockGitHub>,
+    llm: &FakeLlm,
+    user_name: Option<&str>,
+) -> Result<Report, AnalyzerError> {
+    weekly_report(
+        &client(mock),
+        llm,
+        &MemoryStore::new(),
+        &LogNotifier,
+        &PipelineConfig::default(),
+        &PromptRegistry::builtin(),
+        OWNER,
+        REPO,
+        user_name.map(String::from),
+        &demo_range(),
+        None,
+        None,
+    )
+    .await
+}
+
+fn section_names(report: &Report) -> Vec<&str> {
+    report.sections.iter().map(|s| s.name.as_str()).collect()
+}
+
+#[tokio::test]
+async fn reports_every_kind_of_activity() {
+    let mock = Arc::new(demo_repo());
+    let llm = FakeLlm::new()
+        .with_stage_reply("readme", "A job queue backed by SQLite.")
+        .with_stage_reply("synthesis", SYNTHESIS_JSON);
+
+    let report = run(&mock, &llm, None).await.unwrap();
+
+    assert_eq!(
+        section_names(&report),
+        [
+            "Commits",
+            "Issues",
+            "Pull requests",
+            "Reviews",
+            "Discussions"
+        ]
+    );
+    assert_eq!(report.stats.commits, 2);
+    assert_eq!(report.stats.issues, 1);
+    assert_eq!(report.stats.pull_requests, 1);
+    assert_eq!(report.stats.reviews, 1);
+    assert_eq!(report.stats.discussions, 1);
+    assert_eq!(
+        report.about.as_deref(),
+        Some("A job queue backed by SQLite.")
+    );
+    for section in &report.sections[..3] {
+        assert!(section.items.iter().all(|item| item.summary.is_some()));
+    }
+    assert_eq!(
+        report.sections[4].items[0].url,
+        "https://github.com/octo-org/demo/discussions/14"
+    );
+
+    let synthesis = report.synthesis.as_ref().unwrap();
+    assert_eq!(
+        synthesis.patterns,
+        "Bugs are fixed within two days. Reviews ask for better logging."
+    );
+    assert!(synthesis.significance.is_empty());
+    assert!(report.synthesis_text.is_none());
+    assert!(report.usage.total_tokens() > 0);
+
+    let paths = mock.paths();
+    assert!(paths.contains(
+        &"/octo-org/demo/commit/a1b2c3d4e5f60718293a4b5c6d7e8f9012345678.patch".to_string()
+    ));
+    let diff_request = mock
+        .requests()
+        .into_iter()
+        .find(|req| req.url.ends_with("/repos/octo-org/demo/pulls/13"))
+        .unwrap();
+    assert!(diff_request.headers.contains(&(
+        "Accept".to_string(),
+        "application/vnd.github.diff".to_string()
+    )));
+    let timeline_fetches = paths
+        .iter()
+        .filter(|path| path.ends_with("/issues/12/timeline"))
+        .count();
+    assert_eq!(timeline_fetches, 1);
+    assert!(report
+        .render_markdown()
+        .contains("Jobs that fail once are never retried"));
+
+    let links = report
+        .links
+        .iter()
+        .map(|link| link.describe())
+        .collect::<Vec<String>>();
+    assert_eq!(
+        links,
+        [
+            "commit a1b2c3d by alice closes octo-org/demo#12 (from its text)",
+            "pull request octo-org/demo#13 by alice closes octo-org/demo#12 (from the issue timeline)",
+        ]
+    );
+    let requests = llm.requests();
+    let synthesis_prompt = &requests
+        .iter()
+        .find(|req| req.stage == "synthesis")
+        .unwrap()
+        .messages[1]
+        .content;
+    assert!(synthesis_prompt.contains("treat them as facts: commit a1b2c3d by alice closes"));
+}
+
+#[tokio::test]
+async fn reads_every_page_of_pull_request_comments() {
+    let mut first_page = ok(include_str!("fixtures/pull_13_comments.json"));
+    first_page.headers.push((
+        "Link".to_string(),
+        r#"<https://api.github.com/repositories/1/issues/13/comments?page=2>; rel="next""#
+            .to_string(),
+    ));
+    let mut second_page: serde_json::Value =
+        serde_json::from_str(include_str!("fixtures/pull_13_comments.json")).unwrap();
+    second_page[0]["body"] = "Found on the second page.".into();
+    let mock = Arc::new(
+        demo_repo()
+            .respond(
+                Method::Get,
+                "/repos/octo-org/demo/issues/13/comments",
+                None,
+                first_page,
+            )
+            .get(
+                "/repositories/1/issues/13/comments",
+                &second_page.to_string(),
+            ),
+    );
+    let llm = FakeLlm::new().with_stage_reply("synthesis", SYNTHESIS_JSON);
+
+    run(&mock, &llm, None).await.unwrap();
+
+    assert!(llm
+        .requests()
+        .iter()
+        .filter(|req| req.stage.starts_with("pull_request"))
+        .any(|req| req
+            .messages
+            .iter()
+            .any(|m| m.content.contains("Found on the second page."))));
+}
+
+#[tokio::test]
+async fn narrows_the_report_to_one_contributor() {
+    let mock = Arc::new(demo_repo());
+    let llm = FakeLlm::new().with_stage_reply("synthesis", SYNTHESIS_JSON);
+
+    let report = run(&mock, &llm, Some("alice")).await.unwrap();
+
+    assert_eq!(report.stats.commits, 1);
+    assert_eq!(report.sections[0].items[0].author, "alice");
+    // Only bob reviewed #13.
+    assert!(!section_names(&report).contains(&"Reviews"));
+    assert!(llm
+        .requests()
+        .iter()
+        .filter(|req| req.stage == "synthesis")
+        .all(|req| req.messages.iter().any(|m| m.content.contains("alice"))));
+}
+
+#[tokio::test]
+async fn keeps_the_raw_synthesis_when_it_is_not_json() {
+    let mock = Arc::new(demo_repo());
+    let llm = FakeLlm::new();
+
+    let report = run(&mock, &llm, None).await.unwrap();
+
+    assert!(report.synthesis.is_none());
+    assert!(report.synthesis_text.is_some());
+    assert!(llm
+        .requests()
+        .iter()
+        .any(|req| req.chat_id == "synthesis-repair"));
+}
+
+#[tokio::test]
+async fn fails_for_an_unknown_repository() {
+    let mock = Arc::new(MockGitHub::new());
+
+    let res = run(&mock, &FakeLlm::new(), None).await;
+
+    assert!(matches!(res, Err(AnalyzerError::NotFound(_))));
+}
+
+#[tokio::test]
+async fn leaves_out_the_sources_that_fail() {
+    let unavailable = HttpResponse {
+        status: 500,
+        headers: Vec::new(),
+        body: br#"{"message": "Server Error"}"#.to_vec(),
+    };
+    let mock = Arc::new(
+        demo_repo()
+            .respond(
+                Method::Get,
+                "/repos/octo-org/demo/commits",
+                None,
+                unavailable.clone(),
+            )
+            .respond(
+                Method::Get,
+                "/repos/octo-org/demo/pulls/13/reviews",
+                None,
+                unavailable,
+            ),
+    );
+    let llm = FakeLlm::new().with_stage_reply("synthesis", SYNTHESIS_JSON);
+
+    let report = run(&mock, &llm, None).await.unwrap();
+
+    // The pull request whose reviews fail is skipped, not the whole source.
+    assert_eq!(
+        section_names(&report),
+        ["Issues", "Pull requests", "Discussions"]
+    );
+    assert_eq!(report.stats.commits, 0);
+    assert_eq!(report.notes.len(), 1);
+    assert!(report.notes[0].starts_with("Commits could not be fetched"));
+    assert!(report.synthesis.is_some());
+}
+
+#[tokio::test]
+async fn fails_when_every_source_fails() {
+    let mock = Arc::new(
+        MockGitHub::new()
+            .get(
+                "/repos/octo-org/demo/community/profile",
+                include_str!("fixtures/community_profile.json"),
+            )
+            .get(
+                "/repos/octo-org/demo/readme",
+                include_str!("fixtures/readme.json"),
+            ),
+    );
+
+    let res = run(&mock, &FakeLlm::new(), None).await;
+
+    assert!(matches!(res, Err(AnalyzerError::NotFound(_))));
+}